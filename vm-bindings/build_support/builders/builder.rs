@@ -327,6 +327,19 @@ pub trait Builder: Debug {
             .allowlist_function("exportStatScavengeGCUsecs")
             .allowlist_function("exportClassOrNilAtIndex")
             .allowlist_function("exportIsOopForwarded")
+            .allowlist_function("exportGetMemoryMap")
+            .allowlist_function("exportGetEndOfMemory")
+            .allowlist_function("exportGetPermSpaceFreeStart")
+            .allowlist_function("exportGetEdenStart")
+            .allowlist_function("exportGetFreeStart")
+            .allowlist_function("exportGetPastSpaceBase")
+            .allowlist_function("exportGetPastSpaceStart")
+            .allowlist_function("exportGetSchedulerPointer")
+            .allowlist_function("exportContextInstVarOf")
+            .allowlist_function("exportStatCodeCompactionCount")
+            .allowlist_function("exportStatCodeCompactionUsecs")
+            .allowlist_function("exportGetCogCodeSize")
+            .allowlist_function("exportGetMethodZoneUsedBytes")
             .allowlist_function("setVmRunOnWorkerThread")
             .allowlist_function("setLogger")
             .allowlist_function("setShouldLog")
@@ -378,6 +391,7 @@ pub trait Builder: Debug {
             .allowlist_type("usqInt")
             .allowlist_type("sqExport")
             .allowlist_type("VirtualMachine")
+            .allowlist_type("VMMemoryMap")
             .header(
                 include_dir
                     .join("pharovm")
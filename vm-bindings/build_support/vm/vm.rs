@@ -130,17 +130,25 @@ impl VirtualMachine {
 
     /// Return the name of the interpreter that should be generated.
     /// This basically boils down to choosing between faster JIT-enabled or
-    /// slower stack interpreter
+    /// slower stack interpreter. Only the interpreter with telemetry emits
+    /// the message send, return and primitive signals, the default one emits process switches
     fn interpreter(_builder: Rc<dyn Builder>) -> &'static str {
-        if cfg!(feature = "jit") {
+        if cfg!(feature = "telemetry") {
+            "CoInterpreterWithTelemetry"
+        } else if cfg!(feature = "jit") {
             "CoInterpreterWithProcessSwitchTelemetry"
         } else {
             "StackVM"
         }
     }
 
+    /// The jit with telemetry calls a trampoline on every send and return of machine code methods
     fn cogit_compiler(_builder: Rc<dyn Builder>) -> &'static str {
-        "StackToRegisterMappingCogitWithProcessSwitchTelemetry"
+        if cfg!(feature = "telemetry") {
+            "StackToRegisterMappingCogitWithTelemetry"
+        } else {
+            "StackToRegisterMappingCogitWithProcessSwitchTelemetry"
+        }
     }
 
     fn interpreter_sources() -> Vec<&'static str> {
@@ -31,3 +31,72 @@ usqLong exportStatScavengeGCUsecs() {
 sqInt exportClassOrNilAtIndex(sqInt classIndex) {
     return classOrNilAtIndex(classIndex);
 }
+
+VMMemoryMap* exportGetMemoryMap() {
+    return getMemoryMap();
+}
+
+usqInt exportGetEndOfMemory() {
+    return getEndOfMemory();
+}
+
+usqInt exportGetPermSpaceFreeStart() {
+    return getPermSpaceFreeStart();
+}
+
+usqInt exportGetEdenStart() {
+    return getEdenStart();
+}
+
+usqInt exportGetFreeStart() {
+    return getFreeStart();
+}
+
+usqInt exportGetPastSpaceBase() {
+    return getPastSpaceBase();
+}
+
+usqInt exportGetPastSpaceStart() {
+    return getPastSpaceStart();
+}
+
+sqInt exportGetSchedulerPointer() {
+    return getSchedulerPointer();
+}
+
+sqInt exportContextInstVarOf(sqInt index, sqInt aContext) {
+    return contextInstVarof(index, aContext);
+}
+
+// the code zone only exists in the vm with the jit
+usqLong exportStatCodeCompactionCount() {
+#if COGVM
+    return getStatCodeCompactionCount();
+#else
+    return 0;
+#endif
+}
+
+usqLong exportStatCodeCompactionUsecs() {
+#if COGVM
+    return getStatCodeCompactionUsecs();
+#else
+    return 0;
+#endif
+}
+
+usqInt exportGetCogCodeSize() {
+#if COGVM
+    return getCogCodeSize();
+#else
+    return 0;
+#endif
+}
+
+usqInt exportGetMethodZoneUsedBytes() {
+#if COGVM
+    return getMethodZoneUsedBytes();
+#else
+    return 0;
+#endif
+}
@@ -1,6 +1,7 @@
 #include "sq.h"
 #include "exportDefinition.h"
 #include "sqVirtualMachine.h"
+#include "vmMemoryMap.h"
 
 extern void* getHandler(sqInt anOop);
 extern void* readAddress(sqInt anExternalAddress);
@@ -9,6 +10,21 @@ extern int osCogStackPageHeadroom();
 extern usqLong getStatFullGCUsecs();
 extern usqLong getStatScavengeGCUsecs();
 extern sqInt classOrNilAtIndex(sqInt classIndex);
+extern VMMemoryMap* getMemoryMap();
+extern usqInt getEndOfMemory();
+extern usqInt getPermSpaceFreeStart();
+extern usqInt getEdenStart();
+extern usqInt getFreeStart();
+extern usqInt getPastSpaceBase();
+extern usqInt getPastSpaceStart();
+extern sqInt getSchedulerPointer();
+extern sqInt contextInstVarof(sqInt index, sqInt aContext);
+#if COGVM
+extern sqInt getStatCodeCompactionCount();
+extern sqInt getStatCodeCompactionUsecs();
+extern sqInt getCogCodeSize();
+extern usqInt getMethodZoneUsedBytes();
+#endif
 
 EXPORT(void*) exportGetHandler(sqInt anOop);
 EXPORT(void*) exportReadAddress(sqInt anExternalAddress);
@@ -17,6 +33,19 @@ EXPORT(VirtualMachine*) exportSqGetInterpreterProxy();
 EXPORT(usqLong) exportStatFullGCUsecs();
 EXPORT(usqLong) exportStatScavengeGCUsecs();
 EXPORT(sqInt) exportClassOrNilAtIndex(sqInt classIndex);
+EXPORT(VMMemoryMap*) exportGetMemoryMap();
+EXPORT(usqInt) exportGetEndOfMemory();
+EXPORT(usqInt) exportGetPermSpaceFreeStart();
+EXPORT(usqInt) exportGetEdenStart();
+EXPORT(usqInt) exportGetFreeStart();
+EXPORT(usqInt) exportGetPastSpaceBase();
+EXPORT(usqInt) exportGetPastSpaceStart();
+EXPORT(sqInt) exportGetSchedulerPointer();
+EXPORT(sqInt) exportContextInstVarOf(sqInt index, sqInt aContext);
+EXPORT(usqLong) exportStatCodeCompactionCount();
+EXPORT(usqLong) exportStatCodeCompactionUsecs();
+EXPORT(usqInt) exportGetCogCodeSize();
+EXPORT(usqInt) exportGetMethodZoneUsedBytes();
 
 // Custom
 EXPORT(sqInt) createNewMethodheaderbytecodeCount(sqInt class, sqInt header, sqInt bytecodeCount);
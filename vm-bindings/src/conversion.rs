@@ -0,0 +1,613 @@
+use crate::bindings::exportSqGetInterpreterProxy as sqGetInterpreterProxy;
+use crate::{InterpreterProxy, ObjectPointer, Smalltalk, StringClasses, StringKind};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem::size_of;
+use std::os::raw::c_void;
+use vm_object_model::{
+    AnyObjectRef, ByteArrayRef, ByteStringRef, Immediate, ObjectFormat, ObjectRef,
+    RawObjectPointer, WideStringRef, WordArrayRef,
+};
+
+/// Converts a Rust value into a Smalltalk object.
+///
+/// Numbers become SmallIntegers and SmallFloats when they fit, strings become ByteStrings or WideStrings,
+/// `None` becomes nil and raw pointers become ExternalAddresses.
+/// Collections, tuples and structs (see the `ToSmalltalk` derive) become Arrays,
+/// while a `HashMap` becomes an Array of key-value pairs.
+/// The conversion fails if an object can not be allocated.
+pub trait ToSmalltalk {
+    fn to_smalltalk(&self) -> Result<ObjectPointer>;
+}
+
+/// Converts a Smalltalk object into a Rust value, the reverse of `ToSmalltalk`
+pub trait FromSmalltalk: Sized {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self>;
+}
+
+fn proxy() -> &'static InterpreterProxy {
+    unsafe { InterpreterProxy::from_native_ref(&*sqGetInterpreterProxy()) }
+}
+
+fn to_any_object_ref(object: ObjectPointer) -> AnyObjectRef {
+    AnyObjectRef::from(RawObjectPointer::from(object.as_i64()))
+}
+
+fn to_object_pointer(object: impl Into<AnyObjectRef>) -> ObjectPointer {
+    ObjectPointer::from(object.into().as_i64())
+}
+
+/// The vm answers a null pointer when it fails to allocate an object
+fn allocated(object: ObjectPointer, description: &str) -> Result<ObjectPointer> {
+    if object.as_i64() == 0 {
+        bail!("Failed to allocate {}", description);
+    }
+    Ok(object)
+}
+
+impl Smalltalk {
+    /// Return a value converted to a Smalltalk object from the primitive,
+    /// or fail the primitive if the object can not be allocated
+    pub fn method_return<T: ToSmalltalk + ?Sized>(value: &T) {
+        match value.to_smalltalk() {
+            Ok(object) => Self::method_return_value(object),
+            Err(error) => {
+                error!("Failed to convert the result of a primitive: {}", error);
+                Self::primitive_fail();
+            }
+        }
+    }
+}
+
+/// Allocate an Array with the converted items
+pub fn new_array_of(items: &[&dyn ToSmalltalk]) -> Result<ObjectPointer> {
+    new_array_with(items.len(), |index| items[index].to_smalltalk())
+}
+
+/// Allocate an Array of a given size and fill it with the results of a block evaluated for each index.
+/// The array is allocated before the items
+fn new_array_with(
+    size: usize,
+    item_at: impl Fn(usize) -> Result<ObjectPointer>,
+) -> Result<ObjectPointer> {
+    let array = allocated(
+        Smalltalk::primitive_instantiate_indexable_class_of_size(
+            Smalltalk::primitive_class_array(),
+            size,
+        ),
+        "an Array",
+    )?;
+    let mut array_ref = to_any_object_ref(array).as_object()?;
+    for index in 0..size {
+        let item = item_at(index)?;
+        Smalltalk::prepare_to_store(array, item);
+        array_ref.inst_var_at_put(index, to_any_object_ref(item))?;
+    }
+    Ok(array)
+}
+
+/// Return the items of an Array, checking the amount of items if an expected size is given
+pub fn array_items(
+    object: ObjectPointer,
+    expected_size: Option<usize>,
+) -> Result<Vec<ObjectPointer>> {
+    let array = to_any_object_ref(object).as_object()?.follow_forwarded();
+    if array.object_format() != ObjectFormat::IndexableWithoutInstVars {
+        bail!("Expected an Array, got {:?}", array.object_format());
+    }
+
+    let size = array.amount_of_slots();
+    if let Some(expected_size) = expected_size {
+        if size != expected_size {
+            bail!("Expected an Array of {} items, got {}", expected_size, size);
+        }
+    }
+
+    Ok((0..size)
+        .filter_map(|index| array.inst_var_at(index))
+        .map(to_object_pointer)
+        .collect())
+}
+
+fn is_nil(object: ObjectPointer) -> bool {
+    object == Smalltalk::nil_object()
+}
+
+fn class_index_of(object: AnyObjectRef) -> Result<u32> {
+    Ok(object
+        .as_object()?
+        .follow_forwarded()
+        .header()
+        .class_index())
+}
+
+/// Return the magnitude and the sign of a LargePositiveInteger or a LargeNegativeInteger,
+/// failing if the magnitude does not fit in 64 bits.
+/// The object is decoded here rather than by the interpreter proxy,
+/// because the proxy reports a failure through the failure code of the running primitive
+fn large_integer_value(object: ObjectPointer) -> Result<(u64, bool)> {
+    let any_object = to_any_object_ref(object);
+    let is_negative = match class_index_of(any_object)? {
+        Smalltalk::LARGE_POSITIVE_INTEGER_CLASS_INDEX => false,
+        Smalltalk::LARGE_NEGATIVE_INTEGER_CLASS_INDEX => true,
+        _ => bail!("Expected an Integer, got {:?}", object),
+    };
+
+    // the magnitude is stored as little-endian bytes
+    let bytes = ByteArrayRef::try_from(any_object)?;
+    let magnitude = bytes.as_slice();
+    let length = magnitude
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |index| index + 1);
+    if length > size_of::<u64>() {
+        bail!(
+            "Expected an Integer in the range of 64 bits, got {:?}",
+            object
+        );
+    }
+
+    let magnitude = magnitude[..length]
+        .iter()
+        .rev()
+        .fold(0u64, |value, byte| (value << 8) | *byte as u64);
+    Ok((magnitude, is_negative))
+}
+
+impl ToSmalltalk for ObjectPointer {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        Ok(*self)
+    }
+}
+
+impl FromSmalltalk for ObjectPointer {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        Ok(object)
+    }
+}
+
+impl<T: ToSmalltalk + ?Sized> ToSmalltalk for &T {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        (**self).to_smalltalk()
+    }
+}
+
+impl ToSmalltalk for bool {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        Ok(Smalltalk::primitive_bool_object(*self))
+    }
+}
+
+impl FromSmalltalk for bool {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        if object == Smalltalk::true_object() {
+            Ok(true)
+        } else if object == Smalltalk::false_object() {
+            Ok(false)
+        } else {
+            bail!("Expected a Boolean, got {:?}", object)
+        }
+    }
+}
+
+impl ToSmalltalk for i64 {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        if Immediate::is_small_integer_value(*self) {
+            Ok(to_object_pointer(Immediate::new_i64(*self)))
+        } else {
+            allocated(proxy().new_signed_64bit_integer(*self), "a LargeInteger")
+        }
+    }
+}
+
+impl FromSmalltalk for i64 {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        if let Some(integer) = to_any_object_ref(object).as_integer() {
+            return Ok(integer);
+        }
+
+        let (magnitude, is_negative) = large_integer_value(object)?;
+        let integer = if is_negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        };
+        integer.ok_or_else(|| {
+            anyhow::anyhow!("Expected an Integer in the range of i64, got {:?}", object)
+        })
+    }
+}
+
+impl ToSmalltalk for u64 {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        match i64::try_from(*self) {
+            Ok(integer) if Immediate::is_small_integer_value(integer) => integer.to_smalltalk(),
+            _ => allocated(
+                proxy().new_positive_64bit_integer(*self),
+                "a LargePositiveInteger",
+            ),
+        }
+    }
+}
+
+impl FromSmalltalk for u64 {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        if let Some(integer) = to_any_object_ref(object).as_integer() {
+            return Ok(u64::try_from(integer)?);
+        }
+
+        match large_integer_value(object)? {
+            (magnitude, false) => Ok(magnitude),
+            (_, true) => bail!("Expected an Integer in the range of u64, got {:?}", object),
+        }
+    }
+}
+
+/// Integers that are converted through i64 or u64
+macro_rules! integer_conversions {
+    ($via:ty => $($integer:ty),*) => {
+        $(
+            impl ToSmalltalk for $integer {
+                fn to_smalltalk(&self) -> Result<ObjectPointer> {
+                    (*self as $via).to_smalltalk()
+                }
+            }
+
+            impl FromSmalltalk for $integer {
+                fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+                    Ok(<$integer>::try_from(<$via>::from_smalltalk(object)?)?)
+                }
+            }
+        )*
+    };
+}
+
+integer_conversions!(i64 => i8, i16, i32, isize);
+integer_conversions!(u64 => u8, u16, u32, usize);
+
+impl ToSmalltalk for f64 {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        match Immediate::try_new_float(*self) {
+            Some(immediate) => Ok(to_object_pointer(immediate)),
+            None => allocated(proxy().new_float(*self), "a BoxedFloat64"),
+        }
+    }
+}
+
+impl FromSmalltalk for f64 {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        if let Ok(immediate) = to_any_object_ref(object).as_immediate() {
+            return immediate
+                .as_float()
+                .ok_or_else(|| anyhow::anyhow!("Expected a Float, got {:?}", immediate));
+        }
+
+        let any_object = to_any_object_ref(object);
+        if class_index_of(any_object)? != Smalltalk::BOXED_FLOAT_CLASS_INDEX {
+            bail!("Expected a Float, got {:?}", object);
+        }
+
+        // the 8 bytes of the float are stored as two 32-bit words in platform order
+        let words = WordArrayRef::try_from(any_object)?;
+        if words.len() != 2 {
+            bail!("Expected a Float, got {:?}", object);
+        }
+        let mut bytes = [0u8; 8];
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(words.iter()) {
+            chunk.copy_from_slice(&word.to_ne_bytes());
+        }
+        Ok(f64::from_ne_bytes(bytes))
+    }
+}
+
+impl ToSmalltalk for f32 {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        (*self as f64).to_smalltalk()
+    }
+}
+
+impl FromSmalltalk for f32 {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        Ok(f64::from_smalltalk(object)? as f32)
+    }
+}
+
+/// Strings become ByteStrings if all characters fit in Latin-1, otherwise WideStrings.
+/// Fails if the string classes can not be found
+impl ToSmalltalk for str {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        let string = StringClasses::of_image()?.new_string(self)?;
+        Ok(to_object_pointer(string))
+    }
+}
+
+impl ToSmalltalk for String {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        self.as_str().to_smalltalk()
+    }
+}
+
+/// Read a ByteString (Latin-1) or a WideString (UTF-32), including symbols
+impl FromSmalltalk for String {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        let any_object = to_any_object_ref(object);
+        let Some(kind) = StringKind::of(any_object) else {
+            bail!("Expected a String, got {:?}", object);
+        };
+
+        if kind.is_wide() {
+            Ok(WideStringRef::try_from(any_object)?.chars_lossy().collect())
+        } else {
+            Ok(ByteStringRef::try_from(any_object)?.chars().collect())
+        }
+    }
+}
+
+impl<T: ToSmalltalk> ToSmalltalk for Option<T> {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        match self {
+            Some(value) => value.to_smalltalk(),
+            None => Ok(Smalltalk::nil_object()),
+        }
+    }
+}
+
+impl<T: FromSmalltalk> FromSmalltalk for Option<T> {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        if is_nil(object) {
+            Ok(None)
+        } else {
+            Ok(Some(T::from_smalltalk(object)?))
+        }
+    }
+}
+
+impl<T: ToSmalltalk> ToSmalltalk for [T] {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        new_array_with(self.len(), |index| self[index].to_smalltalk())
+    }
+}
+
+impl<T: ToSmalltalk> ToSmalltalk for Vec<T> {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        self.as_slice().to_smalltalk()
+    }
+}
+
+impl<T: FromSmalltalk> FromSmalltalk for Vec<T> {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        array_items(object, None)?
+            .into_iter()
+            .map(T::from_smalltalk)
+            .collect()
+    }
+}
+
+/// A map is an Array of two-item Arrays, a key followed by its value
+impl<K: ToSmalltalk, V: ToSmalltalk, S> ToSmalltalk for HashMap<K, V, S> {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        let pairs = self.iter().collect::<Vec<_>>();
+        new_array_with(pairs.len(), |index| {
+            let (key, value) = pairs[index];
+            new_array_of(&[key, value])
+        })
+    }
+}
+
+impl<K: FromSmalltalk + Eq + Hash, V: FromSmalltalk> FromSmalltalk for HashMap<K, V> {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        array_items(object, None)?
+            .into_iter()
+            .map(<(K, V)>::from_smalltalk)
+            .collect()
+    }
+}
+
+/// Tuples are Arrays with an item per element
+macro_rules! tuple_conversions {
+    ($size:literal => $($element:ident $index:tt),+) => {
+        impl<$($element: ToSmalltalk),+> ToSmalltalk for ($($element,)+) {
+            fn to_smalltalk(&self) -> Result<ObjectPointer> {
+                new_array_of(&[$(&self.$index),+])
+            }
+        }
+
+        impl<$($element: FromSmalltalk),+> FromSmalltalk for ($($element,)+) {
+            fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+                let items = array_items(object, Some($size))?;
+                Ok(($($element::from_smalltalk(items[$index])?,)+))
+            }
+        }
+    };
+}
+
+tuple_conversions!(1 => A 0);
+tuple_conversions!(2 => A 0, B 1);
+tuple_conversions!(3 => A 0, B 1, C 2);
+tuple_conversions!(4 => A 0, B 1, C 2, D 3);
+tuple_conversions!(5 => A 0, B 1, C 2, D 3, E 4);
+tuple_conversions!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// Allocate an ExternalAddress with the address in platform order
+impl<T> ToSmalltalk for *const T {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        let address = (*self as usize).to_ne_bytes();
+        let external_address = allocated(
+            Smalltalk::primitive_instantiate_indexable_class_of_size(
+                Smalltalk::class_external_address(),
+                address.len(),
+            ),
+            "an ExternalAddress",
+        )?;
+        ByteArrayRef::try_from(to_any_object_ref(external_address))?
+            .as_slice_mut()?
+            .copy_from_slice(&address);
+        Ok(external_address)
+    }
+}
+
+impl<T> ToSmalltalk for *mut T {
+    fn to_smalltalk(&self) -> Result<ObjectPointer> {
+        (*self as *const T).to_smalltalk()
+    }
+}
+
+/// Read the address stored in an ExternalAddress
+impl<T> FromSmalltalk for *const T {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        let any_object = to_any_object_ref(object);
+        let is_external_address = Smalltalk::class_of(any_object).is_some_and(|class| {
+            AnyObjectRef::from(class).as_i64() == Smalltalk::class_external_address().as_i64()
+        });
+        if !is_external_address {
+            bail!("Expected an ExternalAddress, got {:?}", object);
+        }
+
+        let bytes = ByteArrayRef::try_from(any_object)?;
+        let address: [u8; size_of::<*const c_void>()] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Expected an ExternalAddress, got {:?}", object))?;
+        Ok(usize::from_ne_bytes(address) as *const T)
+    }
+}
+
+impl<T> FromSmalltalk for *mut T {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        Ok(<*const T>::from_smalltalk(object)? as *mut T)
+    }
+}
+
+impl FromSmalltalk for ObjectRef {
+    fn from_smalltalk(object: ObjectPointer) -> Result<Self> {
+        Ok(to_any_object_ref(object).as_object()?)
+    }
+}
+
+#[cfg(all(test, feature = "test_heap"))]
+mod tests {
+    use super::*;
+    use crate::with_object_memory;
+    use std::rc::Rc;
+    use vm_object_model::TestHeap;
+
+    fn with_test_heap(block: impl FnOnce(&TestHeap)) {
+        let heap = Rc::new(TestHeap::new());
+        with_object_memory(heap.clone(), || block(&heap))
+    }
+
+    #[test]
+    fn round_trip_immediates_and_arrays() {
+        with_test_heap(|_heap| {
+            let value = (vec![Some(1i64), None, Some(-3)], true, (42u8, 0.5f64));
+            let object = value.to_smalltalk().unwrap();
+            assert_eq!(
+                <(Vec<Option<i64>>, bool, (u8, f64))>::from_smalltalk(object).unwrap(),
+                value
+            );
+
+            assert!(u8::from_smalltalk(300i64.to_smalltalk().unwrap()).is_err());
+            assert!(<(bool, bool)>::from_smalltalk(vec![true].to_smalltalk().unwrap()).is_err());
+        })
+    }
+
+    #[test]
+    fn read_strings_and_maps() {
+        with_test_heap(|heap| {
+            let string_class =
+                heap.new_named_class("ByteString", None, ObjectFormat::Indexable8(16), &[]);
+            let byte_array_class =
+                heap.new_named_class("ByteArray", None, ObjectFormat::Indexable8(16), &[]);
+            let key = to_object_pointer(heap.new_bytes(string_class, &[b'c', b'a', b'f', 0xE9]));
+            let bytes = to_object_pointer(heap.new_bytes(byte_array_class, b"bytes"));
+
+            let pairs = vec![(key, 7i64)];
+            let map =
+                HashMap::<String, i64>::from_smalltalk(pairs.to_smalltalk().unwrap()).unwrap();
+            assert_eq!(map.get("café"), Some(&7));
+
+            assert!(String::from_smalltalk(bytes).is_err());
+        })
+    }
+
+    #[test]
+    fn round_trip_external_addresses() {
+        with_test_heap(|heap| {
+            let address = 0x1234_5678usize as *const c_void;
+            let external_address = address.to_smalltalk().unwrap();
+            assert_eq!(
+                <*const c_void>::from_smalltalk(external_address).unwrap(),
+                address
+            );
+
+            let byte_array_class =
+                heap.new_named_class("ByteArray", None, ObjectFormat::Indexable8(16), &[]);
+            let bytes = heap.new_bytes(byte_array_class, &(address as usize).to_ne_bytes());
+            assert!(<*const c_void>::from_smalltalk(to_object_pointer(bytes)).is_err());
+        })
+    }
+
+    #[test]
+    fn allocate_byte_and_wide_strings() {
+        with_test_heap(|heap| {
+            assert!("café".to_smalltalk().is_err());
+
+            heap.new_named_class("ByteString", None, ObjectFormat::Indexable8(16), &[]);
+            heap.new_named_class("WideString", None, ObjectFormat::Indexable32(10), &[]);
+            let kind = |object| StringKind::of(to_any_object_ref(object));
+
+            let byte_string = "café".to_smalltalk().unwrap();
+            assert_eq!(kind(byte_string), Some(StringKind::ByteString));
+            assert_eq!(String::from_smalltalk(byte_string).unwrap(), "café");
+
+            let wide_string = "λ\0😀".to_string().to_smalltalk().unwrap();
+            assert_eq!(kind(wide_string), Some(StringKind::WideString));
+            assert_eq!(String::from_smalltalk(wide_string).unwrap(), "λ\0😀");
+
+            let like_a_string_class =
+                heap.new_named_class("FooString", None, ObjectFormat::Indexable8(16), &[]);
+            let like_a_string = heap.new_bytes(like_a_string_class, b"foo");
+            assert!(String::from_smalltalk(to_object_pointer(like_a_string)).is_err());
+        })
+    }
+
+    #[test]
+    fn read_large_integers_and_boxed_floats() {
+        with_test_heap(|heap| {
+            let positive_class = heap.class_large_positive_integer();
+            let negative_class = heap.class_large_negative_integer();
+            let float_class = heap.class_boxed_float();
+            let large_integer =
+                |class, magnitude: &[u8]| to_object_pointer(heap.new_bytes(class, magnitude));
+
+            let max_u64 = large_integer(positive_class, &[0xFF; 8]);
+            assert_eq!(u64::from_smalltalk(max_u64).unwrap(), u64::MAX);
+            assert!(i64::from_smalltalk(max_u64).is_err());
+
+            let min_i64 = large_integer(negative_class, &[0, 0, 0, 0, 0, 0, 0, 0x80]);
+            assert_eq!(i64::from_smalltalk(min_i64).unwrap(), i64::MIN);
+            assert!(u64::from_smalltalk(min_i64).is_err());
+
+            let too_large = large_integer(positive_class, &[0, 0, 0, 0, 0, 0, 0, 0, 1]);
+            assert!(u64::from_smalltalk(too_large).is_err());
+
+            let value = 1.0e300f64;
+            let float = heap.instantiate_indexable(float_class, 2);
+            let mut words = WordArrayRef::try_from(AnyObjectRef::from(float)).unwrap();
+            for (word, chunk) in words
+                .as_slice_mut()
+                .unwrap()
+                .iter_mut()
+                .zip(value.to_ne_bytes().chunks_exact(4))
+            {
+                *word = u32::from_ne_bytes(chunk.try_into().unwrap());
+            }
+            assert_eq!(
+                f64::from_smalltalk(to_object_pointer(float)).unwrap(),
+                value
+            );
+            assert!(f64::from_smalltalk(max_u64).is_err());
+        })
+    }
+}
@@ -1,15 +1,20 @@
 use crate::bindings::{
-    disableTelemetry, enableTelemetry, exportOsCogStackPageHeadroom as osCogStackPageHeadroom,
-    exportSqGetInterpreterProxy as sqGetInterpreterProxy, exportStatFullGCUsecs as statFullGCUsecs,
-    exportStatScavengeGCUsecs as statScavengeGCUsecs, getVMExports, installErrorHandlers,
-    registerCurrentThreadToHandleExceptions, setLogger, setProcessArguments,
-    setProcessEnvironmentVector, setShouldLog, setTelemetry, setVMExports, setVmRunOnWorkerThread,
-    sqExport, sqInt, takeTelemetry, vm_init, vm_parameters_ensure_interactive_image_parameter,
-    vm_run_interpreter, InterpreterTelemetry, VirtualMachine,
+    disableTelemetry, enableTelemetry, exportGetCogCodeSize as getCogCodeSize,
+    exportGetMethodZoneUsedBytes as getMethodZoneUsedBytes,
+    exportOsCogStackPageHeadroom as osCogStackPageHeadroom,
+    exportSqGetInterpreterProxy as sqGetInterpreterProxy,
+    exportStatCodeCompactionCount as statCodeCompactionCount,
+    exportStatCodeCompactionUsecs as statCodeCompactionUsecs,
+    exportStatFullGCUsecs as statFullGCUsecs, exportStatScavengeGCUsecs as statScavengeGCUsecs,
+    getVMExports, installErrorHandlers, registerCurrentThreadToHandleExceptions, setLogger,
+    setProcessArguments, setProcessEnvironmentVector, setShouldLog, setTelemetry, setVMExports,
+    setVmRunOnWorkerThread, sqExport, sqInt, takeTelemetry, vm_init,
+    vm_parameters_ensure_interactive_image_parameter, vm_run_interpreter, InterpreterTelemetry,
+    VirtualMachine,
 };
 use crate::parameters::InterpreterParameters;
 use crate::prelude::NativeAccess;
-use crate::{InterpreterConfiguration, InterpreterProxy, NamedPrimitive};
+use crate::{InterpreterConfiguration, InterpreterProxy, NamedPrimitive, Smalltalk};
 use anyhow::{bail, Result};
 use std::fmt::Debug;
 use std::os::raw::{c_char, c_int};
@@ -134,6 +139,9 @@ impl PharoInterpreter {
     /// Can be executed from any thread
     fn run(&self, parameters: InterpreterParameters) -> Result<()> {
         self.init(parameters)?;
+        if self.configuration.should_verify_heap_on_start() {
+            self.verify_heap();
+        }
         self.register_current_thread_to_handle_exceptions();
         self.run_interpreter();
         Ok(())
@@ -150,6 +158,16 @@ impl PharoInterpreter {
         Ok(())
     }
 
+    /// Verify the heap of the freshly loaded image and log the report
+    fn verify_heap(&self) {
+        let report = Smalltalk::verify_heap();
+        if report.is_ok() {
+            info!("{}", report);
+        } else {
+            error!("{}", report);
+        }
+    }
+
     fn register_current_thread_to_handle_exceptions(&self) {
         unsafe { registerCurrentThreadToHandleExceptions() };
     }
@@ -215,6 +233,27 @@ impl PharoInterpreter {
         unsafe { statScavengeGCUsecs().into() }
     }
 
+    /// Return the amount of times the machine code zone was compacted to make room for new methods.
+    /// The vm without the jit has no code zone and answers 0 for all of the code zone statistics
+    pub fn code_compactions(&self) -> u64 {
+        unsafe { statCodeCompactionCount() }
+    }
+
+    /// Return the total amount of microseconds spent on compacting the machine code zone
+    pub fn code_compaction_microseconds(&self) -> u64 {
+        unsafe { statCodeCompactionUsecs() }
+    }
+
+    /// Return the size in bytes of the machine code zone
+    pub fn code_zone_size(&self) -> usize {
+        unsafe { getCogCodeSize() as usize }
+    }
+
+    /// Return the amount of bytes of the machine code zone taken by methods
+    pub fn code_zone_used_bytes(&self) -> usize {
+        unsafe { getMethodZoneUsedBytes() as usize }
+    }
+
     /// re-allocate the vm-exports memory using rust allocator so that we can modify the exports
     fn initialize_vm_exports(&self) {
         let vm_exports_ptr: *const NamedPrimitive =
@@ -8,6 +8,7 @@ pub struct InterpreterConfiguration {
     interactive_session: bool,
     should_print_stack_on_signals: bool,
     should_avoid_searching_segments_with_pinned_objects: bool,
+    should_verify_heap_on_start: bool,
     worker_thread: bool,
     arguments: Vec<String>,
 }
@@ -19,6 +20,7 @@ impl InterpreterConfiguration {
             interactive_session: false,
             should_print_stack_on_signals: false,
             should_avoid_searching_segments_with_pinned_objects: false,
+            should_verify_heap_on_start: false,
             worker_thread: false,
             arguments: vec![],
         }
@@ -36,6 +38,10 @@ impl InterpreterConfiguration {
         self.should_print_stack_on_signals
     }
 
+    pub fn should_verify_heap_on_start(&self) -> bool {
+        self.should_verify_heap_on_start
+    }
+
     pub fn set_is_worker_thread(&mut self, worker_thread: bool) -> &mut Self {
         self.worker_thread = worker_thread;
         self
@@ -63,6 +69,15 @@ impl InterpreterConfiguration {
         self
     }
 
+    /// Verify the integrity of the heap after the image is loaded and before the interpreter starts
+    pub fn set_should_verify_heap_on_start(
+        &mut self,
+        should_verify_heap_on_start: bool,
+    ) -> &mut Self {
+        self.should_verify_heap_on_start = should_verify_heap_on_start;
+        self
+    }
+
     pub fn set_extra_arguments(&mut self, arguments: Vec<String>) -> &mut Self {
         self.arguments = arguments;
         self
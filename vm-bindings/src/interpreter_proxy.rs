@@ -111,6 +111,16 @@ impl InterpreterProxy {
         unsafe { cast_integer(function(object.into_native())) }
     }
 
+    pub fn signed_64bit_value_of(&self, object: ObjectPointer) -> i64 {
+        let function = self.native().signed64BitValueOf.unwrap();
+        unsafe { cast_integer(function(object.into_native())) }
+    }
+
+    pub fn float_value_of(&self, object: ObjectPointer) -> c_double {
+        let function = self.native().floatValueOf.unwrap();
+        unsafe { function(object.into_native()) }
+    }
+
     pub fn fetch_float_at(&self, object: ObjectPointer, index: ObjectFieldIndex) -> c_double {
         let function = self.native().fetchFloatofObject.unwrap();
         unsafe { function(index.into_native(), object.into_native()) }
@@ -169,6 +179,20 @@ impl InterpreterProxy {
         ObjectPointer::from_native_c(oop)
     }
 
+    /// Return a SmallInteger if the value fits, otherwise allocate a LargePositive/NegativeInteger
+    pub fn new_signed_64bit_integer(&self, integer: i64) -> ObjectPointer {
+        let function = self.native().signed64BitIntegerFor.unwrap();
+        let oop = unsafe { function(cast_integer(integer)) };
+        ObjectPointer::from_native_c(oop)
+    }
+
+    /// Return a SmallFloat64 if the value fits, otherwise allocate a BoxedFloat64
+    pub fn new_float(&self, float: c_double) -> ObjectPointer {
+        let function = self.native().floatObjectOf.unwrap();
+        let oop = unsafe { function(float) };
+        ObjectPointer::from_native_c(oop)
+    }
+
     pub fn read_address(&self, external_address_object: ObjectPointer) -> *mut c_void {
         unsafe { readAddress(external_address_object.into_native()) }
     }
@@ -1,24 +1,37 @@
 #[macro_use]
+extern crate log;
+#[macro_use]
 extern crate num_derive;
 
 pub mod bindings;
+mod conversion;
 mod export;
 mod interpreter;
 mod interpreter_config;
 mod interpreter_marshalling;
 mod interpreter_proxy;
+#[cfg(feature = "test_heap")]
+mod object_memory;
 mod parameter_vector;
 mod parameters;
 mod prelude;
+mod string_classes;
 mod virtual_machine;
 
+pub use conversion::*;
 pub use export::NamedPrimitive;
 pub use interpreter::{LogLevel, PharoInterpreter};
 pub use interpreter_config::InterpreterConfiguration;
 pub use interpreter_marshalling::Marshallable;
 pub use interpreter_proxy::{InterpreterProxy, ObjectFieldIndex, ObjectPointer, StackOffset};
+#[cfg(feature = "test_heap")]
+pub use object_memory::{with_object_memory, ObjectMemory};
+pub use string_classes::*;
 pub use virtual_machine::*;
 
+// re-exported for the conversions generated by the derive macros
+pub use anyhow;
+
 // re-export ffi
 #[cfg(feature = "libffi")]
 pub use libffi;
@@ -28,3 +41,9 @@ pub use libffi_sys;
 pub fn virtual_machine_info() -> &'static str {
     include_str!(env!("VM_INFO"))
 }
+
+/// Return true if the interpreter and the jit emit the message send, return, primitive
+/// and machine method signals, which requires the `telemetry` feature
+pub fn emits_send_signals() -> bool {
+    cfg!(feature = "telemetry")
+}
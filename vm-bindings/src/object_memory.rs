@@ -0,0 +1,129 @@
+use crate::ObjectPointer;
+use std::cell::RefCell;
+use std::rc::Rc;
+use vm_object_model::{AnyObjectRef, ObjectRef, RawObjectPointer, TestHeap};
+
+/// An allocation backend that replaces the object memory of the interpreter.
+/// When installed for the current thread, `Smalltalk` allocates objects, answers well-known
+/// objects and performs store checks through it instead of calling the vm.
+pub trait ObjectMemory {
+    fn nil_object(&self) -> ObjectPointer;
+    fn true_object(&self) -> ObjectPointer;
+    fn false_object(&self) -> ObjectPointer;
+    fn class_array(&self) -> ObjectPointer;
+    fn class_external_address(&self) -> ObjectPointer;
+    fn instantiate_class(&self, class: ObjectPointer, is_pinned: bool) -> ObjectPointer;
+    fn instantiate_indexable_class(
+        &self,
+        class: ObjectPointer,
+        size: usize,
+        is_pinned: bool,
+    ) -> ObjectPointer;
+    fn class_or_nil_at_index(&self, class_index: u32) -> ObjectPointer;
+    /// Return the identity hash bits of an object (not shifted)
+    fn identity_hash(&self, object: ObjectPointer) -> u32;
+    /// Return a fixed field of a context the way Smalltalk code sees it
+    fn context_inst_var_at(&self, context: ObjectPointer, field_index: usize) -> ObjectPointer;
+    fn is_old(&self, _object: ObjectPointer) -> bool {
+        true
+    }
+    fn is_young(&self, _object: ObjectPointer) -> bool {
+        false
+    }
+    fn possible_old_object_store_into(&self, _object: ObjectPointer) {}
+    fn possible_perm_object_store_into(&self, _object: ObjectPointer, _value: ObjectPointer) {}
+}
+
+thread_local! {
+    static OBJECT_MEMORY: RefCell<Option<Rc<dyn ObjectMemory>>> = const { RefCell::new(None) };
+}
+
+/// Evaluate a closure with a given object memory installed for the current thread.
+/// The previously installed object memory is restored afterwards.
+pub fn with_object_memory<R>(object_memory: Rc<dyn ObjectMemory>, block: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Rc<dyn ObjectMemory>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            OBJECT_MEMORY.with(|memory| *memory.borrow_mut() = self.0.take());
+        }
+    }
+
+    let previous = OBJECT_MEMORY.with(|memory| memory.borrow_mut().replace(object_memory));
+    let _restore = Restore(previous);
+    block()
+}
+
+pub(crate) fn current_object_memory() -> Option<Rc<dyn ObjectMemory>> {
+    OBJECT_MEMORY.with(|memory| memory.borrow().clone())
+}
+
+fn to_object_ref(object: ObjectPointer) -> ObjectRef {
+    ObjectRef::try_from(RawObjectPointer::new(object.as_i64())).unwrap()
+}
+
+fn to_object_pointer(object: impl Into<AnyObjectRef>) -> ObjectPointer {
+    ObjectPointer::from(object.into().as_i64())
+}
+
+impl ObjectMemory for TestHeap {
+    fn nil_object(&self) -> ObjectPointer {
+        to_object_pointer(self.nil())
+    }
+
+    fn true_object(&self) -> ObjectPointer {
+        to_object_pointer(TestHeap::true_object(self))
+    }
+
+    fn false_object(&self) -> ObjectPointer {
+        to_object_pointer(TestHeap::false_object(self))
+    }
+
+    fn class_array(&self) -> ObjectPointer {
+        to_object_pointer(TestHeap::class_array(self))
+    }
+
+    fn class_external_address(&self) -> ObjectPointer {
+        to_object_pointer(TestHeap::class_external_address(self))
+    }
+
+    fn instantiate_class(&self, class: ObjectPointer, is_pinned: bool) -> ObjectPointer {
+        let object = self.instantiate(to_object_ref(class));
+        self.set_pinned(object, is_pinned);
+        to_object_pointer(object)
+    }
+
+    fn instantiate_indexable_class(
+        &self,
+        class: ObjectPointer,
+        size: usize,
+        is_pinned: bool,
+    ) -> ObjectPointer {
+        let object = self.instantiate_indexable(to_object_ref(class), size);
+        self.set_pinned(object, is_pinned);
+        to_object_pointer(object)
+    }
+
+    fn class_or_nil_at_index(&self, class_index: u32) -> ObjectPointer {
+        self.class_at_index(class_index)
+            .map(to_object_pointer)
+            .unwrap_or_else(|| self.nil_object())
+    }
+
+    fn identity_hash(&self, object: ObjectPointer) -> u32 {
+        to_object_ref(object).header().identity_hash()
+    }
+
+    /// There are no stack frames, so a context that was married to a frame is a widow,
+    /// whose sender and pc read as nil the same way the vm marks it as dead
+    fn context_inst_var_at(&self, context: ObjectPointer, field_index: usize) -> ObjectPointer {
+        let context = to_object_ref(context);
+        let is_widowed = context
+            .inst_var_at(0)
+            .is_some_and(|sender| sender.is_immediate());
+        match context.inst_var_at(field_index) {
+            Some(_) if is_widowed && field_index < 2 => self.nil_object(),
+            Some(value) => to_object_pointer(value),
+            None => self.nil_object(),
+        }
+    }
+}
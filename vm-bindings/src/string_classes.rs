@@ -0,0 +1,122 @@
+use crate::Smalltalk;
+use std::sync::atomic::AtomicU32;
+use vm_object_model::{
+    AnyObjectRef, ByteStringRef, ClassRef, Error, ObjectRef, Result, WideStringRef,
+};
+
+/// The classes of the strings that are allocated from Rust, provided by the image
+#[derive(Debug, Copy, Clone)]
+pub struct StringClasses {
+    pub byte_string: ObjectRef,
+    pub wide_string: ObjectRef,
+}
+
+/// Class indices never change, so the indices of the string classes are remembered after the first lookup
+static BYTE_STRING_CLASS_INDEX: AtomicU32 = AtomicU32::new(0);
+static WIDE_STRING_CLASS_INDEX: AtomicU32 = AtomicU32::new(0);
+static BYTE_SYMBOL_CLASS_INDEX: AtomicU32 = AtomicU32::new(0);
+static WIDE_SYMBOL_CLASS_INDEX: AtomicU32 = AtomicU32::new(0);
+
+impl StringClasses {
+    /// Return the ByteString and WideString classes of the running image
+    pub fn of_image() -> Result<Self> {
+        Ok(Self {
+            byte_string: StringKind::ByteString.class()?.into(),
+            wide_string: StringKind::WideString.class()?.into(),
+        })
+    }
+
+    /// Allocate a ByteString if all characters fit in Latin-1, otherwise a WideString
+    pub fn new_string(&self, string: &str) -> Result<AnyObjectRef> {
+        if is_latin1(string) {
+            Ok(new_byte_string(self.byte_string, string)?.into())
+        } else {
+            Ok(new_wide_string(self.wide_string, string)?.into())
+        }
+    }
+}
+
+/// The classes of the image whose instances are strings.
+/// The format does not tell a string from a ByteArray or a WordArray, so strings are recognized
+/// by their class, which must be one of these classes and not merely have a similar name
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StringKind {
+    ByteString,
+    WideString,
+    ByteSymbol,
+    WideSymbol,
+}
+
+impl StringKind {
+    /// Return the kind of string of an object, or None if it is not a string or a symbol
+    pub fn of(object: impl Into<AnyObjectRef>) -> Option<Self> {
+        let class = Smalltalk::class_of(object)?;
+        let kind = match class.name()?.as_str() {
+            "ByteString" => Self::ByteString,
+            "WideString" => Self::WideString,
+            "ByteSymbol" => Self::ByteSymbol,
+            "WideSymbol" => Self::WideSymbol,
+            _ => return None,
+        };
+        (kind.class().ok()?.class_index() == class.class_index()).then_some(kind)
+    }
+
+    pub fn is_symbol(&self) -> bool {
+        matches!(self, Self::ByteSymbol | Self::WideSymbol)
+    }
+
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Self::WideString | Self::WideSymbol)
+    }
+
+    pub fn class_name(&self) -> &'static str {
+        match self {
+            Self::ByteString => "ByteString",
+            Self::WideString => "WideString",
+            Self::ByteSymbol => "ByteSymbol",
+            Self::WideSymbol => "WideSymbol",
+        }
+    }
+
+    /// Return the class of this kind of strings in the running image
+    pub fn class(&self) -> Result<ClassRef> {
+        let class_index = match self {
+            Self::ByteString => &BYTE_STRING_CLASS_INDEX,
+            Self::WideString => &WIDE_STRING_CLASS_INDEX,
+            Self::ByteSymbol => &BYTE_SYMBOL_CLASS_INDEX,
+            Self::WideSymbol => &WIDE_SYMBOL_CLASS_INDEX,
+        };
+        Smalltalk::class_named_at(self.class_name(), class_index)
+            .ok_or_else(|| Error::InvalidType(format!("{} class", self.class_name())))
+    }
+}
+
+/// Return true if all characters of a string can be stored in a ByteString
+pub fn is_latin1(string: &str) -> bool {
+    string.chars().all(|each| (each as u32) <= 0xFF)
+}
+
+/// Allocate an instance of a byte string class (ByteString or ByteSymbol) with Latin-1 encoded characters.
+/// Fails if a character does not fit in Latin-1
+pub fn new_byte_string(class: ObjectRef, string: &str) -> Result<ByteStringRef> {
+    if !is_latin1(string) {
+        return Err(Error::InvalidType("Latin-1 string".to_string()));
+    }
+
+    let mut byte_string =
+        Smalltalk::instantiate_indexable::<ByteStringRef>(class, string.chars().count())?;
+    for (target, char) in byte_string.as_slice_mut()?.iter_mut().zip(string.chars()) {
+        *target = char as u8;
+    }
+    Ok(byte_string)
+}
+
+/// Allocate an instance of a wide string class (WideString or WideSymbol) with UTF-32 encoded characters
+pub fn new_wide_string(class: ObjectRef, string: &str) -> Result<WideStringRef> {
+    let mut wide_string =
+        Smalltalk::instantiate_indexable::<WideStringRef>(class, string.chars().count())?;
+    for (target, char) in wide_string.as_slice_mut()?.iter_mut().zip(string.chars()) {
+        *target = char as u32;
+    }
+    Ok(wide_string)
+}
@@ -1,6 +1,13 @@
 use crate::bindings::{
     addressCouldBeClassObj, classArray, classExternalAddress, classString,
-    createNewMethodheaderbytecodeCount, ensureBehaviorHash, falseObject, fetchPointerofObject,
+    createNewMethodheaderbytecodeCount, ensureBehaviorHash, exportClassOrNilAtIndex as classOrNilAtIndex,
+    exportContextInstVarOf as contextInstVarOf, exportGetEdenStart as getEdenStart,
+    exportGetEndOfMemory as getEndOfMemory,
+    exportGetFreeStart as getFreeStart, exportGetMemoryMap as getMemoryMap,
+    exportGetPastSpaceBase as getPastSpaceBase, exportGetPastSpaceStart as getPastSpaceStart,
+    exportGetPermSpaceFreeStart as getPermSpaceFreeStart,
+    exportGetSchedulerPointer as getSchedulerPointer,
+    falseObject, fetchPointerofObject,
     firstBytePointerOfDataObject, firstFixedField, firstIndexableField, hashBitsOf,
     instantiateClassindexableSize, instantiateClassindexableSizeisPinned, instantiateClassisPinned,
     integerObjectOf, isOld, isOopForwarded, isYoung, methodArgumentCount, methodReturnBool,
@@ -8,14 +15,32 @@ use crate::bindings::{
     possiblePermObjectStoreIntovalue, primitiveFail, primitiveFailFor, sqInt, stObjectat,
     stObjectatput, stSizeOf, stackIntegerValue, stackValue, trueObject,
 };
+#[cfg(feature = "test_heap")]
+use crate::object_memory::current_object_memory;
 use crate::prelude::NativeTransmutable;
 use crate::{ObjectFieldIndex, ObjectPointer, StackOffset};
+use std::ops::Range;
 use std::os::raw::c_void;
-use vm_object_model::{AnyObjectRef, ObjectRef, RawObjectPointer};
+use std::sync::atomic::{AtomicU32, Ordering};
+use vm_object_model::{
+    AnyObjectRef, ClassRef, HeapReport, HeapSpace, HeapVerifier, ObjectRef, RawObjectPointer,
+};
 
 pub struct Smalltalk {}
 
 impl Smalltalk {
+    /// Class indices below 32 are reserved for immediates and puns
+    /// (free chunks, forwarders, hidden objects and the classes of arrays)
+    pub const FIRST_CLASS_INDEX: u32 = 32;
+    /// Spur registers the classes of the numbers that do not fit in an immediate at fixed indices
+    pub const LARGE_NEGATIVE_INTEGER_CLASS_INDEX: u32 = 32;
+    pub const LARGE_POSITIVE_INTEGER_CLASS_INDEX: u32 = 33;
+    pub const BOXED_FLOAT_CLASS_INDEX: u32 = 34;
+    /// The class table is made of pages of 1024 classes that are allocated as classes are added
+    const CLASS_TABLE_PAGE_SIZE: u32 = 1024;
+    /// Class indices are 22 bits
+    const MAX_CLASS_TABLE_SIZE: u32 = 1 << 22;
+
     pub fn new() -> Self {
         Self {}
     }
@@ -138,6 +163,28 @@ impl Smalltalk {
         }
     }
 
+    /// Return a fixed field of a context the way Smalltalk code sees it.
+    /// The fields of a context married to a frame are computed from the frame,
+    /// and asking for the sender marries the caller frame, which allocates a context and may move objects
+    pub fn context_inst_var_at(context: ObjectPointer, field_index: usize) -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.context_inst_var_at(context, field_index);
+        }
+
+        unsafe {
+            ObjectPointer::from_native_c(contextInstVarOf(
+                field_index as sqInt,
+                context.into_native(),
+            ))
+        }
+    }
+
+    /// Return the ProcessorScheduler of the image (`Processor`)
+    pub fn scheduler() -> ObjectPointer {
+        unsafe { ObjectPointer::from_native_c(getSchedulerPointer()) }
+    }
+
     pub fn first_indexable_field(object: ObjectPointer) -> *mut c_void {
         unsafe { firstIndexableField(object.into_native()) }
     }
@@ -161,18 +208,38 @@ impl Smalltalk {
     }
 
     pub fn true_object() -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.true_object();
+        }
+
         unsafe { ObjectPointer::from_native_c(trueObject()) }
     }
 
     pub fn false_object() -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.false_object();
+        }
+
         unsafe { ObjectPointer::from_native_c(falseObject()) }
     }
 
     pub fn nil_object() -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.nil_object();
+        }
+
         unsafe { ObjectPointer::from_native_c(nilObject()) }
     }
 
     pub fn primitive_class_array() -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.class_array();
+        }
+
         unsafe { ObjectPointer::from_native_c(classArray()) }
     }
 
@@ -184,7 +251,73 @@ impl Smalltalk {
         .unwrap()
     }
 
+    pub fn primitive_class_or_nil_at_index(class_index: u32) -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.class_or_nil_at_index(class_index);
+        }
+
+        unsafe { ObjectPointer::from_native_c(classOrNilAtIndex(class_index as sqInt)) }
+    }
+
+    /// Return a class with a given index in the class table
+    pub fn class_at_index(class_index: u32) -> Option<ClassRef> {
+        let class = Self::primitive_class_or_nil_at_index(class_index);
+        ClassRef::try_from(AnyObjectRef::from(RawObjectPointer::from(class.as_i64()))).ok()
+    }
+
+    /// Return the class index right after the last page of the class table that holds classes
+    pub fn class_table_size() -> u32 {
+        let page_size = Self::CLASS_TABLE_PAGE_SIZE;
+        let used_pages = (1..Self::MAX_CLASS_TABLE_SIZE / page_size)
+            .find(|page| {
+                (page * page_size..(page + 1) * page_size)
+                    .all(|class_index| Self::class_at_index(class_index).is_none())
+            })
+            .unwrap_or(Self::MAX_CLASS_TABLE_SIZE / page_size);
+        used_pages * page_size
+    }
+
+    /// Return a class with a given name by scanning the class table.
+    /// The scan is linear, so callers should remember the class index of the found class,
+    /// see `class_named_at`
+    pub fn class_named(name: &str) -> Option<ClassRef> {
+        Self::class_named_within(name, Self::FIRST_CLASS_INDEX..Self::class_table_size())
+    }
+
+    fn class_named_within(name: &str, class_indices: Range<u32>) -> Option<ClassRef> {
+        class_indices
+            .filter_map(Self::class_at_index)
+            .find(|class| class.name().as_deref() == Some(name))
+    }
+
+    /// Return a class with a given name, first trying the class index found by a previous lookup
+    /// and remembering the index of the found class
+    pub fn class_named_at(name: &str, class_index: &AtomicU32) -> Option<ClassRef> {
+        let class = Self::class_at_index(class_index.load(Ordering::Relaxed))
+            .filter(|class| class.name().as_deref() == Some(name))
+            .or_else(|| Self::class_named(name))?;
+
+        class_index.store(class.class_index(), Ordering::Relaxed);
+        Some(class)
+    }
+
+    /// Return the class of an object or an immediate value by looking up its class index in the class table
+    pub fn class_of(object: impl Into<AnyObjectRef>) -> Option<ClassRef> {
+        let object = object.into();
+        let class_index = match object.as_immediate() {
+            Ok(immediate) => immediate.class_index(),
+            Err(_) => object.as_object().ok()?.follow_forwarded().header().class_index(),
+        };
+        Self::class_at_index(class_index)
+    }
+
     pub fn class_external_address() -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.class_external_address();
+        }
+
         unsafe { ObjectPointer::from_native_c(classExternalAddress()) }
     }
 
@@ -193,6 +326,11 @@ impl Smalltalk {
     }
 
     pub fn primitive_instantiate_class(class: ObjectPointer, is_pinned: bool) -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.instantiate_class(class, is_pinned);
+        }
+
         let is_pinned = if is_pinned { 1 } else { 0 };
         let oop = unsafe { instantiateClassisPinned(class.into_native(), is_pinned) };
         ObjectPointer::from_native_c(oop)
@@ -217,6 +355,11 @@ impl Smalltalk {
         class: ObjectPointer,
         size: usize,
     ) -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.instantiate_indexable_class(class, size, false);
+        }
+
         let oop = unsafe { instantiateClassindexableSize(class.into_native(), size as sqInt) };
         ObjectPointer::from_native_c(oop)
     }
@@ -226,6 +369,11 @@ impl Smalltalk {
         size: usize,
         is_pinned: bool,
     ) -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.instantiate_indexable_class(class, size, is_pinned);
+        }
+
         let is_pinned = if is_pinned { 1 } else { 0 };
         let oop = unsafe {
             instantiateClassindexableSizeisPinned(
@@ -258,6 +406,11 @@ impl Smalltalk {
         size: usize,
         is_pinned: bool,
     ) -> ObjectPointer {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.instantiate_indexable_class(class, size, is_pinned);
+        }
+
         let is_pinned = if is_pinned { 1 } else { 0 };
         let oop = unsafe {
             instantiateClassindexableSizeisPinned(
@@ -284,6 +437,11 @@ impl Smalltalk {
     }
 
     pub fn identity_hash(object: ObjectPointer) -> u64 {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return (object_memory.identity_hash(object) as u64) << 8;
+        }
+
         let hash = if Self::could_oop_be_class(object) {
             Self::behavior_identity_hash(object)
         } else {
@@ -321,18 +479,38 @@ impl Smalltalk {
     }
 
     pub fn is_old(object: ObjectPointer) -> bool {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.is_old(object);
+        }
+
         (unsafe { isOld(object.into_native()) }) != 0
     }
 
     pub fn is_young(object: ObjectPointer) -> bool {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.is_young(object);
+        }
+
         (unsafe { isYoung(object.into_native()) }) != 0
     }
 
     pub fn possible_old_object_store_into(object: ObjectPointer) {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.possible_old_object_store_into(object);
+        }
+
         unsafe { possibleOldObjectStoreInto(object.into_native()) };
     }
 
     pub fn possible_perm_object_store_into(object: ObjectPointer, value: ObjectPointer) {
+        #[cfg(feature = "test_heap")]
+        if let Some(object_memory) = current_object_memory() {
+            return object_memory.possible_perm_object_store_into(object, value);
+        }
+
         unsafe { possiblePermObjectStoreIntovalue(object.into_native(), value.into_native()) };
     }
 
@@ -346,4 +524,41 @@ impl Smalltalk {
         }
         Smalltalk::possible_perm_object_store_into(object, value);
     }
+
+    /// Return the parts of the object memory that are filled with objects,
+    /// as described by the vm memory map and the current allocation pointers
+    pub fn heap_spaces() -> Vec<HeapSpace> {
+        let memory_map = unsafe { &*getMemoryMap() };
+        let spaces = unsafe {
+            [
+                HeapSpace::new(
+                    "perm space",
+                    memory_map.permSpaceStart as usize,
+                    getPermSpaceFreeStart() as usize,
+                ),
+                HeapSpace::new(
+                    "old space",
+                    memory_map.oldSpaceStart as usize,
+                    getEndOfMemory() as usize,
+                ),
+                HeapSpace::new(
+                    "past space",
+                    getPastSpaceBase() as usize,
+                    getPastSpaceStart() as usize,
+                ),
+                HeapSpace::new("eden", getEdenStart() as usize, getFreeStart() as usize),
+            ]
+        };
+        spaces
+            .into_iter()
+            .filter(|space| space.size() > 0)
+            .collect()
+    }
+
+    /// Walk all objects in the heap and validate their headers and slots.
+    /// Must not be called during garbage collection
+    pub fn verify_heap() -> HeapReport {
+        HeapVerifier::new(Self::heap_spaces())
+            .verify(|class_index| Self::class_at_index(class_index).is_some())
+    }
 }
@@ -77,6 +77,14 @@ fn main() {
                 .action(clap::ArgAction::SetTrue)
                 .help("Pablos questionable command line parameter"),
         )
+        .arg(
+            Arg::new("verify-heap-on-start")
+                .long("verify-heap-on-start")
+                .action(clap::ArgAction::SetTrue)
+                .help(
+                    "Verify the integrity of the heap after loading the image and print a report",
+                ),
+        )
         .arg(
             Arg::new("version")
                 .long("version")
@@ -165,6 +173,8 @@ fn main() {
     interpreter_configuration.set_is_worker_thread(worker_mode.should_run_in_worker_thread());
     interpreter_configuration
         .set_should_print_stack_on_signals(matches.get_flag("print-stack-on-signals"));
+    interpreter_configuration
+        .set_should_verify_heap_on_start(matches.get_flag("verify-heap-on-start"));
     interpreter_configuration.set_extra_arguments(extra_args);
 
     let log_signals = matches
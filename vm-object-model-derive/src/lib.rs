@@ -0,0 +1,382 @@
+//! Derive macro for wrappers of Pharo objects.
+//!
+//! ```ignore
+//! #[derive(Debug, PharoObject)]
+//! #[repr(C)]
+//! pub struct Association {
+//!     this: Object,
+//!     key: AnyObjectRef,
+//!     value: AnyObjectRef,
+//! }
+//! ```
+//!
+//! generates:
+//!  - `Deref<Target = Object>` for the struct, through its `this` header field;
+//!  - a `#[repr(transparent)]` reference type `AssociationRef(ObjectRef)` with `Deref`/`DerefMut`
+//!    to the struct, following forwarders;
+//!  - `TryFrom<AnyObjectRef>` for the reference, checking the amount of slots
+//!    (one per field after `this`) and, optionally, the class or the format of the object;
+//!  - `From<AssociationRef> for AnyObjectRef`;
+//!  - a setter per field (`set_key`, `set_value`) that goes through the write barrier
+//!    the same way `assign_field!` does. Immediate fields are assigned directly.
+//!    Setters fail with `Error::Immutable` if the object is read-only.
+//!
+//! Struct attributes:
+//!  - `#[pharo(class = <expr>)]` an expression that evaluates to the `ObjectRef` of the expected class;
+//!  - `#[pharo(format = <expr>)]` the expected `ObjectFormat`, for example of ephemerons;
+//!  - `#[pharo(ref_name = Name)]` the name of the reference type, `<Struct>Ref` by default.
+//!
+//! Field attributes:
+//!  - `#[pharo(no_setter)]` do not generate a setter for the field.
+//!
+//! `ToSmalltalk` and `FromSmalltalk` derive the conversions of `vm_bindings` for plain Rust structs,
+//! named or tuple, as an Array with an item per field in the order of declaration:
+//!
+//! ```ignore
+//! #[derive(ToSmalltalk, FromSmalltalk)]
+//! struct NamedPrimitive {
+//!     plugin_name: String,
+//!     primitive_name: String,
+//!     address: *const c_void,
+//! }
+//! ```
+//!
+//! Every field must itself implement the derived trait.
+//! Both conversions return the `anyhow::Result` re-exported by `vm_bindings`,
+//! so crates that derive them do not need to depend on `anyhow`.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Field, Fields, Ident, Type};
+
+#[proc_macro_derive(PharoObject, attributes(pharo))]
+pub fn derive_pharo_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.into_compile_error().into(),
+    }
+}
+
+struct ObjectAttributes {
+    class: Option<Expr>,
+    format: Option<Expr>,
+    ref_name: Option<Ident>,
+}
+
+fn parse_object_attributes(input: &DeriveInput) -> syn::Result<ObjectAttributes> {
+    let mut attributes = ObjectAttributes {
+        class: None,
+        format: None,
+        ref_name: None,
+    };
+
+    for attribute in input
+        .attrs
+        .iter()
+        .filter(|each| each.path().is_ident("pharo"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("class") {
+                attributes.class = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("format") {
+                attributes.format = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("ref_name") {
+                attributes.ref_name = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `class`, `format` or `ref_name`"))
+            }
+        })?;
+    }
+
+    Ok(attributes)
+}
+
+fn has_no_setter(field: &Field) -> syn::Result<bool> {
+    let mut no_setter = false;
+    for attribute in field
+        .attrs
+        .iter()
+        .filter(|each| each.path().is_ident("pharo"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("no_setter") {
+                no_setter = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `no_setter`"))
+            }
+        })?;
+    }
+    Ok(no_setter)
+}
+
+fn is_type_named(ty: &Type, name: &str) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == name)
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let attributes = parse_object_attributes(&input)?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "PharoObject can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "PharoObject can only be derived for structs",
+            ))
+        }
+    };
+
+    let header = fields.first().ok_or_else(|| {
+        syn::Error::new(input.span(), "The first field must be the object header")
+    })?;
+    if !is_type_named(&header.ty, "Object") {
+        return Err(syn::Error::new(
+            header.ty.span(),
+            "The first field must be the object header of type `Object`",
+        ));
+    }
+    let header_field = header.ident.as_ref().unwrap();
+    let slots = &fields[1..];
+
+    let name = &input.ident;
+    let visibility = &input.vis;
+    let ref_name = attributes
+        .ref_name
+        .unwrap_or_else(|| format_ident!("{}Ref", name));
+    let type_name = name.to_string();
+    let amount_of_slots = slots.len();
+
+    let mut setters = vec![];
+    for field in slots {
+        if has_no_setter(field)? {
+            continue;
+        }
+
+        let field_name = field.ident.as_ref().unwrap();
+        let field_type = &field.ty;
+        let setter = Ident::new(&format!("set_{}", field_name), Span::call_site());
+
+        let setter = if is_type_named(field_type, "Immediate") {
+            quote! {
+                pub fn #setter(&mut self, value: #field_type) -> ::vm_object_model::Result<()> {
+                    self.#header_field.check_mutable()?;
+                    self.#field_name = value;
+                    Ok(())
+                }
+            }
+        } else if is_type_named(field_type, "AnyObjectRef") {
+            quote! {
+                pub fn #setter(
+                    &mut self,
+                    value: impl Into<::vm_object_model::AnyObjectRef>,
+                ) -> ::vm_object_model::Result<()> {
+                    self.#header_field.check_mutable()?;
+                    let value: ::vm_object_model::AnyObjectRef = value.into();
+                    ::vm_bindings::Smalltalk::prepare_to_store(
+                        ::vm_bindings::ObjectPointer::from(self.#header_field.as_ptr()),
+                        ::vm_bindings::ObjectPointer::from(value.as_i64()),
+                    );
+                    self.#field_name = value;
+                    Ok(())
+                }
+            }
+        } else {
+            quote! {
+                pub fn #setter(&mut self, value: #field_type) -> ::vm_object_model::Result<()> {
+                    self.#header_field.check_mutable()?;
+                    let value_ref: ::vm_object_model::AnyObjectRef = value.into();
+                    ::vm_bindings::Smalltalk::prepare_to_store(
+                        ::vm_bindings::ObjectPointer::from(self.#header_field.as_ptr()),
+                        ::vm_bindings::ObjectPointer::from(value_ref.as_i64()),
+                    );
+                    self.#field_name = value;
+                    Ok(())
+                }
+            }
+        };
+        setters.push(setter);
+    }
+
+    let class_check = attributes.class.map(|class| {
+        quote! {
+            let expected_class: ::vm_object_model::ObjectRef = #class;
+            // in Spur the identity hash of a class is its index in the class table
+            if object.header().class_index() != expected_class.header().identity_hash() {
+                return Err(::vm_object_model::Error::InvalidType(#type_name.to_string()));
+            }
+        }
+    });
+
+    let format_check = attributes.format.map(|format| {
+        quote! {
+            let expected_format: ::vm_object_model::ObjectFormat = #format;
+            if object.object_format() != expected_format {
+                return Err(::vm_object_model::Error::InvalidType(#type_name.to_string()));
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #name {
+            pub const AMOUNT_OF_SLOTS: usize = #amount_of_slots;
+
+            #(#setters)*
+        }
+
+        impl ::std::ops::Deref for #name {
+            type Target = ::vm_object_model::Object;
+
+            fn deref(&self) -> &Self::Target {
+                &self.#header_field
+            }
+        }
+
+        #[derive(Debug, Copy, Clone)]
+        #[repr(transparent)]
+        #visibility struct #ref_name(::vm_object_model::ObjectRef);
+
+        impl ::std::ops::Deref for #ref_name {
+            type Target = #name;
+
+            fn deref(&self) -> &Self::Target {
+                unsafe { self.0.cast() }
+            }
+        }
+
+        impl ::std::ops::DerefMut for #ref_name {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                unsafe { self.0.cast_mut() }
+            }
+        }
+
+        impl TryFrom<::vm_object_model::AnyObjectRef> for #ref_name {
+            type Error = ::vm_object_model::Error;
+
+            fn try_from(value: ::vm_object_model::AnyObjectRef) -> ::vm_object_model::Result<Self> {
+                let object = value.as_object()?.follow_forwarded();
+                let actual_amount_of_slots = object.amount_of_slots();
+
+                if actual_amount_of_slots != #name::AMOUNT_OF_SLOTS {
+                    return Err(::vm_object_model::Error::WrongAmountOfSlots {
+                        object: *object.header(),
+                        expected: #name::AMOUNT_OF_SLOTS,
+                        actual: actual_amount_of_slots,
+                    });
+                }
+
+                #class_check
+                #format_check
+
+                Ok(Self(object))
+            }
+        }
+
+        impl From<#ref_name> for ::vm_object_model::AnyObjectRef {
+            fn from(value: #ref_name) -> Self {
+                value.0.into()
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(ToSmalltalk)]
+pub fn derive_to_smalltalk(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_to_smalltalk(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.into_compile_error().into(),
+    }
+}
+
+#[proc_macro_derive(FromSmalltalk)]
+pub fn derive_from_smalltalk(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_from_smalltalk(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.into_compile_error().into(),
+    }
+}
+
+fn struct_fields<'a>(input: &'a DeriveInput, derive_name: &str) -> syn::Result<&'a Fields> {
+    match &input.data {
+        Data::Struct(data) => Ok(&data.fields),
+        _ => Err(syn::Error::new(
+            input.span(),
+            format!("{} can only be derived for structs", derive_name),
+        )),
+    }
+}
+
+/// Field accessors in the order of declaration, `self.name` for named fields and `self.0` for tuple fields
+fn field_members(fields: &Fields) -> Vec<syn::Member> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match &field.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(index.into()),
+        })
+        .collect()
+}
+
+fn expand_to_smalltalk(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = struct_fields(&input, "ToSmalltalk")?;
+    let members = field_members(fields);
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::vm_bindings::ToSmalltalk for #name #type_generics #where_clause {
+            fn to_smalltalk(&self) -> ::vm_bindings::anyhow::Result<::vm_bindings::ObjectPointer> {
+                ::vm_bindings::new_array_of(&[
+                    #(&self.#members as &dyn ::vm_bindings::ToSmalltalk),*
+                ])
+            }
+        }
+    })
+}
+
+fn expand_from_smalltalk(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = struct_fields(&input, "FromSmalltalk")?;
+    let members = field_members(fields);
+    let indices = 0..members.len();
+    let amount_of_fields = members.len();
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::vm_bindings::FromSmalltalk for #name #type_generics #where_clause {
+            fn from_smalltalk(object: ::vm_bindings::ObjectPointer) -> ::vm_bindings::anyhow::Result<Self> {
+                let items = ::vm_bindings::array_items(object, Some(#amount_of_fields))?;
+                Ok(Self {
+                    #(#members: ::vm_bindings::FromSmalltalk::from_smalltalk(items[#indices])?),*
+                })
+            }
+        }
+    })
+}
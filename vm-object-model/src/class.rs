@@ -0,0 +1,363 @@
+use crate::{AnyObjectRef, ByteStringRef, Error, Object, ObjectFormat, ObjectRef, Result};
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+
+/// The format and the amount of fixed slots of the instances of a class,
+/// as encoded in the format slot of a class: `(instSpec << 16) | instSize`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct InstanceSpecification {
+    pub format: ObjectFormat,
+    pub fixed_slots: usize,
+}
+
+impl InstanceSpecification {
+    const FORMAT_SHIFT: i64 = 16;
+    const FORMAT_MASK: i64 = 0x1F;
+    const FIXED_SLOTS_MASK: i64 = 0xFFFF;
+
+    pub fn new(format: ObjectFormat, fixed_slots: usize) -> Self {
+        Self {
+            format,
+            fixed_slots,
+        }
+    }
+
+    pub fn from_bits(bits: i64) -> Self {
+        Self {
+            format: ObjectFormat::from_bits(
+                ((bits >> Self::FORMAT_SHIFT) & Self::FORMAT_MASK) as u8,
+            ),
+            fixed_slots: (bits & Self::FIXED_SLOTS_MASK) as usize,
+        }
+    }
+
+    pub fn into_bits(self) -> i64 {
+        ((self.format.into_bits() as i64) << Self::FORMAT_SHIFT) | self.fixed_slots as i64
+    }
+}
+
+/// A view on a class (or a metaclass) object.
+/// In Spur the identity hash of a class is its index in the class table,
+/// so instances refer to their class through `class_index` of their header.
+///
+/// The first slots of a class are defined by Behavior: superclass, method dictionary,
+/// format and, in Pharo, the layout. The name of a class is its first byte symbol slot
+/// after those, a metaclass refers to its sole instance instead.
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct ClassRef(ObjectRef);
+
+impl ClassRef {
+    pub const SUPERCLASS_INDEX: usize = 0;
+    pub const METHOD_DICTIONARY_INDEX: usize = 1;
+    pub const INSTANCE_SPECIFICATION_INDEX: usize = 2;
+    pub const LAYOUT_INDEX: usize = 3;
+
+    /// The slot of a layout that holds its slot scope
+    const LAYOUT_SLOT_SCOPE_INDEX: usize = 1;
+    /// The slot of a Slot that holds its name
+    const SLOT_NAME_INDEX: usize = 0;
+
+    /// Return the index of this class in the class table
+    pub fn class_index(&self) -> u32 {
+        self.0.header().identity_hash()
+    }
+
+    /// Return true if a given object is a direct instance of this class
+    pub fn is_class_of(&self, object: impl Into<AnyObjectRef>) -> bool {
+        let object = object.into();
+        let class_index = match object.as_immediate() {
+            Ok(immediate) => immediate.class_index(),
+            Err(_) => match object.as_object() {
+                Ok(object) => object.follow_forwarded().header().class_index(),
+                Err(_) => return false,
+            },
+        };
+        class_index == self.class_index()
+    }
+
+    pub fn superclass(&self) -> Option<ClassRef> {
+        let superclass = self.0.inst_var_at(Self::SUPERCLASS_INDEX)?;
+        ClassRef::try_from(superclass).ok()
+    }
+
+    /// Return the chain of superclasses, starting from the direct superclass
+    pub fn superclasses(&self) -> impl Iterator<Item = ClassRef> {
+        std::iter::successors(self.superclass(), |each| each.superclass())
+    }
+
+    /// Return true if this class is a given class or one of its subclasses
+    pub fn includes_behavior(&self, class: &ClassRef) -> bool {
+        self.class_index() == class.class_index()
+            || self
+                .superclasses()
+                .any(|each| each.class_index() == class.class_index())
+    }
+
+    /// Fails if the class has no method dictionary slot
+    pub fn method_dictionary(&self) -> Result<AnyObjectRef> {
+        self.0
+            .inst_var_at(Self::METHOD_DICTIONARY_INDEX)
+            .ok_or_else(|| Error::InvalidType("method dictionary".to_string()))
+    }
+
+    /// Fails if the format slot of the class is missing or is not a SmallInteger
+    pub fn instance_specification(&self) -> Result<InstanceSpecification> {
+        self.0
+            .inst_var_at(Self::INSTANCE_SPECIFICATION_INDEX)
+            .and_then(|each| each.as_integer())
+            .map(InstanceSpecification::from_bits)
+            .ok_or_else(|| Error::InvalidType("instance specification".to_string()))
+    }
+
+    pub fn instance_format(&self) -> Result<ObjectFormat> {
+        Ok(self.instance_specification()?.format)
+    }
+
+    pub fn amount_of_fixed_slots(&self) -> Result<usize> {
+        Ok(self.instance_specification()?.fixed_slots)
+    }
+
+    /// Return the name of the class, or `Foo class` for a metaclass.
+    /// Anonymous classes and classes without a name slot have no name
+    pub fn name(&self) -> Option<String> {
+        if let Some(name) = self.own_name() {
+            return Some(name);
+        }
+
+        self.this_class()
+            .and_then(|class| class.own_name())
+            .map(|name| format!("{} class", name))
+    }
+
+    /// Return true if this class has no name of its own but refers to its sole instance
+    pub fn is_metaclass(&self) -> bool {
+        self.own_name().is_none() && self.this_class().is_some()
+    }
+
+    /// Return the names of the instance variables defined by this class (not including inherited ones),
+    /// as stored in the slot scope of the class layout
+    pub fn instance_variable_names(&self) -> Vec<String> {
+        let inherited_slots = self
+            .superclass()
+            .and_then(|superclass| superclass.amount_of_fixed_slots().ok())
+            .unwrap_or(0);
+        let own_slots = self
+            .amount_of_fixed_slots()
+            .unwrap_or(0)
+            .saturating_sub(inherited_slots);
+
+        if own_slots == 0 {
+            return vec![];
+        }
+
+        let Some(scope) = self
+            .0
+            .inst_var_at(Self::LAYOUT_INDEX)
+            .and_then(|layout| layout.as_object().ok())
+            .and_then(|layout| layout.inst_var_at(Self::LAYOUT_SLOT_SCOPE_INDEX))
+            .and_then(|scope| scope.as_object().ok())
+        else {
+            return vec![];
+        };
+
+        // the slots are the last indexable fields of the scope
+        let scope_size = scope.amount_of_slots();
+        (scope_size.saturating_sub(own_slots)..scope_size)
+            .filter_map(|index| scope.inst_var_at(index))
+            .filter_map(|slot| slot.as_object().ok())
+            .filter_map(|slot| slot.inst_var_at(Self::SLOT_NAME_INDEX))
+            .filter_map(|name| ByteStringRef::try_from(name).ok())
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    /// Return the names of all instance variables, starting with the inherited ones
+    pub fn all_instance_variable_names(&self) -> Vec<String> {
+        let mut names = self
+            .superclass()
+            .map(|superclass| superclass.all_instance_variable_names())
+            .unwrap_or_default();
+        names.extend(self.instance_variable_names());
+        names
+    }
+
+    fn own_name(&self) -> Option<String> {
+        self.slots_after_layout()
+            .find_map(|each| ByteStringRef::try_from(each).ok())
+            .map(|name| name.to_string())
+    }
+
+    /// A metaclass refers to its sole instance (`thisClass`)
+    fn this_class(&self) -> Option<ClassRef> {
+        self.slots_after_layout()
+            .filter_map(|each| ClassRef::try_from(each).ok())
+            .find(|each| each.own_name().is_some())
+    }
+
+    fn slots_after_layout(&self) -> impl Iterator<Item = AnyObjectRef> + '_ {
+        (Self::LAYOUT_INDEX + 1..self.0.amount_of_slots())
+            .filter_map(|index| self.0.inst_var_at(index))
+    }
+}
+
+impl Deref for ClassRef {
+    type Target = Object;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Debug for ClassRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClassRef")
+            .field("name", &self.name())
+            .field("class_index", &self.class_index())
+            .field(
+                "instance_specification",
+                &self.instance_specification().ok(),
+            )
+            .finish()
+    }
+}
+
+/// An object is considered to be a class if it is a non-indexable object
+/// with a superclass, a method dictionary and a valid format
+impl TryFrom<AnyObjectRef> for ClassRef {
+    type Error = Error;
+
+    fn try_from(value: AnyObjectRef) -> Result<Self> {
+        let object = value.as_object()?.follow_forwarded();
+
+        if object.object_format() != ObjectFormat::NonIndexable
+            || object.amount_of_slots() <= Self::INSTANCE_SPECIFICATION_INDEX
+        {
+            return Err(Error::InvalidType("Class".to_string()));
+        }
+
+        let is_valid_specification = object
+            .inst_var_at(Self::INSTANCE_SPECIFICATION_INDEX)
+            .and_then(|each| each.as_integer())
+            .map(|bits| {
+                !matches!(
+                    InstanceSpecification::from_bits(bits).format,
+                    ObjectFormat::Unsupported(_) | ObjectFormat::Forwarded
+                )
+            })
+            .unwrap_or(false);
+
+        let has_pointer_slots = object
+            .inst_var_at(Self::SUPERCLASS_INDEX)
+            .map(|each| !each.is_immediate())
+            .unwrap_or(false)
+            && object
+                .inst_var_at(Self::METHOD_DICTIONARY_INDEX)
+                .map(|each| !each.is_immediate())
+                .unwrap_or(false);
+
+        if !is_valid_specification || !has_pointer_slots {
+            return Err(Error::InvalidType("Class".to_string()));
+        }
+
+        Ok(Self(object))
+    }
+}
+
+impl From<ClassRef> for AnyObjectRef {
+    fn from(value: ClassRef) -> Self {
+        value.0.into()
+    }
+}
+
+impl From<ClassRef> for ObjectRef {
+    fn from(value: ClassRef) -> Self {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Immediate, TestHeap};
+
+    #[test]
+    fn instance_specification() {
+        let specification = InstanceSpecification::new(ObjectFormat::IndexableWithInstVars, 6);
+        assert_eq!(specification.into_bits(), (3 << 16) | 6);
+        assert_eq!(
+            InstanceSpecification::from_bits(specification.into_bits()),
+            specification
+        );
+    }
+
+    #[test]
+    fn class_metadata() {
+        let heap = TestHeap::new();
+        let object_class = heap.new_named_class("Object", None, ObjectFormat::NonIndexable, &[]);
+        let point_class = heap.new_named_class(
+            "Point",
+            Some(object_class),
+            ObjectFormat::NonIndexable,
+            &["x", "y"],
+        );
+        let point_3d_class = heap.new_named_class(
+            "Point3D",
+            Some(point_class),
+            ObjectFormat::NonIndexable,
+            &["z"],
+        );
+
+        let point_3d = ClassRef::try_from(AnyObjectRef::from(point_3d_class)).unwrap();
+        assert_eq!(point_3d.name().as_deref(), Some("Point3D"));
+        assert_eq!(
+            point_3d.superclass().unwrap().name().as_deref(),
+            Some("Point")
+        );
+        assert_eq!(
+            point_3d
+                .superclasses()
+                .filter_map(|each| each.name())
+                .collect::<Vec<_>>(),
+            vec!["Point", "Object"]
+        );
+        assert_eq!(
+            point_3d.instance_specification().unwrap(),
+            InstanceSpecification::new(ObjectFormat::NonIndexable, 3)
+        );
+        assert_eq!(point_3d.instance_variable_names(), vec!["z"]);
+        assert_eq!(point_3d.all_instance_variable_names(), vec!["x", "y", "z"]);
+
+        let point = ClassRef::try_from(AnyObjectRef::from(point_class)).unwrap();
+        assert!(point_3d.includes_behavior(&point));
+        assert!(!point.includes_behavior(&point_3d));
+
+        let instance = heap.instantiate(point_3d_class);
+        assert!(point_3d.is_class_of(instance));
+        assert!(!point.is_class_of(instance));
+        assert!(!point.is_class_of(Immediate::new_i64(3)));
+    }
+
+    #[test]
+    fn not_a_class() {
+        let heap = TestHeap::new();
+        assert!(ClassRef::try_from(AnyObjectRef::from(heap.nil())).is_err());
+        assert!(ClassRef::try_from(AnyObjectRef::from(heap.new_byte_string("Object"))).is_err());
+        assert!(ClassRef::try_from(AnyObjectRef::from(Immediate::new_i64(42))).is_err());
+        assert!(ClassRef::try_from(AnyObjectRef::from(heap.class_array())).is_ok());
+    }
+
+    #[test]
+    fn malformed_class() {
+        let heap = TestHeap::new();
+        let mut object_class =
+            heap.new_named_class("Object", None, ObjectFormat::NonIndexable, &[]);
+        let class = ClassRef::try_from(AnyObjectRef::from(object_class)).unwrap();
+
+        object_class.inst_var_at_put_unchecked(ClassRef::INSTANCE_SPECIFICATION_INDEX, heap.nil());
+        assert!(class.instance_specification().is_err());
+        assert!(class.amount_of_fixed_slots().is_err());
+        assert!(class.instance_variable_names().is_empty());
+        assert!(format!("{:?}", class).contains("instance_specification: None"));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{ObjectFormat, ObjectHeader, RawObjectPointer};
+use crate::{Immediate, ImmediateValue, ObjectFormat, ObjectHeader, RawObjectPointer};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -7,12 +7,26 @@ pub enum Error {
     NotAnObject(RawObjectPointer),
     #[error("Expected an immediate value, found an object {0:?}")]
     NotAnImmediate(RawObjectPointer),
+    #[error("Immediate value {0:?} has an unsupported tag")]
+    UnsupportedImmediate(Immediate),
+    #[error("{0:?} can not be represented as an immediate value")]
+    NotRepresentableAsImmediate(ImmediateValue),
     #[error("Expected an array, got {0:?} instead")]
     NotAnArray(ObjectFormat),
     #[error("Forwarded object ({0:?}) is not supported for this operation")]
     ForwardedUnsupported(ObjectHeader),
+    #[error("Object {0:?} is immutable")]
+    Immutable(ObjectHeader),
+    #[error("Object {0:?} is not pinned")]
+    NotPinned(ObjectHeader),
     #[error("Expected an object of type {0}")]
     InvalidType(String),
+    #[error("Index {index} is out of bounds of {size} elements")]
+    IndexOutOfBounds { index: usize, size: usize },
+    #[error("Hashed collection {0:?} has no empty slot")]
+    NoEmptySlot(ObjectHeader),
+    #[error("Dictionary {0:?} already includes the key")]
+    DuplicateKey(ObjectHeader),
     #[error(
         "Object {object:?} has a wrong amount of slots; expected {expected} but got {actual}."
     )]
@@ -21,6 +35,12 @@ pub enum Error {
         expected: usize,
         actual: usize,
     },
+    #[error("Unsupported image format {0}, only 64-bit Spur images can be read")]
+    UnsupportedImageFormat(u32),
+    #[error("Invalid image file: {0}")]
+    InvalidImage(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
 }
 
 pub type Result<T> = core::result::Result<T, Error>;
@@ -0,0 +1,577 @@
+use crate::{Immediate, Object, ObjectFormat, ObjectHeader, RawObjectPointer};
+use std::fmt::{Display, Formatter};
+use std::ptr::with_exposed_provenance;
+
+const WORD_SIZE: usize = size_of::<u64>();
+const NUM_SLOTS_OVERFLOW: u64 = 0xFF;
+const FREE_CHUNK_CLASS_INDEX: u32 = 0;
+const FORWARDED_CLASS_INDEX_PUN: u32 = 8;
+/// Class indices below this one are puns used by the vm for hidden objects
+/// (segment bridges, class table pages, remembered set, etc.)
+const FIRST_CLASS_INDEX: u32 = 32;
+/// A method header is a SmallInteger with the amount of literals in the lower bits
+const METHOD_LITERAL_COUNT_MASK: i64 = 0x7FFF;
+/// Only the first issues are kept in the report, the rest are only counted
+const MAX_REPORTED_ISSUES: usize = 100;
+
+/// A contiguous range of the object memory in which objects are allocated one after another,
+/// for example the used part of the old space or of eden.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct HeapSpace {
+    pub name: &'static str,
+    /// The address of the first object (or of its overflow header)
+    pub start: usize,
+    /// The address right after the last object
+    pub limit: usize,
+}
+
+impl HeapSpace {
+    pub fn new(name: &'static str, start: usize, limit: usize) -> Self {
+        Self { name, start, limit }
+    }
+
+    pub fn contains(&self, address: usize) -> bool {
+        address >= self.start && address < self.limit
+    }
+
+    pub fn size(&self) -> usize {
+        self.limit.saturating_sub(self.start)
+    }
+}
+
+/// A problem found by the heap verifier
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HeapIssueKind {
+    /// The class index of the object does not resolve to a class in the class table
+    UnknownClass(u32),
+    /// The format of the object is not a valid Spur format
+    InvalidFormat(u8),
+    /// The slots of the object go past the end of its space, the rest of the space can not be walked
+    SlotsExceedSpace { slots: usize },
+    /// A forwarder outside of garbage collection or become
+    Forwarder,
+    /// A slot holds an immediate with an unused tag
+    InvalidImmediate { slot: usize, value: i64 },
+    /// A slot points outside of all heap spaces
+    DanglingPointer { slot: usize, pointer: usize },
+    /// A slot points inside of a space, but not at the header of an object
+    PointerIntoObject { slot: usize, pointer: usize },
+    /// A slot points to a free chunk
+    PointerToFreeChunk { slot: usize, pointer: usize },
+}
+
+impl Display for HeapIssueKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownClass(class_index) => {
+                write!(f, "class index {} does not resolve to a class", class_index)
+            }
+            Self::InvalidFormat(format) => write!(f, "invalid object format {}", format),
+            Self::SlotsExceedSpace { slots } => {
+                write!(f, "{} slots do not fit in the space", slots)
+            }
+            Self::Forwarder => write!(f, "forwarder survived outside of GC"),
+            Self::InvalidImmediate { slot, value } => {
+                write!(f, "slot {} holds an invalid immediate {:#x}", slot, value)
+            }
+            Self::DanglingPointer { slot, pointer } => {
+                write!(
+                    f,
+                    "slot {} points outside of the heap to {:#x}",
+                    slot, pointer
+                )
+            }
+            Self::PointerIntoObject { slot, pointer } => {
+                write!(
+                    f,
+                    "slot {} points into the middle of an object at {:#x}",
+                    slot, pointer
+                )
+            }
+            Self::PointerToFreeChunk { slot, pointer } => {
+                write!(f, "slot {} points to a free chunk at {:#x}", slot, pointer)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct HeapIssue {
+    pub space: &'static str,
+    /// The address of the object's header
+    pub address: usize,
+    pub header: ObjectHeader,
+    pub kind: HeapIssueKind,
+}
+
+impl HeapIssue {
+    fn new(space: &HeapSpace, object: &Object, kind: HeapIssueKind) -> Self {
+        Self {
+            space: space.name,
+            address: object.as_ptr() as usize,
+            header: *object.header(),
+            kind,
+        }
+    }
+}
+
+impl Display for HeapIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:#x} (class index {}, format {}): {}",
+            self.space,
+            self.address,
+            self.header.class_index(),
+            self.header.format().into_bits(),
+            self.kind
+        )
+    }
+}
+
+/// The result of a heap verification
+#[derive(Debug, Clone, Default)]
+pub struct HeapReport {
+    pub spaces: Vec<HeapSpace>,
+    pub objects: usize,
+    pub free_chunks: usize,
+    pub hidden_objects: usize,
+    /// The first found issues, at most `MAX_REPORTED_ISSUES`
+    pub issues: Vec<HeapIssue>,
+    /// The total amount of found issues
+    pub amount_of_issues: usize,
+}
+
+impl HeapReport {
+    pub fn is_ok(&self) -> bool {
+        self.amount_of_issues == 0
+    }
+
+    fn add_issue(&mut self, issue: HeapIssue) {
+        if self.issues.len() < MAX_REPORTED_ISSUES {
+            self.issues.push(issue);
+        }
+        self.amount_of_issues += 1;
+    }
+}
+
+impl Display for HeapReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Heap verification: {} objects, {} free chunks, {} hidden objects",
+            self.objects, self.free_chunks, self.hidden_objects
+        )?;
+        for space in &self.spaces {
+            writeln!(
+                f,
+                "  {}: {:#x} - {:#x} ({} bytes)",
+                space.name,
+                space.start,
+                space.limit,
+                space.size()
+            )?;
+        }
+        if self.is_ok() {
+            return write!(f, "No corruption found");
+        }
+        write!(f, "Found {} issue(s)", self.amount_of_issues)?;
+        for issue in &self.issues {
+            write!(f, "\n  {}", issue)?;
+        }
+        if self.amount_of_issues > self.issues.len() {
+            write!(
+                f,
+                "\n  ... and {} more",
+                self.amount_of_issues - self.issues.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Walks every object of the given heap spaces and validates its header and pointer slots.
+/// Must be run while the heap is not being modified, e.g. from a primitive
+/// or before the interpreter starts.
+#[derive(Debug, Clone)]
+pub struct HeapVerifier {
+    spaces: Vec<HeapSpace>,
+}
+
+impl HeapVerifier {
+    pub fn new(spaces: Vec<HeapSpace>) -> Self {
+        Self { spaces }
+    }
+
+    /// Verify the heap. `is_class_index` answers whether a class index resolves to a class in the class table.
+    pub fn verify(&self, is_class_index: impl Fn(u32) -> bool) -> HeapReport {
+        let mut report = HeapReport {
+            spaces: self.spaces.clone(),
+            ..Default::default()
+        };
+
+        // the first pass validates headers and remembers where objects start,
+        // so that the second pass can check pointers to objects that come later in the heap
+        let walks = self
+            .spaces
+            .iter()
+            .map(|space| self.verify_headers(space, &is_class_index, &mut report))
+            .collect::<Vec<_>>();
+
+        for (space, walk) in self.spaces.iter().zip(walks.iter()) {
+            for object in SpaceObjects::new(space.start, walk.limit) {
+                self.verify_slots(space, object, &walks, &mut report);
+            }
+        }
+
+        report
+    }
+
+    fn verify_headers(
+        &self,
+        space: &HeapSpace,
+        is_class_index: &impl Fn(u32) -> bool,
+        report: &mut HeapReport,
+    ) -> SpaceWalk {
+        let mut walk = SpaceWalk::new(space);
+        let mut objects = SpaceObjects::new(space.start, space.limit);
+
+        while let Some(object) = objects.next() {
+            if objects.position > space.limit {
+                report.add_issue(HeapIssue::new(
+                    space,
+                    object,
+                    HeapIssueKind::SlotsExceedSpace {
+                        slots: object.amount_of_slots_unchecked(),
+                    },
+                ));
+                break;
+            }
+            walk.limit = objects.position;
+
+            match object.header().class_index() {
+                FREE_CHUNK_CLASS_INDEX => {
+                    report.free_chunks += 1;
+                    continue;
+                }
+                FORWARDED_CLASS_INDEX_PUN => {
+                    report.add_issue(HeapIssue::new(space, object, HeapIssueKind::Forwarder))
+                }
+                class_index if class_index < FIRST_CLASS_INDEX => report.hidden_objects += 1,
+                class_index => {
+                    report.objects += 1;
+                    if let ObjectFormat::Unsupported(format) = object.object_format() {
+                        report.add_issue(HeapIssue::new(
+                            space,
+                            object,
+                            HeapIssueKind::InvalidFormat(format),
+                        ));
+                    }
+                    if !is_class_index(class_index) {
+                        report.add_issue(HeapIssue::new(
+                            space,
+                            object,
+                            HeapIssueKind::UnknownClass(class_index),
+                        ));
+                    }
+                }
+            }
+            walk.mark_object_start(object.as_ptr() as usize);
+        }
+
+        walk
+    }
+
+    fn verify_slots(
+        &self,
+        space: &HeapSpace,
+        object: &Object,
+        walks: &[SpaceWalk],
+        report: &mut HeapReport,
+    ) {
+        if object.header().class_index() < FIRST_CLASS_INDEX {
+            return;
+        }
+
+        for slot in 0..pointer_slots(object) {
+            let value = slot_at(object, slot);
+            let kind = if RawObjectPointer::new(value).is_immediate() {
+                (!is_valid_immediate(value))
+                    .then_some(HeapIssueKind::InvalidImmediate { slot, value })
+            } else {
+                verify_pointer(slot, value as usize, walks)
+            };
+
+            if let Some(kind) = kind {
+                report.add_issue(HeapIssue::new(space, object, kind));
+            }
+        }
+    }
+}
+
+/// In 64-bit Spur only SmallInteger, Character and SmallFloat64 tags are used
+fn is_valid_immediate(value: i64) -> bool {
+    matches!(Immediate(value).class_index(), 1 | 2 | 4)
+}
+
+fn verify_pointer(slot: usize, pointer: usize, walks: &[SpaceWalk]) -> Option<HeapIssueKind> {
+    let Some(walk) = walks.iter().find(|each| each.contains(pointer)) else {
+        return Some(HeapIssueKind::DanglingPointer { slot, pointer });
+    };
+    if !walk.is_object_start(pointer) {
+        let target = unsafe { &*with_exposed_provenance::<Object>(pointer) };
+        return Some(
+            if pointer.is_multiple_of(WORD_SIZE)
+                && target.header().class_index() == FREE_CHUNK_CLASS_INDEX
+            {
+                HeapIssueKind::PointerToFreeChunk { slot, pointer }
+            } else {
+                HeapIssueKind::PointerIntoObject { slot, pointer }
+            },
+        );
+    }
+    None
+}
+
+/// Return the amount of slots that hold object pointers or immediates
+fn pointer_slots(object: &Object) -> usize {
+    match object.object_format() {
+        ObjectFormat::NonIndexable
+        | ObjectFormat::IndexableWithoutInstVars
+        | ObjectFormat::IndexableWithInstVars
+        | ObjectFormat::WeakIndexable
+        | ObjectFormat::WeakNonIndexable => object.amount_of_slots_unchecked(),
+        ObjectFormat::CompiledMethod(_) => {
+            // the header of a jitted method points to its machine code method,
+            // the literals of such methods are not verified
+            match Immediate(slot_at(object, 0)).as_integer() {
+                Some(header) => (1 + (header & METHOD_LITERAL_COUNT_MASK) as usize)
+                    .min(object.amount_of_slots_unchecked()),
+                None => 0,
+            }
+        }
+        _ => 0,
+    }
+}
+
+fn slot_at(object: &Object, index: usize) -> i64 {
+    unsafe { *(object.first_fixed_field_ptr() as *const i64).add(index) }
+}
+
+/// The part of a space that was walked in the first pass and the addresses of object headers in it
+struct SpaceWalk {
+    start: usize,
+    limit: usize,
+    /// A bit per word of the space, set if an object header is at that word
+    object_starts: Vec<u64>,
+}
+
+impl SpaceWalk {
+    fn new(space: &HeapSpace) -> Self {
+        let words = space.size() / WORD_SIZE;
+        Self {
+            start: space.start,
+            limit: space.start,
+            object_starts: vec![0; words.div_ceil(64)],
+        }
+    }
+
+    fn contains(&self, address: usize) -> bool {
+        address >= self.start && address < self.limit
+    }
+
+    fn mark_object_start(&mut self, address: usize) {
+        let word = (address - self.start) / WORD_SIZE;
+        self.object_starts[word / 64] |= 1 << (word % 64);
+    }
+
+    fn is_object_start(&self, address: usize) -> bool {
+        if !address.is_multiple_of(WORD_SIZE) {
+            return false;
+        }
+        let word = (address - self.start) / WORD_SIZE;
+        self.object_starts[word / 64] & (1 << (word % 64)) != 0
+    }
+}
+
+/// Iterates over objects laid out one after another in memory
+struct SpaceObjects<'heap> {
+    /// The address right after the last returned object
+    position: usize,
+    limit: usize,
+    phantom: std::marker::PhantomData<&'heap Object>,
+}
+
+impl SpaceObjects<'_> {
+    fn new(start: usize, limit: usize) -> Self {
+        Self {
+            position: start,
+            limit,
+            phantom: Default::default(),
+        }
+    }
+}
+
+impl<'heap> Iterator for SpaceObjects<'heap> {
+    type Item = &'heap Object;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position + size_of::<ObjectHeader>() > self.limit {
+            return None;
+        }
+
+        // objects with more than 254 slots are preceded by an overflow word
+        // which has its num slots byte set to 255
+        let first_word = unsafe { *with_exposed_provenance::<u64>(self.position) };
+        let header_address = if first_word >> 56 == NUM_SLOTS_OVERFLOW {
+            self.position + WORD_SIZE
+        } else {
+            self.position
+        };
+
+        if header_address + size_of::<ObjectHeader>() > self.limit {
+            self.position = self.limit;
+            return None;
+        }
+
+        let object = unsafe { &*with_exposed_provenance::<Object>(header_address) };
+        // every object has at least one slot
+        self.position = object
+            .amount_of_slots_unchecked()
+            .max(1)
+            .saturating_mul(WORD_SIZE)
+            .saturating_add(header_address + size_of::<ObjectHeader>());
+        Some(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NIL_CLASS: u32 = 40;
+    const ARRAY_CLASS: u32 = 41;
+
+    fn header_word(class_index: u32, format: ObjectFormat, num_slots: u8) -> u64 {
+        ObjectHeader::new()
+            .with_class_index(class_index)
+            .with_format(format)
+            .with_num_slots(num_slots)
+            .into_bits()
+    }
+
+    fn verify(words: &[u64]) -> HeapReport {
+        let start = words.as_ptr() as usize;
+        HeapVerifier::new(vec![HeapSpace::new(
+            "old space",
+            start,
+            start + words.len() * WORD_SIZE,
+        )])
+        .verify(|class_index| class_index == NIL_CLASS || class_index == ARRAY_CLASS)
+    }
+
+    #[test]
+    fn valid_heap() {
+        let mut words = vec![
+            header_word(NIL_CLASS, ObjectFormat::ZeroSized, 0),
+            0,
+            header_word(FREE_CHUNK_CLASS_INDEX, ObjectFormat::ZeroSized, 1),
+            0,
+            header_word(ARRAY_CLASS, ObjectFormat::IndexableWithoutInstVars, 3),
+            0,
+            Immediate::new_i64(42).0 as u64,
+            0,
+            // a hidden object is not verified
+            header_word(16, ObjectFormat::IndexableWithoutInstVars, 1),
+            0xBAD,
+        ];
+        let start = words.as_ptr() as u64;
+        // the array points to nil and to itself
+        words[5] = start;
+        words[7] = start + 32;
+
+        let report = verify(&words);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.objects, 2);
+        assert_eq!(report.free_chunks, 1);
+        assert_eq!(report.hidden_objects, 1);
+    }
+
+    #[test]
+    fn unknown_class_and_forwarder() {
+        let words = vec![
+            header_word(1234, ObjectFormat::ZeroSized, 0),
+            0,
+            header_word(FORWARDED_CLASS_INDEX_PUN, ObjectFormat::Forwarded, 1),
+            0,
+        ];
+        let report = verify(&words);
+        let kinds = report
+            .issues
+            .iter()
+            .map(|each| each.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![HeapIssueKind::UnknownClass(1234), HeapIssueKind::Forwarder]
+        );
+    }
+
+    #[test]
+    fn invalid_pointers() {
+        let mut words = vec![
+            header_word(ARRAY_CLASS, ObjectFormat::IndexableWithoutInstVars, 4),
+            0x10,
+            7,
+            0,
+            0,
+            header_word(FREE_CHUNK_CLASS_INDEX, ObjectFormat::ZeroSized, 1),
+            0,
+        ];
+        let start = words.as_ptr() as u64;
+        words[3] = start + 8;
+        words[4] = start + 40;
+
+        let report = verify(&words);
+        let kinds = report
+            .issues
+            .iter()
+            .map(|each| each.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                HeapIssueKind::DanglingPointer {
+                    slot: 0,
+                    pointer: 0x10
+                },
+                HeapIssueKind::InvalidImmediate { slot: 1, value: 7 },
+                HeapIssueKind::PointerIntoObject {
+                    slot: 2,
+                    pointer: start as usize + 8
+                },
+                HeapIssueKind::PointerToFreeChunk {
+                    slot: 3,
+                    pointer: start as usize + 40
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn slots_exceed_space() {
+        let words = vec![
+            header_word(NIL_CLASS, ObjectFormat::ZeroSized, 0),
+            0,
+            header_word(ARRAY_CLASS, ObjectFormat::IndexableWithoutInstVars, 10),
+            0,
+        ];
+        let report = verify(&words);
+        assert_eq!(report.objects, 1);
+        assert_eq!(
+            report.issues[0].kind,
+            HeapIssueKind::SlotsExceedSpace { slots: 10 }
+        );
+        assert!(report.to_string().contains("Found 1 issue(s)"));
+    }
+}
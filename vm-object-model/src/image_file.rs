@@ -0,0 +1,706 @@
+use crate::{Error, Immediate, ObjectFormat, ObjectHeader, RawObjectPointer, Result};
+use std::path::Path;
+
+const WORD_SIZE: usize = size_of::<u64>();
+/// Every segment ends with a two word bridge to the next segment
+const BRIDGE_SIZE: usize = 2 * WORD_SIZE;
+const NUM_SLOTS_OVERFLOW: u64 = 0xFF;
+/// The slot count of an overflow word or a bridge, below the tag byte
+const SLOT_COUNT_MASK: u64 = 0x00FF_FFFF_FFFF_FFFF;
+
+/// Represents a header of a 64-bit Spur image file.
+/// | 4: imageFormat | 4: headerSize | 8: dataSize | 8: oldBaseAddress
+/// | 8: specialObjectsOop | 8: lastHash | 8: savedWindowSize | 8: headerFlags
+/// | 4: extraVMMemory | 2: numStackPages | 2: cogCodeSize | 4: edenBytes
+/// | 2: maxExtSemTabSize | 2: unused | 8: firstSegmentSize | 8: freeOldSpace
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ImageHeader {
+    pub image_format: ImageFormat,
+    pub header_size: usize,
+    pub data_size: usize,
+    pub old_base_address: usize,
+    pub special_objects_oop: RawObjectPointer,
+    pub last_hash: u64,
+    pub saved_window_size: u64,
+    pub header_flags: u64,
+    pub extra_vm_memory: u32,
+    pub num_stack_pages: u16,
+    pub cog_code_size: u16,
+    pub eden_bytes: u32,
+    pub max_ext_sem_tab_size: u16,
+    pub first_segment_size: usize,
+    pub free_old_space: usize,
+}
+
+impl ImageHeader {
+    /// The amount of bytes read from the beginning of the file,
+    /// the rest of the header (up to `header_size`) is padding.
+    const SIZE: usize = 88;
+
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < Self::SIZE {
+            return Err(Error::InvalidImage(format!(
+                "File is too small to contain an image header: {} bytes",
+                bytes.len()
+            )));
+        }
+
+        let image_format = ImageFormat::new(read_u32(bytes, 0));
+        if !image_format.is_spur_64_bit() {
+            return Err(Error::UnsupportedImageFormat(image_format.version()));
+        }
+
+        let header = Self {
+            image_format,
+            header_size: read_u32(bytes, 4) as usize,
+            data_size: read_u64(bytes, 8) as usize,
+            old_base_address: read_u64(bytes, 16) as usize,
+            special_objects_oop: RawObjectPointer::new(read_u64(bytes, 24) as i64),
+            last_hash: read_u64(bytes, 32),
+            saved_window_size: read_u64(bytes, 40),
+            header_flags: read_u64(bytes, 48),
+            extra_vm_memory: read_u32(bytes, 56),
+            num_stack_pages: read_u16(bytes, 60),
+            cog_code_size: read_u16(bytes, 62),
+            eden_bytes: read_u32(bytes, 64),
+            max_ext_sem_tab_size: read_u16(bytes, 68),
+            first_segment_size: read_u64(bytes, 72) as usize,
+            free_old_space: read_u64(bytes, 80) as usize,
+        };
+
+        if header.header_size < Self::SIZE || !header.header_size.is_multiple_of(WORD_SIZE) {
+            return Err(Error::InvalidImage(format!(
+                "Invalid header size: {}",
+                header.header_size
+            )));
+        }
+
+        Ok(header)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct ImageFormat(u32);
+
+impl ImageFormat {
+    /// 64-bit Spur image format
+    pub const SPUR_64_BIT: u32 = 68021;
+    /// Set when the image uses more than one bytecode set (Sista)
+    pub const MULTIPLE_BYTECODE_SETS_FLAG: u32 = 512;
+
+    pub fn new(version: u32) -> Self {
+        Self(version)
+    }
+
+    pub fn version(&self) -> u32 {
+        self.0
+    }
+
+    pub fn is_spur_64_bit(&self) -> bool {
+        self.0 & !Self::MULTIPLE_BYTECODE_SETS_FLAG == Self::SPUR_64_BIT
+    }
+
+    pub fn uses_multiple_bytecode_sets(&self) -> bool {
+        self.0 & Self::MULTIPLE_BYTECODE_SETS_FLAG != 0
+    }
+}
+
+/// A segment of the old space as it was saved in the image file.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ImageSegment {
+    /// The address of the segment in the memory of the vm that saved the image
+    pub saved_start: usize,
+    /// The offset of the segment's first byte within the image file
+    pub file_offset: usize,
+    /// The size of the segment in bytes, including the bridge
+    pub size: usize,
+}
+
+impl ImageSegment {
+    pub fn saved_limit(&self) -> usize {
+        self.saved_start + self.size
+    }
+
+    pub fn contains_saved_address(&self, address: usize) -> bool {
+        address >= self.saved_start && address < self.saved_limit()
+    }
+
+    /// Return the file offset of the first byte after the last object (where the bridge starts)
+    fn objects_limit(&self) -> usize {
+        self.file_offset + self.size - BRIDGE_SIZE
+    }
+}
+
+/// A Spur image file loaded in memory that can be inspected without booting the vm.
+pub struct ImageFile {
+    header: ImageHeader,
+    segments: Vec<ImageSegment>,
+    bytes: Vec<u8>,
+    length: usize,
+}
+
+impl ImageFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let header = ImageHeader::parse(bytes)?;
+
+        let mut image = Self {
+            header,
+            segments: vec![],
+            bytes: bytes.to_vec(),
+            length: bytes.len(),
+        };
+        image.segments = image.read_segments()?;
+        Ok(image)
+    }
+
+    pub fn header(&self) -> &ImageHeader {
+        &self.header
+    }
+
+    pub fn segments(&self) -> &[ImageSegment] {
+        self.segments.as_slice()
+    }
+
+    pub fn special_objects_array(&self) -> Option<ImageObject<'_>> {
+        self.object_at(self.relocate(self.header.special_objects_oop)?)
+    }
+
+    /// Translate a pointer as it was saved in the image into an offset within the file.
+    /// Return None for immediates or pointers outside of the saved segments
+    pub fn relocate(&self, pointer: RawObjectPointer) -> Option<usize> {
+        if pointer.is_immediate() {
+            return None;
+        }
+        let address = usize::try_from(pointer.as_i64()).ok()?;
+        self.segments
+            .iter()
+            .find(|segment| segment.contains_saved_address(address))
+            .map(|segment| segment.file_offset + (address - segment.saved_start))
+    }
+
+    /// Return an object whose header is located at a given file offset.
+    /// The slots of the object are only checked to fit in the file when they are accessed
+    pub fn object_at(&self, offset: usize) -> Option<ImageObject<'_>> {
+        // an object with an overflow word needs room for it before the header
+        if !offset.is_multiple_of(WORD_SIZE)
+            || offset < WORD_SIZE
+            || offset + WORD_SIZE > self.length
+        {
+            return None;
+        }
+        Some(ImageObject {
+            image: self,
+            offset,
+        })
+    }
+
+    /// Iterate over all objects of all segments in the order they are laid out in the file.
+    /// Free chunks are skipped.
+    pub fn objects(&self) -> impl Iterator<Item = ImageObject<'_>> {
+        self.segments
+            .iter()
+            .flat_map(|segment| self.segment_objects(segment))
+    }
+
+    /// Iterate over all objects within a given segment. Free chunks are skipped.
+    pub fn segment_objects<'image>(
+        &'image self,
+        segment: &ImageSegment,
+    ) -> impl Iterator<Item = ImageObject<'image>> {
+        ImageSegmentObjects {
+            image: self,
+            position: segment.file_offset,
+            limit: segment.objects_limit(),
+        }
+        .filter(|object| !object.is_free_chunk())
+    }
+
+    fn read_segments(&self) -> Result<Vec<ImageSegment>> {
+        let mut segments = vec![];
+
+        let mut segment = ImageSegment {
+            saved_start: self.header.old_base_address,
+            file_offset: self.header.header_size,
+            size: if self.header.first_segment_size == 0 {
+                self.header.data_size
+            } else {
+                self.header.first_segment_size
+            },
+        };
+
+        loop {
+            if segment.size < BRIDGE_SIZE
+                || !segment.size.is_multiple_of(WORD_SIZE)
+                || segment.file_offset + segment.size > self.length
+            {
+                return Err(Error::InvalidImage(format!(
+                    "Segment {:?} does not fit in the image file of {} bytes",
+                    segment, self.length
+                )));
+            }
+            segments.push(segment);
+
+            // the bridge is saved as the overflow word of a pseudo object whose slots span
+            // the gap between the end of the segment and the start of the next one,
+            // followed by the size of the next segment in bytes, or 0 after the last segment
+            let bridge = segment.objects_limit();
+            let bridge_span = (self.word_at(bridge) & SLOT_COUNT_MASK) as usize * WORD_SIZE;
+            let next_segment_size = self.word_at(bridge + WORD_SIZE) as usize;
+
+            if next_segment_size == 0 {
+                break;
+            }
+
+            segment = ImageSegment {
+                saved_start: segment.saved_start + segment.size + bridge_span,
+                file_offset: segment.file_offset + segment.size,
+                size: next_segment_size,
+            };
+        }
+
+        let total_size: usize = segments.iter().map(|segment| segment.size).sum();
+        if total_size != self.header.data_size {
+            return Err(Error::InvalidImage(format!(
+                "Segments occupy {} bytes, while the header declares {} bytes of data",
+                total_size, self.header.data_size
+            )));
+        }
+
+        Ok(segments)
+    }
+
+    fn word_at(&self, offset: usize) -> u64 {
+        read_u64(&self.bytes, offset)
+    }
+}
+
+/// An object that lives in the memory of the loaded image file.
+/// Only its header is known to be within the file, its slots are checked when they are read
+#[derive(Copy, Clone)]
+pub struct ImageObject<'image> {
+    image: &'image ImageFile,
+    offset: usize,
+}
+
+impl<'image> ImageObject<'image> {
+    /// Return the offset of the object's header within the image file
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn header(&self) -> ObjectHeader {
+        ObjectHeader::from_bits(self.image.word_at(self.offset))
+    }
+
+    pub fn object_format(&self) -> ObjectFormat {
+        self.header().format()
+    }
+
+    pub fn is_free_chunk(&self) -> bool {
+        self.header().class_index() == 0
+    }
+
+    /// Return the amount of slots as declared by the header, or by the overflow word that precedes it,
+    /// without checking that they fit in the image file
+    fn slot_count(&self) -> usize {
+        match self.header().num_slots() as u64 {
+            NUM_SLOTS_OVERFLOW => {
+                (self.image.word_at(self.offset - WORD_SIZE) & SLOT_COUNT_MASK) as usize
+            }
+            num_slots => num_slots as usize,
+        }
+    }
+
+    /// Return the amount of slots after checking that they fit in the image file,
+    /// which is not the case for truncated or corrupt files
+    pub fn amount_of_slots(&self) -> Result<usize> {
+        let slots = self.slot_count();
+        let fits = slots
+            .checked_mul(WORD_SIZE)
+            .and_then(|size| size.checked_add(self.offset + size_of::<ObjectHeader>()))
+            .is_some_and(|limit| limit <= self.image.length);
+
+        if fits {
+            Ok(slots)
+        } else {
+            Err(Error::InvalidImage(format!(
+                "Object at {} with {} slots does not fit in the image file of {} bytes",
+                self.offset, slots, self.image.length
+            )))
+        }
+    }
+
+    /// Return the amount of bytes the object occupies in the image including its header(s)
+    pub fn size_in_bytes(&self) -> usize {
+        let slots = self.slot_count();
+        let overflow_header = if slots >= NUM_SLOTS_OVERFLOW as usize {
+            WORD_SIZE
+        } else {
+            0
+        };
+        // every object has at least one slot
+        overflow_header + size_of::<ObjectHeader>() + slots.max(1) * WORD_SIZE
+    }
+
+    /// Return true if the slots of the object contain object pointers
+    pub fn has_pointer_slots(&self) -> bool {
+        matches!(
+            self.object_format(),
+            ObjectFormat::NonIndexable
+                | ObjectFormat::IndexableWithoutInstVars
+                | ObjectFormat::IndexableWithInstVars
+                | ObjectFormat::WeakIndexable
+                | ObjectFormat::WeakNonIndexable
+                | ObjectFormat::Forwarded
+        )
+    }
+
+    /// Return the raw value of a slot at a given index, as it was saved in the image,
+    /// or None if the object has no such slot
+    pub fn raw_slot_at(&self, index: usize) -> Result<Option<RawObjectPointer>> {
+        if index >= self.amount_of_slots()? {
+            return Ok(None);
+        }
+        Ok(Some(self.raw_slot_at_unchecked(index)))
+    }
+
+    fn raw_slot_at_unchecked(&self, index: usize) -> RawObjectPointer {
+        let offset = self.offset + size_of::<ObjectHeader>() + index * WORD_SIZE;
+        RawObjectPointer::new(self.image.word_at(offset) as i64)
+    }
+
+    /// Return a pointer slot at a given index with the object pointer relocated to the file offset
+    pub fn slot_at(&self, index: usize) -> Result<Option<ImageSlot>> {
+        if !self.has_pointer_slots() {
+            return Ok(None);
+        }
+        Ok(self
+            .raw_slot_at(index)?
+            .and_then(|pointer| self.relocate_slot(pointer)))
+    }
+
+    fn relocate_slot(&self, pointer: RawObjectPointer) -> Option<ImageSlot> {
+        Some(if pointer.is_immediate() {
+            ImageSlot::Immediate(Immediate::try_from(pointer).ok()?)
+        } else {
+            match self.image.relocate(pointer) {
+                Some(offset) => ImageSlot::Object(offset),
+                None => ImageSlot::Dangling(pointer),
+            }
+        })
+    }
+
+    /// Iterate over all pointer slots with object pointers relocated to file offsets.
+    /// Is empty for non-pointer objects (bytes, words, compiled methods)
+    pub fn slots(&self) -> Result<impl Iterator<Item = ImageSlot> + '_> {
+        let amount = if self.has_pointer_slots() {
+            self.amount_of_slots()?
+        } else {
+            0
+        };
+        Ok((0..amount).filter_map(|index| self.relocate_slot(self.raw_slot_at_unchecked(index))))
+    }
+
+    /// Return an object referenced by a slot at a given index
+    pub fn object_slot_at(&self, index: usize) -> Result<Option<ImageObject<'image>>> {
+        Ok(match self.slot_at(index)? {
+            Some(ImageSlot::Object(offset)) => self.image.object_at(offset),
+            _ => None,
+        })
+    }
+
+    /// Return the indexable bytes of the object.
+    pub fn bytes(&self) -> Result<&'image [u8]> {
+        let slots = self.amount_of_slots()?;
+        let length = match self.object_format() {
+            ObjectFormat::Indexable8(_) | ObjectFormat::CompiledMethod(_) => self
+                .object_format()
+                .amount_of_indexable_units(slots)
+                .min(slots * WORD_SIZE),
+            _ => slots * WORD_SIZE,
+        };
+        let start = self.offset + size_of::<ObjectHeader>();
+        Ok(&self.image.bytes[start..start + length])
+    }
+}
+
+impl std::fmt::Debug for ImageObject<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImageObject")
+            .field("offset", &self.offset)
+            .field("header", &self.header())
+            .finish()
+    }
+}
+
+/// The content of a pointer slot of an object in the image file
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ImageSlot {
+    Immediate(Immediate),
+    /// File offset of the referenced object
+    Object(usize),
+    /// The pointer does not point into any of the saved segments
+    Dangling(RawObjectPointer),
+}
+
+struct ImageSegmentObjects<'image> {
+    image: &'image ImageFile,
+    position: usize,
+    limit: usize,
+}
+
+impl<'image> Iterator for ImageSegmentObjects<'image> {
+    type Item = ImageObject<'image>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.limit {
+            return None;
+        }
+
+        // objects with more than 254 slots are preceded by an overflow word
+        // which has its num slots byte set to 255
+        let first_word = self.image.word_at(self.position);
+        let header_offset = if first_word >> 56 == NUM_SLOTS_OVERFLOW {
+            self.position + WORD_SIZE
+        } else {
+            self.position
+        };
+
+        if header_offset >= self.limit {
+            self.position = self.limit;
+            return None;
+        }
+
+        let object = self.image.object_at(header_offset)?;
+        // every object has at least one slot
+        self.position = object
+            .slot_count()
+            .max(1)
+            .checked_mul(WORD_SIZE)
+            .and_then(|size| size.checked_add(header_offset + size_of::<ObjectHeader>()))
+            .unwrap_or(self.limit);
+        Some(object)
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER_SIZE: usize = 128;
+    const OLD_BASE_ADDRESS: u64 = 0x10000000;
+    const SECOND_SEGMENT_ADDRESS: u64 = 0x20000000;
+
+    fn header_word(class_index: u32, format: ObjectFormat, num_slots: u8) -> u64 {
+        ObjectHeader::new()
+            .with_class_index(class_index)
+            .with_format(format)
+            .with_num_slots(num_slots)
+            .into_bits()
+    }
+
+    /// Return the bridge saved at the end of a segment: the overflow word of a pseudo object
+    /// spanning the gap up to the next segment, followed by the size of the next segment
+    fn bridge(span_in_bytes: u64, next_segment_size: u64) -> [u64; 2] {
+        [
+            (NUM_SLOTS_OVERFLOW << 56) | (span_in_bytes / WORD_SIZE as u64),
+            next_segment_size,
+        ]
+    }
+
+    /// Return an image with the given segments, the first one starting at `OLD_BASE_ADDRESS`
+    /// and the second one at `SECOND_SEGMENT_ADDRESS`
+    fn image_bytes(segments: &[Vec<u64>], special_objects_oop: u64) -> Vec<u8> {
+        let mut segment_words = segments.to_vec();
+        for (index, words) in segment_words.iter_mut().enumerate() {
+            let segment_limit = OLD_BASE_ADDRESS + ((words.len() + 2) * WORD_SIZE) as u64;
+            words.extend(match segments.get(index + 1) {
+                Some(next) => bridge(
+                    SECOND_SEGMENT_ADDRESS - segment_limit,
+                    ((next.len() + 2) * WORD_SIZE) as u64,
+                ),
+                None => bridge(0, 0),
+            });
+        }
+        let data_size: usize = segment_words
+            .iter()
+            .map(|each| each.len() * WORD_SIZE)
+            .sum();
+
+        let mut bytes = vec![0u8; HEADER_SIZE];
+        bytes[0..4].copy_from_slice(&(ImageFormat::SPUR_64_BIT + 512).to_le_bytes());
+        bytes[4..8].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        bytes[8..16].copy_from_slice(&(data_size as u64).to_le_bytes());
+        bytes[16..24].copy_from_slice(&OLD_BASE_ADDRESS.to_le_bytes());
+        bytes[24..32].copy_from_slice(&special_objects_oop.to_le_bytes());
+        bytes[72..80].copy_from_slice(&((segment_words[0].len() * WORD_SIZE) as u64).to_le_bytes());
+
+        for word in segment_words.iter().flatten() {
+            bytes.extend(word.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn read_objects_across_segments() {
+        let first_segment = vec![
+            // nil: zero sized, still occupies one slot
+            header_word(10, ObjectFormat::ZeroSized, 0),
+            0,
+            // free chunk
+            header_word(0, ObjectFormat::ZeroSized, 1),
+            0,
+            // array with nil, 42 and an object from the second segment
+            header_word(11, ObjectFormat::IndexableWithoutInstVars, 3),
+            OLD_BASE_ADDRESS,
+            Immediate::new_i64(42).0 as u64,
+            SECOND_SEGMENT_ADDRESS + 8,
+        ];
+
+        let mut second_segment = vec![
+            // overflow word of an object with 300 slots
+            0xFF00_0000_0000_0000 | 300,
+            header_word(12, ObjectFormat::IndexableWithoutInstVars, 255),
+        ];
+        second_segment.extend(vec![OLD_BASE_ADDRESS; 300]);
+        second_segment.extend([
+            header_word(13, ObjectFormat::Indexable8(16 + 5), 1),
+            0x6f6c6c6568,
+        ]);
+
+        let bytes = image_bytes(&[first_segment, second_segment], OLD_BASE_ADDRESS + 32);
+        let image = ImageFile::from_bytes(&bytes).unwrap();
+
+        assert!(image.header().image_format.uses_multiple_bytecode_sets());
+        assert_eq!(image.segments().len(), 2);
+        assert_eq!(
+            image.segments()[1].saved_start,
+            SECOND_SEGMENT_ADDRESS as usize
+        );
+
+        let objects = image.objects().collect::<Vec<_>>();
+        let class_indices = objects
+            .iter()
+            .map(|each| each.header().class_index())
+            .collect::<Vec<_>>();
+        assert_eq!(class_indices, vec![10, 11, 12, 13]);
+
+        let array = image.special_objects_array().unwrap();
+        assert_eq!(array.offset(), objects[1].offset());
+        assert_eq!(
+            array.slots().unwrap().collect::<Vec<_>>(),
+            vec![
+                ImageSlot::Object(HEADER_SIZE),
+                ImageSlot::Immediate(Immediate::new_i64(42)),
+                ImageSlot::Object(objects[2].offset()),
+            ]
+        );
+
+        let large_object = array.object_slot_at(2).unwrap().unwrap();
+        assert_eq!(large_object.amount_of_slots().unwrap(), 300);
+        assert_eq!(large_object.slots().unwrap().count(), 300);
+        assert_eq!(objects[3].bytes().unwrap(), b"hel");
+    }
+
+    #[test]
+    fn follow_segment_bridges() {
+        // the first segment is 4 words long including its bridge and is followed by a gap
+        // of 12 words, so the second segment starts 16 words after the first one
+        let first_segment = [
+            header_word(10, ObjectFormat::ZeroSized, 0),
+            0,
+            (NUM_SLOTS_OVERFLOW << 56) | 12,
+            6 * WORD_SIZE as u64,
+        ];
+        let second_segment = [
+            header_word(11, ObjectFormat::IndexableWithoutInstVars, 1),
+            OLD_BASE_ADDRESS,
+            header_word(12, ObjectFormat::IndexableWithoutInstVars, 1),
+            OLD_BASE_ADDRESS + 16 * WORD_SIZE as u64,
+            NUM_SLOTS_OVERFLOW << 56,
+            0,
+        ];
+
+        let mut bytes = vec![0u8; HEADER_SIZE];
+        bytes[0..4].copy_from_slice(&ImageFormat::SPUR_64_BIT.to_le_bytes());
+        bytes[4..8].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        bytes[8..16].copy_from_slice(&(10 * WORD_SIZE as u64).to_le_bytes());
+        bytes[16..24].copy_from_slice(&OLD_BASE_ADDRESS.to_le_bytes());
+        bytes[72..80].copy_from_slice(&(4 * WORD_SIZE as u64).to_le_bytes());
+        for word in first_segment.iter().chain(second_segment.iter()) {
+            bytes.extend(word.to_le_bytes());
+        }
+
+        let image = ImageFile::from_bytes(&bytes).unwrap();
+        let segments = image.segments();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(
+            segments[1].saved_start,
+            OLD_BASE_ADDRESS as usize + 16 * WORD_SIZE
+        );
+        assert_eq!(segments[1].file_offset, HEADER_SIZE + 4 * WORD_SIZE);
+        assert_eq!(segments[1].size, 6 * WORD_SIZE);
+
+        let objects = image.objects().collect::<Vec<_>>();
+        assert_eq!(objects.len(), 3);
+        assert_eq!(
+            objects[2].slot_at(0).unwrap(),
+            Some(ImageSlot::Object(objects[1].offset()))
+        );
+    }
+
+    #[test]
+    fn reject_slots_beyond_the_end_of_the_file() {
+        let segment = vec![
+            header_word(10, ObjectFormat::ZeroSized, 0),
+            0,
+            // claims to have 200 slots in a file that is much shorter
+            header_word(11, ObjectFormat::IndexableWithoutInstVars, 200),
+            OLD_BASE_ADDRESS,
+            header_word(12, ObjectFormat::Indexable8(16), 200),
+            0x6f6c6c6568,
+        ];
+        let bytes = image_bytes(&[segment], OLD_BASE_ADDRESS);
+        let image = ImageFile::from_bytes(&bytes).unwrap();
+
+        let array = image.object_at(HEADER_SIZE + 2 * WORD_SIZE).unwrap();
+        assert!(matches!(
+            array.amount_of_slots(),
+            Err(Error::InvalidImage(_))
+        ));
+        assert!(matches!(array.raw_slot_at(0), Err(Error::InvalidImage(_))));
+        assert!(matches!(array.slot_at(0), Err(Error::InvalidImage(_))));
+        assert!(array.slots().is_err());
+
+        let bytes_object = image.object_at(HEADER_SIZE + 4 * WORD_SIZE).unwrap();
+        assert!(matches!(bytes_object.bytes(), Err(Error::InvalidImage(_))));
+    }
+
+    #[test]
+    fn reject_non_spur_images() {
+        let mut bytes = image_bytes(&[vec![header_word(10, ObjectFormat::ZeroSized, 0), 0]], 0);
+        bytes[0..4].copy_from_slice(&6521u32.to_le_bytes());
+        assert!(matches!(
+            ImageFile::from_bytes(&bytes),
+            Err(Error::UnsupportedImageFormat(6521))
+        ));
+    }
+}
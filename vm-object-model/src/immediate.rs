@@ -8,8 +8,20 @@ pub struct Immediate(pub i64);
 
 impl Immediate {
     const SMALL_INTEGER_TAG: i64 = 1;
+    const CHARACTER_TAG: i64 = 2;
+    const SMALL_FLOAT_TAG: i64 = 4;
+    const TAG_MASK: i64 = 7;
     const NUMBER_TAG: i64 = 3;
 
+    pub const MIN_SMALL_INTEGER: i64 = -(1 << 60);
+    pub const MAX_SMALL_INTEGER: i64 = (1 << 60) - 1;
+    pub const MAX_CHARACTER_VALUE: u32 = 0x3FFFFFFF;
+
+    /// The difference between the exponent bias of a 64-bit float (1023)
+    /// and the bias of the 8-bit exponent stored in a SmallFloat64 (127)
+    const SMALL_FLOAT_EXPONENT_OFFSET: u64 = 896;
+    const SMALL_FLOAT_MANTISSA_BITS: u64 = 52;
+
     pub fn new_i64(value: i64) -> Self {
         let unsigned_value: u64 = unsafe { transmute(value) };
         Self::new_u64(unsigned_value)
@@ -19,10 +31,97 @@ impl Immediate {
         Self(unsafe { transmute((value << Self::NUMBER_TAG) + 1) })
     }
 
+    /// Return the index of the class of the immediate in the class table,
+    /// in Spur it is the same as the tag
+    pub fn class_index(&self) -> u32 {
+        (self.0 & Self::TAG_MASK) as u32
+    }
+
     pub fn is_small_integer(&self) -> bool {
         self.0 & Self::SMALL_INTEGER_TAG != 0
     }
 
+    /// Create a Character immediate from a unicode code point
+    pub fn new_character(value: char) -> Self {
+        Self(((value as i64) << Self::NUMBER_TAG) + Self::CHARACTER_TAG)
+    }
+
+    /// Create a SmallFloat64 immediate if the float's exponent fits in 8 bits.
+    /// The sign bit is rotated into the least significant bit and the exponent is rebased,
+    /// so that zero stays zero and the rest of the bits are preserved
+    pub fn try_new_float(value: f64) -> Option<Self> {
+        if !Self::is_small_float_value(value) {
+            return None;
+        }
+
+        let mut bits = value.to_bits().rotate_left(1);
+        if bits > 1 {
+            bits -= Self::SMALL_FLOAT_EXPONENT_OFFSET << (Self::SMALL_FLOAT_MANTISSA_BITS + 1);
+        }
+        Some(Self(
+            ((bits << Self::NUMBER_TAG) as i64) + Self::SMALL_FLOAT_TAG,
+        ))
+    }
+
+    /// Return true if a given float can be represented as SmallFloat64
+    pub fn is_small_float_value(value: f64) -> bool {
+        let bits = value.to_bits();
+        let exponent = (bits >> Self::SMALL_FLOAT_MANTISSA_BITS) & 0x7FF;
+        let mantissa = bits & ((1 << Self::SMALL_FLOAT_MANTISSA_BITS) - 1);
+
+        (exponent > Self::SMALL_FLOAT_EXPONENT_OFFSET
+            && exponent <= 255 + Self::SMALL_FLOAT_EXPONENT_OFFSET)
+            // positive and negative zero
+            || (exponent == 0 && mantissa == 0)
+    }
+
+    pub fn is_small_integer_value(value: i64) -> bool {
+        (Self::MIN_SMALL_INTEGER..=Self::MAX_SMALL_INTEGER).contains(&value)
+    }
+
+    pub fn is_character(&self) -> bool {
+        self.0 & Self::TAG_MASK == Self::CHARACTER_TAG
+    }
+
+    pub fn is_small_float(&self) -> bool {
+        self.0 & Self::TAG_MASK == Self::SMALL_FLOAT_TAG
+    }
+
+    pub fn as_character(&self) -> Option<char> {
+        if self.is_character() {
+            char::from_u32(self.character_value()?)
+        } else {
+            None
+        }
+    }
+
+    /// Return the code point of a Character immediate.
+    /// Pharo characters are not limited to valid unicode scalar values
+    pub fn character_value(&self) -> Option<u32> {
+        if self.is_character() {
+            Some((self.0 as u64 >> Self::NUMBER_TAG) as u32)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        if !self.is_small_float() {
+            return None;
+        }
+
+        let mut bits = self.0 as u64 >> Self::NUMBER_TAG;
+        if bits > 1 {
+            bits += Self::SMALL_FLOAT_EXPONENT_OFFSET << (Self::SMALL_FLOAT_MANTISSA_BITS + 1);
+        }
+        Some(f64::from_bits(bits.rotate_right(1)))
+    }
+
+    /// Decode the immediate into a typed value
+    pub fn value(&self) -> Result<ImmediateValue> {
+        ImmediateValue::try_from(*self)
+    }
+
     pub fn as_integer(&self) -> Option<i64> {
         if self.is_small_integer() {
             if self.0 >> 63 == 1 {
@@ -52,3 +151,131 @@ impl TryFrom<RawObjectPointer> for Immediate {
     }
 }
 
+/// A decoded immediate value
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ImmediateValue {
+    SmallInteger(i64),
+    Character(char),
+    SmallFloat(f64),
+}
+
+impl TryFrom<Immediate> for ImmediateValue {
+    type Error = Error;
+
+    fn try_from(immediate: Immediate) -> Result<Self> {
+        if let Some(integer) = immediate.as_integer() {
+            return Ok(Self::SmallInteger(integer));
+        }
+        if let Some(float) = immediate.as_float() {
+            return Ok(Self::SmallFloat(float));
+        }
+        if let Some(character) = immediate.as_character() {
+            return Ok(Self::Character(character));
+        }
+        Err(Error::UnsupportedImmediate(immediate))
+    }
+}
+
+impl TryFrom<ImmediateValue> for Immediate {
+    type Error = Error;
+
+    fn try_from(value: ImmediateValue) -> Result<Self> {
+        match value {
+            ImmediateValue::SmallInteger(integer) => {
+                if Immediate::is_small_integer_value(integer) {
+                    Ok(Immediate::new_i64(integer))
+                } else {
+                    Err(Error::NotRepresentableAsImmediate(value))
+                }
+            }
+            ImmediateValue::Character(character) => Ok(Immediate::new_character(character)),
+            ImmediateValue::SmallFloat(float) => {
+                Immediate::try_new_float(float).ok_or(Error::NotRepresentableAsImmediate(value))
+            }
+        }
+    }
+}
+
+impl From<char> for ImmediateValue {
+    fn from(value: char) -> Self {
+        Self::Character(value)
+    }
+}
+
+impl From<i64> for ImmediateValue {
+    fn from(value: i64) -> Self {
+        Self::SmallInteger(value)
+    }
+}
+
+impl From<f64> for ImmediateValue {
+    fn from(value: f64) -> Self {
+        Self::SmallFloat(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(value: ImmediateValue) -> ImmediateValue {
+        Immediate::try_from(value).unwrap().value().unwrap()
+    }
+
+    #[test]
+    fn small_integers() {
+        for value in [
+            0,
+            1,
+            -1,
+            42,
+            Immediate::MIN_SMALL_INTEGER,
+            Immediate::MAX_SMALL_INTEGER,
+        ] {
+            assert_eq!(
+                round_trip(value.into()),
+                ImmediateValue::SmallInteger(value)
+            );
+        }
+        assert!(Immediate::try_from(ImmediateValue::SmallInteger(1 << 60)).is_err());
+    }
+
+    #[test]
+    fn characters() {
+        for value in ['a', '\0', 'é', '😀', char::MAX] {
+            let immediate = Immediate::new_character(value);
+            assert_eq!(immediate.0 & 7, 2);
+            assert_eq!(round_trip(value.into()), ImmediateValue::Character(value));
+        }
+    }
+
+    #[test]
+    fn small_floats() {
+        for value in [
+            0.0,
+            -0.0,
+            1.0,
+            -1.5,
+            123.456,
+            1.0e38,
+            -2.5e-38,
+            f64::EPSILON,
+        ] {
+            let immediate = Immediate::try_new_float(value).unwrap();
+            assert_eq!(immediate.0 & 7, 4);
+            assert_eq!(immediate.as_float().unwrap().to_bits(), value.to_bits());
+        }
+        assert_eq!(Immediate::try_new_float(0.0).unwrap().0, 4);
+        assert_eq!(Immediate::try_new_float(-0.0).unwrap().0, 12);
+        // the 8-bit exponent can't represent these
+        for value in [
+            1.0e300,
+            1.0e-300,
+            f64::NAN,
+            f64::INFINITY,
+            f64::MIN_POSITIVE,
+        ] {
+            assert!(Immediate::try_new_float(value).is_none());
+        }
+    }
+}
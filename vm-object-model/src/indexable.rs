@@ -0,0 +1,269 @@
+use crate::{AnyObjectRef, Error, Object, ObjectFormat, ObjectRef, Result};
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+use widestring::U32Str;
+
+/// Defines a view on the indexable data of non-pointer objects.
+/// The length of the slice is computed from the amount of slots and the odd-size bits of the format.
+macro_rules! indexable_ref {
+    ($(#[$meta:meta])* $name:ident, $unit:ty, $format:pat, $type_name:literal) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone)]
+        #[repr(transparent)]
+        pub struct $name(ObjectRef);
+
+        impl $name {
+            pub fn len(&self) -> usize {
+                self.0.amount_of_indexable_units()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            pub fn as_slice(&self) -> &[$unit] {
+                let slice_ptr = self.0.first_fixed_field_ptr() as *const $unit;
+                unsafe { std::slice::from_raw_parts(slice_ptr, self.len()) }
+            }
+
+            /// Return a mutable slice of the data or an error if the object is read-only
+            pub fn as_slice_mut(&mut self) -> Result<&mut [$unit]> {
+                self.0.check_mutable()?;
+                let length = self.len();
+                let slice_ptr = self.0.first_fixed_field_ptr() as *mut $unit;
+                Ok(unsafe { std::slice::from_raw_parts_mut(slice_ptr, length) })
+            }
+
+            pub fn get(&self, index: usize) -> Option<$unit> {
+                self.as_slice().get(index).copied()
+            }
+
+            pub fn iter(&self) -> impl Iterator<Item = &$unit> {
+                self.as_slice().iter()
+            }
+        }
+
+        impl Deref for $name {
+            type Target = Object;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl TryFrom<AnyObjectRef> for $name {
+            type Error = Error;
+
+            fn try_from(value: AnyObjectRef) -> Result<Self> {
+                let object_ref = value.as_object()?.follow_forwarded();
+                match object_ref.object_format() {
+                    $format => Ok(Self(object_ref)),
+                    _ => Err(Error::InvalidType($type_name.to_string())),
+                }
+            }
+        }
+
+        impl From<$name> for AnyObjectRef {
+            fn from(value: $name) -> Self {
+                value.0.into()
+            }
+        }
+    };
+}
+
+indexable_ref!(
+    /// A view on the bytes of an 8-bit indexable object (ByteArray et al)
+    ByteArrayRef,
+    u8,
+    ObjectFormat::Indexable8(_),
+    "ByteArray"
+);
+
+indexable_ref!(
+    /// A view on the items of a 16-bit indexable object (DoubleByteArray et al)
+    DoubleByteArrayRef,
+    u16,
+    ObjectFormat::Indexable16(_),
+    "DoubleByteArray"
+);
+
+indexable_ref!(
+    /// A view on the items of a 32-bit indexable object (WordArray, Bitmap et al)
+    WordArrayRef,
+    u32,
+    ObjectFormat::Indexable32(_),
+    "WordArray"
+);
+
+indexable_ref!(
+    /// A view on the items of a 64-bit indexable object (DoubleWordArray et al)
+    DoubleWordArrayRef,
+    u64,
+    ObjectFormat::Indexable64,
+    "DoubleWordArray"
+);
+
+indexable_ref!(
+    /// A view on a Latin-1 encoded ByteString or ByteSymbol.
+    /// The format does not tell a string from a ByteArray, it is up to the caller to check the class
+    ByteStringRef,
+    u8,
+    ObjectFormat::Indexable8(_),
+    "ByteString"
+);
+
+indexable_ref!(
+    /// A view on a UTF-32 encoded WideString or WideSymbol.
+    /// The format does not tell a string from a WordArray, it is up to the caller to check the class
+    WideStringRef,
+    u32,
+    ObjectFormat::Indexable32(_),
+    "WideString"
+);
+
+impl ByteStringRef {
+    pub fn as_bytes(&self) -> &[u8] {
+        self.as_slice()
+    }
+
+    /// Return the string if it only consists of ASCII characters and can be borrowed as is
+    pub fn as_ascii_str(&self) -> Option<&str> {
+        let bytes = self.as_bytes();
+        if bytes.is_ascii() {
+            Some(unsafe { std::str::from_utf8_unchecked(bytes) })
+        } else {
+            None
+        }
+    }
+
+    /// Characters of a ByteString are Latin-1, which map one to one to the first 256 unicode code points
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.as_bytes().iter().map(|byte| *byte as char)
+    }
+}
+
+impl WideStringRef {
+    pub fn as_u32_str(&self) -> &U32Str {
+        U32Str::from_slice(self.as_slice())
+    }
+
+    /// Characters of the string, invalid code points are replaced with `char::REPLACEMENT_CHARACTER`
+    pub fn chars_lossy(&self) -> impl Iterator<Item = char> + '_ {
+        self.as_u32_str().chars_lossy()
+    }
+}
+
+impl std::fmt::Display for ByteStringRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.chars()
+            .try_for_each(|each| std::fmt::Write::write_char(f, each))
+    }
+}
+
+impl std::fmt::Display for WideStringRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.chars_lossy()
+            .try_for_each(|each| std::fmt::Write::write_char(f, each))
+    }
+}
+
+impl Debug for ByteArrayRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ByteArrayRef")
+            .field(&self.as_slice())
+            .finish()
+    }
+}
+
+impl Debug for DoubleByteArrayRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DoubleByteArrayRef")
+            .field(&self.as_slice())
+            .finish()
+    }
+}
+
+impl Debug for WordArrayRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WordArrayRef")
+            .field(&self.as_slice())
+            .finish()
+    }
+}
+
+impl Debug for DoubleWordArrayRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DoubleWordArrayRef")
+            .field(&self.as_slice())
+            .finish()
+    }
+}
+
+impl Debug for ByteStringRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ByteStringRef")
+            .field(&self.to_string())
+            .finish()
+    }
+}
+
+impl Debug for WideStringRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WideStringRef")
+            .field(&self.to_string())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ObjectHeader, RawObjectPointer};
+
+    /// Allocate an object with a given format and raw slots, returning the backing memory
+    fn object_with(format: ObjectFormat, slots: &[u64]) -> (Vec<u64>, AnyObjectRef) {
+        let header = ObjectHeader::new()
+            .with_class_index(42)
+            .with_format(format)
+            .with_num_slots(slots.len() as u8);
+        let mut memory = vec![header.into_bits()];
+        memory.extend_from_slice(slots);
+        let object = AnyObjectRef::from(RawObjectPointer::new(memory.as_ptr() as i64));
+        (memory, object)
+    }
+
+    #[test]
+    fn byte_string_length_respects_odd_bits() {
+        // 'hello' occupies 1 slot with 3 unused bytes
+        let (_memory, object) = object_with(ObjectFormat::Indexable8(16 + 3), &[0x6f6c6c6568]);
+        let string = ByteStringRef::try_from(object).unwrap();
+        assert_eq!(string.len(), 5);
+        assert_eq!(string.as_ascii_str(), Some("hello"));
+        assert_eq!(string.to_string(), "hello");
+    }
+
+    #[test]
+    fn sixteen_and_thirty_two_bit_units() {
+        let (_memory, object) = object_with(ObjectFormat::Indexable16(12 + 1), &[0x0003_0002_0001]);
+        let array = DoubleByteArrayRef::try_from(object).unwrap();
+        assert_eq!(array.as_slice(), &[1, 2, 3]);
+
+        let (_memory, object) = object_with(ObjectFormat::Indexable32(10 + 1), &[0x1F600]);
+        let string = WideStringRef::try_from(object).unwrap();
+        assert_eq!(string.to_string(), "😀");
+        assert!(DoubleWordArrayRef::try_from(object).is_err());
+    }
+
+    #[test]
+    fn mutation_respects_immutability() {
+        let (mut memory, object) = object_with(ObjectFormat::Indexable64, &[1, 2]);
+        let mut array = DoubleWordArrayRef::try_from(object).unwrap();
+        array.as_slice_mut().unwrap()[1] = 42;
+        assert_eq!(array.as_slice(), &[1, 42]);
+
+        memory[0] = ObjectHeader::from_bits(memory[0])
+            .with_is_immutable(true)
+            .into_bits();
+        assert!(matches!(array.as_slice_mut(), Err(Error::Immutable(_))));
+    }
+}
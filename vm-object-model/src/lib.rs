@@ -1,13 +1,25 @@
+mod class;
 mod error;
+mod heap_verifier;
+mod image_file;
 mod immediate;
+mod indexable;
 mod object_format;
 mod object_header;
 mod object_pointer;
 mod object;
+#[cfg(any(test, feature = "test_heap"))]
+mod test_heap;
 
+pub use class::*;
 pub use error::*;
+pub use heap_verifier::*;
+pub use image_file::*;
 pub use immediate::*;
+pub use indexable::*;
 pub use object_format::*;
 pub use object_header::*;
 pub use object_pointer::*;
-pub use object::*;
\ No newline at end of file
+pub use object::*;
+#[cfg(any(test, feature = "test_heap"))]
+pub use test_heap::*;
\ No newline at end of file
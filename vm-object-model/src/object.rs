@@ -1,6 +1,7 @@
 use std::ops::{Deref, DerefMut};
 use std::os::raw::c_void;
-use crate::{Error, Immediate, ObjectFormat, ObjectHeader, RawObjectPointer, Result};
+use std::ptr::{with_exposed_provenance, with_exposed_provenance_mut};
+use crate::{Error, Immediate, ImmediateValue, ObjectFormat, ObjectHeader, RawObjectPointer, Result};
 
 #[derive(Debug)]
 #[repr(transparent)]
@@ -60,32 +61,42 @@ impl Object {
         unsafe { self.as_ptr().offset(size_of::<ObjectHeader>() as isize) }
     }
 
+    /// Return true if the object is a forwarder. Free chunks and other puns are not forwarders
     pub fn is_forwarded(&self) -> bool {
-        self.0.class_index() <= Self::FORWARDED_OBJECT_CLASS_INDEX_PUN
+        self.0.class_index() == Self::FORWARDED_OBJECT_CLASS_INDEX_PUN
     }
 
-    pub fn is_identical(&self, second: &Object) -> Option<bool> {
-        if self.is_forwarded() {
+    /// Return the object this forwarder points to, or None if the object is not forwarded.
+    /// A forwarder's first slot holds the pointer to the object it was replaced with
+    pub fn forwarded_target(&self) -> Option<AnyObjectRef> {
+        if !self.is_forwarded() {
             return None;
         }
 
-        if second.is_forwarded() {
+        // forwarders always have room for at least one slot, even if they report zero
+        let first_slot = self.first_fixed_field_ptr() as *const i64;
+        let target = RawObjectPointer::from(unsafe { *first_slot });
+        if target.is_immediate() || target.as_i64() == 0 {
             return None;
         }
+        Some(AnyObjectRef::from(target))
+    }
 
-        Some(self.as_ptr() == second.as_ptr())
+    /// Return true if both objects are the same object once forwarders are followed
+    pub fn is_identical(&self, second: &Object) -> Option<bool> {
+        let first = ObjectRef::from(self).follow_forwarded();
+        let second = ObjectRef::from(second).follow_forwarded();
+
+        Some(first.as_ptr() == second.as_ptr())
     }
 
+    /// Return true if both objects are the same object once forwarders are followed.
+    /// Distinct objects are never equal, even if their headers are
     pub fn equals(&self, other: &Object) -> Result<bool> {
-        // if self.is_forwarded() {
-        //     return Err(Error::ForwardedUnsupported(self.header().clone()));
-        // }
+        let this = ObjectRef::from(self).follow_forwarded();
+        let other = ObjectRef::from(other).follow_forwarded();
 
-        if other.is_forwarded() {
-            return Err(Error::ForwardedUnsupported(other.header().clone()));
-        }
-
-        Ok(self.0 == other.0)
+        Ok(this.0 == other.0)
     }
 
     pub fn inst_var_at(&self, field_index: usize) -> Option<AnyObjectRef> {
@@ -103,7 +114,45 @@ impl Object {
         Some(AnyObjectRef::from(RawObjectPointer::from(pointer_value)))
     }
 
-    pub fn inst_var_at_put(&mut self, field_index: usize, object: impl Into<AnyObjectRef>) {
+    /// Return an error if the object is read-only,
+    /// so that stores from Rust honour the same immutability as the ones from Smalltalk
+    pub fn check_mutable(&self) -> Result<()> {
+        if self.0.is_immutable() {
+            Err(Error::Immutable(self.0))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Return an error if the object may be moved by the garbage collector,
+    /// for objects that are referenced from Rust or native code across allocations
+    pub fn check_pinned(&self) -> Result<()> {
+        if self.0.is_pinned() {
+            Ok(())
+        } else {
+            Err(Error::NotPinned(self.0))
+        }
+    }
+
+    /// Store an object in a field, failing if the receiver is read-only.
+    /// Does not go through the write barrier, see `Smalltalk::prepare_to_store`
+    pub fn inst_var_at_put(
+        &mut self,
+        field_index: usize,
+        object: impl Into<AnyObjectRef>,
+    ) -> Result<()> {
+        self.check_mutable()?;
+        self.inst_var_at_put_unchecked(field_index, object);
+        Ok(())
+    }
+
+    /// An unchecked version of `inst_var_at_put` that also writes into read-only objects.
+    /// Only meant for initializing objects that are not yet visible to the image
+    pub fn inst_var_at_put_unchecked(
+        &mut self,
+        field_index: usize,
+        object: impl Into<AnyObjectRef>,
+    ) {
         if field_index >= self.amount_of_slots_unchecked() {
             return;
         }
@@ -127,12 +176,26 @@ impl ObjectRef {
         self.0
     }
 
+    /// Follow the chain of forwarders (left behind by become: or compaction)
+    /// and return the object they point to, or this object if it is not forwarded.
+    pub fn follow_forwarded(self) -> ObjectRef {
+        let mut object = self;
+        while let Some(target) = object.forwarded_target() {
+            object = Self(target.0);
+        }
+        object
+    }
+
+    /// Cast the object to a given type, following forwarders
     pub unsafe fn cast<T>(&self) -> &T {
-        self.0.cast()
+        let ptr: *const T = with_exposed_provenance(self.follow_forwarded().0.as_i64() as usize);
+        unsafe { &*ptr }
     }
 
+    /// Cast the object to a given type, following forwarders
     pub unsafe fn cast_mut<T>(&mut self) -> &mut T {
-        self.0.cast_mut()
+        let ptr: *mut T = with_exposed_provenance_mut(self.follow_forwarded().0.as_i64() as usize);
+        unsafe { &mut *ptr }
     }
 }
 
@@ -222,9 +285,34 @@ impl AnyObjectRef {
         Immediate::try_from(self.0)
     }
 
+    pub fn as_immediate_value(&self) -> Result<ImmediateValue> {
+        self.as_immediate()?.value()
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        self.as_immediate().ok()?.as_integer()
+    }
+
+    pub fn as_character(&self) -> Option<char> {
+        self.as_immediate().ok()?.as_character()
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        self.as_immediate().ok()?.as_float()
+    }
+
     pub fn as_object(&self) -> Result<ObjectRef> {
         ObjectRef::try_from(self.0)
     }
+
+    /// Follow the chain of forwarders if this is a forwarded object.
+    /// Immediates are returned as is
+    pub fn follow_forwarded(self) -> AnyObjectRef {
+        match self.as_object() {
+            Ok(object) => object.follow_forwarded().into(),
+            Err(_) => self,
+        }
+    }
 }
 
 impl From<RawObjectPointer> for AnyObjectRef {
@@ -237,4 +325,12 @@ impl From<Immediate> for AnyObjectRef {
     fn from(immediate: Immediate) -> Self {
         Self::from(RawObjectPointer::from(immediate.0))
     }
+}
+
+impl TryFrom<ImmediateValue> for AnyObjectRef {
+    type Error = Error;
+
+    fn try_from(value: ImmediateValue) -> Result<Self> {
+        Immediate::try_from(value).map(Self::from)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,645 @@
+use crate::{
+    AnyObjectRef, ClassRef, Immediate, InstanceSpecification, ObjectFormat, ObjectHeader,
+    ObjectRef, RawObjectPointer,
+};
+use std::cell::{Cell, RefCell};
+
+/// An in-memory heap that lays out Spur objects in a Rust buffer,
+/// so that the object model can be exercised without a running vm.
+/// Objects never move and are never collected; the heap must outlive all references to its objects.
+///
+/// Classes are regular objects with three fixed slots (superclass, method dictionary and format),
+/// their identity hash is the class index, the same as in Spur.
+/// Named classes additionally have a layout and a name, shaped like the ones of Pharo.
+pub struct TestHeap {
+    memory: *mut u64,
+    capacity: usize,
+    next_free_word: Cell<usize>,
+    next_identity_hash: Cell<u32>,
+    class_table: RefCell<Vec<Option<ObjectRef>>>,
+    nil: Cell<Option<ObjectRef>>,
+    true_object: Cell<Option<ObjectRef>>,
+    false_object: Cell<Option<ObjectRef>>,
+    class_array: Cell<Option<ObjectRef>>,
+    class_byte_string: Cell<Option<ObjectRef>>,
+    class_byte_symbol: Cell<Option<ObjectRef>>,
+    class_external_address: Cell<Option<ObjectRef>>,
+    class_large_negative_integer: Cell<Option<ObjectRef>>,
+    class_large_positive_integer: Cell<Option<ObjectRef>>,
+    class_boxed_float: Cell<Option<ObjectRef>>,
+}
+
+impl TestHeap {
+    pub const SUPERCLASS_INDEX: usize = 0;
+    pub const METHOD_DICTIONARY_INDEX: usize = 1;
+    pub const INSTANCE_SPECIFICATION_INDEX: usize = 2;
+    pub const CLASS_SLOTS: usize = 3;
+    /// superclass, method dictionary, format, layout, subclasses and name
+    pub const NAMED_CLASS_SLOTS: usize = 6;
+    pub const NAME_INDEX: usize = 5;
+
+    /// Class indices below are reserved by Spur for immediates, puns and free chunks
+    const FIRST_CLASS_INDEX: u32 = 32;
+    /// Spur registers the classes of the numbers that do not fit in an immediate at fixed indices
+    const LARGE_NEGATIVE_INTEGER_CLASS_INDEX: u32 = 32;
+    const LARGE_POSITIVE_INTEGER_CLASS_INDEX: u32 = 33;
+    const BOXED_FLOAT_CLASS_INDEX: u32 = 34;
+    /// The first class that is not at a fixed index, it plays the role of the metaclass
+    const METACLASS_INDEX: u32 = 35;
+    const FORWARDED_CLASS_INDEX: u32 = 8;
+    const DEFAULT_CAPACITY: usize = 1 << 16;
+    const NUM_SLOTS_OVERFLOW: u8 = 255;
+
+    pub fn new() -> Self {
+        Self::with_capacity(Self::DEFAULT_CAPACITY)
+    }
+
+    /// Create a heap that can hold a given amount of 64-bit words
+    pub fn with_capacity(words: usize) -> Self {
+        let heap = Self {
+            memory: Box::into_raw(vec![0u64; words].into_boxed_slice()) as *mut u64,
+            capacity: words,
+            // the address 0 is never a valid object
+            next_free_word: Cell::new(1),
+            next_identity_hash: Cell::new(1),
+            class_table: RefCell::new(vec![
+                None;
+                (Self::METACLASS_INDEX - Self::FIRST_CLASS_INDEX)
+                    as usize
+            ]),
+            nil: Cell::new(None),
+            true_object: Cell::new(None),
+            false_object: Cell::new(None),
+            class_array: Cell::new(None),
+            class_byte_string: Cell::new(None),
+            class_byte_symbol: Cell::new(None),
+            class_external_address: Cell::new(None),
+            class_large_negative_integer: Cell::new(None),
+            class_large_positive_integer: Cell::new(None),
+            class_boxed_float: Cell::new(None),
+        };
+
+        let undefined_object_class = heap.new_class(ObjectFormat::ZeroSized, 0);
+        heap.nil.set(Some(heap.instantiate(undefined_object_class)));
+        // the class was created before nil existed
+        heap.initialize_pointers(undefined_object_class, heap.nil().into());
+
+        let true_class = heap.new_class(ObjectFormat::ZeroSized, 0);
+        heap.true_object.set(Some(heap.instantiate(true_class)));
+        let false_class = heap.new_class(ObjectFormat::ZeroSized, 0);
+        heap.false_object.set(Some(heap.instantiate(false_class)));
+
+        heap.class_array.set(Some(
+            heap.new_class(ObjectFormat::IndexableWithoutInstVars, 0),
+        ));
+        heap.class_byte_string
+            .set(Some(heap.new_class(ObjectFormat::Indexable8(16), 0)));
+        heap.class_byte_symbol
+            .set(Some(heap.new_class(ObjectFormat::Indexable8(16), 0)));
+        heap.class_external_address
+            .set(Some(heap.new_class(ObjectFormat::Indexable8(16), 0)));
+
+        heap.class_large_negative_integer
+            .set(Some(heap.new_named_class_at(
+                Some(Self::LARGE_NEGATIVE_INTEGER_CLASS_INDEX),
+                "LargeNegativeInteger",
+                None,
+                ObjectFormat::Indexable8(16),
+                &[],
+            )));
+        heap.class_large_positive_integer
+            .set(Some(heap.new_named_class_at(
+                Some(Self::LARGE_POSITIVE_INTEGER_CLASS_INDEX),
+                "LargePositiveInteger",
+                None,
+                ObjectFormat::Indexable8(16),
+                &[],
+            )));
+        heap.class_boxed_float.set(Some(heap.new_named_class_at(
+            Some(Self::BOXED_FLOAT_CLASS_INDEX),
+            "BoxedFloat64",
+            None,
+            ObjectFormat::Indexable32(10),
+            &[],
+        )));
+
+        heap
+    }
+
+    pub fn nil(&self) -> ObjectRef {
+        self.nil.get().unwrap()
+    }
+
+    pub fn true_object(&self) -> ObjectRef {
+        self.true_object.get().unwrap()
+    }
+
+    pub fn false_object(&self) -> ObjectRef {
+        self.false_object.get().unwrap()
+    }
+
+    pub fn class_array(&self) -> ObjectRef {
+        self.class_array.get().unwrap()
+    }
+
+    pub fn class_byte_string(&self) -> ObjectRef {
+        self.class_byte_string.get().unwrap()
+    }
+
+    pub fn class_byte_symbol(&self) -> ObjectRef {
+        self.class_byte_symbol.get().unwrap()
+    }
+
+    pub fn class_external_address(&self) -> ObjectRef {
+        self.class_external_address.get().unwrap()
+    }
+
+    pub fn class_large_negative_integer(&self) -> ObjectRef {
+        self.class_large_negative_integer.get().unwrap()
+    }
+
+    pub fn class_large_positive_integer(&self) -> ObjectRef {
+        self.class_large_positive_integer.get().unwrap()
+    }
+
+    pub fn class_boxed_float(&self) -> ObjectRef {
+        self.class_boxed_float.get().unwrap()
+    }
+
+    /// Create a new class whose instances have a given format and amount of fixed slots
+    pub fn new_class(&self, format: ObjectFormat, fixed_slots: usize) -> ObjectRef {
+        self.new_class_with_slots(
+            InstanceSpecification::new(format, fixed_slots),
+            Self::CLASS_SLOTS,
+        )
+    }
+
+    /// Create a new class with a name, a superclass and a layout that defines given instance variables.
+    /// The instances also get the instance variables of the superclass
+    pub fn new_named_class(
+        &self,
+        name: &str,
+        superclass: Option<ObjectRef>,
+        format: ObjectFormat,
+        instance_variable_names: &[&str],
+    ) -> ObjectRef {
+        self.new_named_class_at(None, name, superclass, format, instance_variable_names)
+    }
+
+    /// Create a new named class at a given index of the class table, or at the next free one
+    fn new_named_class_at(
+        &self,
+        class_index: Option<u32>,
+        name: &str,
+        superclass: Option<ObjectRef>,
+        format: ObjectFormat,
+        instance_variable_names: &[&str],
+    ) -> ObjectRef {
+        let inherited_slots = superclass
+            .map(|superclass| self.instance_specification_of(superclass).fixed_slots)
+            .unwrap_or(0);
+
+        let mut class = self.new_class_with_slots_at(
+            class_index,
+            InstanceSpecification::new(format, inherited_slots + instance_variable_names.len()),
+            Self::NAMED_CLASS_SLOTS,
+        );
+        if let Some(superclass) = superclass {
+            class.inst_var_at_put_unchecked(Self::SUPERCLASS_INDEX, superclass);
+        }
+
+        // layout: host, slotScope; scope: parentScope followed by the slots; slot: name
+        let layout_class = self.new_class(ObjectFormat::NonIndexable, 2);
+        let scope_class = self.new_class(ObjectFormat::IndexableWithInstVars, 1);
+        let slot_class = self.new_class(ObjectFormat::NonIndexable, 1);
+
+        let mut scope = self.instantiate_indexable(scope_class, instance_variable_names.len());
+        for (index, name) in instance_variable_names.iter().enumerate() {
+            let slot = self.new_object(slot_class, &[self.new_byte_symbol(name).into()]);
+            scope.inst_var_at_put_unchecked(1 + index, slot);
+        }
+        let layout = self.new_object(layout_class, &[class.into(), scope.into()]);
+
+        class.inst_var_at_put_unchecked(ClassRef::LAYOUT_INDEX, layout);
+        class.inst_var_at_put_unchecked(Self::NAME_INDEX, self.new_byte_symbol(name));
+        class
+    }
+
+    /// Return a class with a given class index
+    pub fn class_at_index(&self, class_index: u32) -> Option<ObjectRef> {
+        let index = class_index.checked_sub(Self::FIRST_CLASS_INDEX)?;
+        self.class_table
+            .borrow()
+            .get(index as usize)
+            .copied()
+            .flatten()
+    }
+
+    pub fn class_of(&self, object: &ObjectRef) -> Option<ObjectRef> {
+        self.class_at_index(object.header().class_index())
+    }
+
+    /// Return the format and the amount of fixed slots of the instances of a class
+    pub fn instance_specification_of(&self, class: ObjectRef) -> InstanceSpecification {
+        let specification = class
+            .inst_var_at(Self::INSTANCE_SPECIFICATION_INDEX)
+            .and_then(|each| each.as_immediate().ok())
+            .and_then(|each| each.as_integer())
+            .expect("Class must have an instance specification");
+
+        InstanceSpecification::from_bits(specification)
+    }
+
+    /// Instantiate a non-indexable instance of a class with all fields set to nil
+    pub fn instantiate(&self, class: ObjectRef) -> ObjectRef {
+        let specification = self.instance_specification_of(class);
+        self.allocate(
+            class.header().identity_hash(),
+            specification.format,
+            specification.fixed_slots,
+        )
+    }
+
+    /// Instantiate an indexable instance of a class with a given amount of indexable units
+    pub fn instantiate_indexable(&self, class: ObjectRef, size: usize) -> ObjectRef {
+        let InstanceSpecification {
+            format,
+            fixed_slots,
+        } = self.instance_specification_of(class);
+        let class_index = class.header().identity_hash();
+
+        match format {
+            ObjectFormat::Indexable64 => self.allocate(class_index, format, size),
+            ObjectFormat::Indexable32(_) => self.allocate_units(class_index, 10, 4, size),
+            ObjectFormat::Indexable16(_) => self.allocate_units(class_index, 12, 2, size),
+            ObjectFormat::Indexable8(_) => self.allocate_units(class_index, 16, 1, size),
+            ObjectFormat::CompiledMethod(_) => self.allocate_units(class_index, 24, 1, size),
+            _ => self.allocate(class_index, format, fixed_slots + size),
+        }
+    }
+
+    /// Allocate an object with all pointer slots set to nil and all other slots zeroed.
+    /// Objects with 255 or more slots are preceded by an overflow word.
+    pub fn allocate(
+        &self,
+        class_index: u32,
+        format: ObjectFormat,
+        amount_of_slots: usize,
+    ) -> ObjectRef {
+        let identity_hash = self.next_identity_hash.get();
+        self.next_identity_hash.set(identity_hash + 1);
+        self.allocate_with_hash(class_index, format, amount_of_slots, identity_hash)
+    }
+
+    /// Allocate an Array with given items
+    pub fn new_array(&self, items: &[AnyObjectRef]) -> ObjectRef {
+        let mut array = self.instantiate_indexable(self.class_array(), items.len());
+        for (index, item) in items.iter().enumerate() {
+            array.inst_var_at_put_unchecked(index, *item);
+        }
+        array
+    }
+
+    /// Allocate a non-indexable instance of a class with given field values
+    pub fn new_object(&self, class: ObjectRef, fields: &[AnyObjectRef]) -> ObjectRef {
+        let mut object = self.instantiate(class);
+        assert_eq!(
+            object.amount_of_slots(),
+            fields.len(),
+            "Must provide a value for every field"
+        );
+        for (index, field) in fields.iter().enumerate() {
+            object.inst_var_at_put_unchecked(index, *field);
+        }
+        object
+    }
+
+    /// Allocate an 8-bit indexable instance of a class with given bytes
+    pub fn new_bytes(&self, class: ObjectRef, bytes: &[u8]) -> ObjectRef {
+        let object = self.instantiate_indexable(class, bytes.len());
+        let bytes_ptr = object.first_fixed_field_ptr() as *mut u8;
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), bytes_ptr, bytes.len()) };
+        object
+    }
+
+    pub fn new_byte_string(&self, string: &str) -> ObjectRef {
+        self.new_bytes(self.class_byte_string(), string.as_bytes())
+    }
+
+    pub fn new_byte_symbol(&self, string: &str) -> ObjectRef {
+        self.new_bytes(self.class_byte_symbol(), string.as_bytes())
+    }
+
+    /// Turn a fresh object into a forwarder to a given target, as become: or compaction would do
+    pub fn new_forwarder(&self, target: impl Into<AnyObjectRef>) -> ObjectRef {
+        let mut forwarder = self.allocate(Self::FORWARDED_CLASS_INDEX, ObjectFormat::Forwarded, 1);
+        forwarder.inst_var_at_put_unchecked(0, target);
+        forwarder
+    }
+
+    /// Mark an object as read-only (or writable again), as `Object>>#beReadOnlyObject` would do
+    pub fn set_immutable(&self, object: ObjectRef, is_immutable: bool) {
+        let header = object.header().with_is_immutable(is_immutable);
+        unsafe { *(object.as_ptr() as *mut u64) = header.into_bits() };
+    }
+
+    /// Mark an object as pinned (or movable again), as `Object>>#pin` would do
+    pub fn set_pinned(&self, object: ObjectRef, is_pinned: bool) {
+        let header = object.header().with_is_pinned(is_pinned);
+        unsafe { *(object.as_ptr() as *mut u64) = header.into_bits() };
+    }
+
+    /// Return true if an object pointer points into this heap
+    pub fn contains(&self, object: AnyObjectRef) -> bool {
+        if object.is_immediate() {
+            return false;
+        }
+        let start = self.memory as i64;
+        let end = start + (self.next_free_word.get() * size_of::<u64>()) as i64;
+        (start..end).contains(&object.as_i64())
+    }
+
+    /// Return the amount of 64-bit words allocated so far
+    pub fn used_words(&self) -> usize {
+        self.next_free_word.get()
+    }
+
+    fn new_class_with_slots(
+        &self,
+        instance_specification: InstanceSpecification,
+        amount_of_class_slots: usize,
+    ) -> ObjectRef {
+        self.new_class_with_slots_at(None, instance_specification, amount_of_class_slots)
+    }
+
+    /// Create a class at a given index of the class table, or at the end of the class table
+    fn new_class_with_slots_at(
+        &self,
+        class_index: Option<u32>,
+        instance_specification: InstanceSpecification,
+        amount_of_class_slots: usize,
+    ) -> ObjectRef {
+        let class_index = class_index
+            .unwrap_or_else(|| Self::FIRST_CLASS_INDEX + self.class_table.borrow().len() as u32);
+
+        // class objects are instances of a class with the same shape as themselves,
+        // the first class that is not at a fixed index plays the role of the metaclass
+        let mut class = self.allocate_with_hash(
+            Self::METACLASS_INDEX,
+            ObjectFormat::NonIndexable,
+            amount_of_class_slots,
+            class_index,
+        );
+        class.inst_var_at_put_unchecked(
+            Self::INSTANCE_SPECIFICATION_INDEX,
+            Immediate::new_i64(instance_specification.into_bits()),
+        );
+
+        let mut class_table = self.class_table.borrow_mut();
+        let table_index = (class_index - Self::FIRST_CLASS_INDEX) as usize;
+        if table_index < class_table.len() {
+            class_table[table_index] = Some(class);
+        } else {
+            class_table.push(Some(class));
+        }
+        class
+    }
+
+    /// Allocate an object whose indexable part consists of units of a given byte size.
+    /// The unused units of the last slot are encoded in the low bits of the format
+    fn allocate_units(
+        &self,
+        class_index: u32,
+        format_base: u8,
+        unit_size: usize,
+        size: usize,
+    ) -> ObjectRef {
+        let units_per_slot = size_of::<u64>() / unit_size;
+        let amount_of_slots = size.div_ceil(units_per_slot);
+        let unused_units = amount_of_slots * units_per_slot - size;
+        self.allocate(
+            class_index,
+            ObjectFormat::from_bits(format_base + unused_units as u8),
+            amount_of_slots,
+        )
+    }
+
+    fn allocate_with_hash(
+        &self,
+        class_index: u32,
+        format: ObjectFormat,
+        amount_of_slots: usize,
+        identity_hash: u32,
+    ) -> ObjectRef {
+        let has_overflow = amount_of_slots >= Self::NUM_SLOTS_OVERFLOW as usize;
+        let overflow_words = if has_overflow { 1 } else { 0 };
+        // every object has at least one slot
+        let total_words = overflow_words + 1 + amount_of_slots.max(1);
+
+        let start = self.next_free_word.get();
+        if start + total_words > self.capacity {
+            panic!(
+                "TestHeap is full: can not allocate {} words, {} of {} are used",
+                total_words, start, self.capacity
+            );
+        }
+        self.next_free_word.set(start + total_words);
+
+        let num_slots = if has_overflow {
+            self.write_word(start, (0xFF << 56) | amount_of_slots as u64);
+            Self::NUM_SLOTS_OVERFLOW
+        } else {
+            amount_of_slots as u8
+        };
+
+        let header_index = start + overflow_words;
+        let header = ObjectHeader::new()
+            .with_class_index(class_index)
+            .with_format(format)
+            .with_num_slots(num_slots)
+            .with_identity_hash(identity_hash);
+        self.write_word(header_index, header.into_bits());
+
+        let object_ptr = self.word_ptr(header_index) as i64;
+        let object = ObjectRef::try_from(RawObjectPointer::new(object_ptr)).unwrap();
+
+        if let Some(nil) = self.nil.get() {
+            self.initialize_pointers(object, nil.into());
+        }
+        object
+    }
+
+    fn initialize_pointers(&self, mut object: ObjectRef, value: AnyObjectRef) {
+        let has_pointers = matches!(
+            object.object_format(),
+            ObjectFormat::NonIndexable
+                | ObjectFormat::IndexableWithoutInstVars
+                | ObjectFormat::IndexableWithInstVars
+                | ObjectFormat::WeakIndexable
+                | ObjectFormat::WeakNonIndexable
+        );
+        if !has_pointers {
+            return;
+        }
+        for index in 0..object.amount_of_slots_unchecked() {
+            if object.inst_var_at(index).unwrap().as_i64() == 0 {
+                object.inst_var_at_put_unchecked(index, value);
+            }
+        }
+    }
+
+    fn write_word(&self, index: usize, value: u64) {
+        unsafe { *self.word_ptr(index) = value };
+    }
+
+    fn word_ptr(&self, index: usize) -> *mut u64 {
+        assert!(index < self.capacity, "Index must be within the heap");
+        unsafe { self.memory.add(index) }
+    }
+}
+
+impl Drop for TestHeap {
+    fn drop(&mut self) {
+        let memory = std::ptr::slice_from_raw_parts_mut(self.memory, self.capacity);
+        drop(unsafe { Box::from_raw(memory) });
+    }
+}
+
+impl Default for TestHeap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ByteStringRef, Error};
+
+    #[test]
+    fn nil_true_and_false() {
+        let heap = TestHeap::new();
+        assert_eq!(heap.nil().amount_of_slots(), 0);
+        assert_eq!(heap.nil().object_format(), ObjectFormat::ZeroSized);
+        assert!(!heap.nil().is_forwarded());
+        assert_ne!(
+            heap.true_object().header().class_index(),
+            heap.false_object().header().class_index()
+        );
+
+        let class = heap.class_of(&heap.nil()).unwrap();
+        assert_eq!(
+            class
+                .inst_var_at(TestHeap::SUPERCLASS_INDEX)
+                .unwrap()
+                .as_i64(),
+            heap.nil().into_inner().as_i64()
+        );
+    }
+
+    #[test]
+    fn amount_of_slots_with_overflow_header() {
+        let heap = TestHeap::new();
+        for size in [0, 1, 254, 255, 256, 1000] {
+            let array = heap.instantiate_indexable(heap.class_array(), size);
+            assert_eq!(array.amount_of_slots(), size);
+            assert_eq!(array.amount_of_indexable_units(), size);
+            assert!(array.inst_var_at(size).is_none());
+            if size > 0 {
+                assert_eq!(
+                    array.inst_var_at(size - 1).unwrap().as_i64(),
+                    heap.nil().into_inner().as_i64()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn follow_forwarders() {
+        let heap = TestHeap::new();
+        let string = heap.new_byte_string("target");
+        let forwarder = heap.new_forwarder(heap.new_forwarder(string));
+
+        assert!(forwarder.is_forwarded());
+        assert!(!forwarder.follow_forwarded().is_forwarded());
+        assert_eq!(forwarder.follow_forwarded(), string);
+        assert!(forwarder.equals(&string).unwrap());
+        assert!(string.is_identical(&forwarder).unwrap());
+        assert!(!forwarder.is_identical(&heap.nil()).unwrap());
+        assert!(!string.equals(&heap.new_byte_string("target")).unwrap());
+
+        let mut free_chunk = heap.allocate(0, ObjectFormat::Forwarded, 1);
+        free_chunk.inst_var_at_put_unchecked(0, string);
+        assert!(!free_chunk.is_forwarded());
+        assert!(free_chunk.forwarded_target().is_none());
+
+        let string = ByteStringRef::try_from(AnyObjectRef::from(forwarder)).unwrap();
+        let immediate = AnyObjectRef::from(Immediate::new_i64(3));
+        assert_eq!(immediate.follow_forwarded().as_i64(), immediate.as_i64());
+        assert_eq!(string.to_string(), "target");
+    }
+
+    #[test]
+    fn byte_objects() {
+        let heap = TestHeap::new();
+        let string = heap.new_byte_string("hello world");
+        assert_eq!(string.amount_of_slots(), 2);
+        assert_eq!(string.object_format(), ObjectFormat::Indexable8(16 + 5));
+
+        let string = ByteStringRef::try_from(AnyObjectRef::from(string)).unwrap();
+        assert_eq!(string.to_string(), "hello world");
+    }
+
+    #[test]
+    fn instantiate_class_with_fixed_fields() {
+        let heap = TestHeap::new();
+        let point_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+        let point = heap.new_object(
+            point_class,
+            &[Immediate::new_i64(3).into(), Immediate::new_i64(4).into()],
+        );
+        assert_eq!(
+            point.header().class_index(),
+            point_class.header().identity_hash()
+        );
+        assert_eq!(
+            point
+                .inst_var_at(1)
+                .unwrap()
+                .as_immediate()
+                .unwrap()
+                .as_integer(),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn read_only_objects() {
+        let heap = TestHeap::new();
+        let mut array = heap.new_array(&[Immediate::new_i64(1).into()]);
+        array.inst_var_at_put(0, Immediate::new_i64(2)).unwrap();
+
+        heap.set_immutable(array, true);
+        assert!(matches!(
+            array.inst_var_at_put(0, Immediate::new_i64(3)),
+            Err(Error::Immutable(_))
+        ));
+        assert_eq!(array.inst_var_at(0).unwrap().as_integer(), Some(2));
+
+        array.inst_var_at_put_unchecked(0, Immediate::new_i64(3));
+        assert_eq!(array.inst_var_at(0).unwrap().as_integer(), Some(3));
+
+        heap.set_immutable(array, false);
+        assert!(array.check_mutable().is_ok());
+    }
+
+    #[test]
+    fn pinned_objects() {
+        let heap = TestHeap::new();
+        let array = heap.new_array(&[]);
+        assert!(matches!(array.check_pinned(), Err(Error::NotPinned(_))));
+
+        heap.set_pinned(array, true);
+        assert!(array.check_pinned().is_ok());
+    }
+}
@@ -0,0 +1,764 @@
+use crate::objects::{
+    print_string, BytecodeSet, CompiledMethod, CompiledMethodRef, SmalltalkString, StringClasses,
+};
+use std::fmt::Write;
+use vm_bindings::{ObjectPointer, Smalltalk, StackOffset};
+
+/// Literals are printed shallowly to keep one instruction per line
+const LITERAL_PRINT_DEPTH: usize = 1;
+const LITERAL_PRINT_WIDTH: usize = 8;
+
+/// Selectors that are sent by dedicated bytecodes instead of a literal, with their amount of arguments.
+/// The first 16 are the arithmetic selectors
+const SPECIAL_SELECTORS: [(&str, usize); 32] = [
+    ("+", 1),
+    ("-", 1),
+    ("<", 1),
+    (">", 1),
+    ("<=", 1),
+    (">=", 1),
+    ("=", 1),
+    ("~=", 1),
+    ("*", 1),
+    ("/", 1),
+    ("\\\\", 1),
+    ("@", 1),
+    ("bitShift:", 1),
+    ("//", 1),
+    ("bitAnd:", 1),
+    ("bitOr:", 1),
+    ("at:", 1),
+    ("at:put:", 2),
+    ("size", 0),
+    ("next", 0),
+    ("nextPut:", 1),
+    ("atEnd", 0),
+    ("==", 1),
+    ("class", 0),
+    ("~~", 1),
+    ("value", 0),
+    ("value:", 1),
+    ("do:", 1),
+    ("new", 0),
+    ("new:", 1),
+    ("x", 0),
+    ("y", 0),
+];
+
+/// The selector of a send
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Selector {
+    /// A literal of the method at a zero-based index
+    Literal(usize),
+    Special(&'static str),
+}
+
+/// What a bytecode does. Indices of literals, variables and temporaries are zero-based,
+/// jump targets are pcs in the method, the same as `Instruction::pc`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operation {
+    PushReceiverVariable(usize),
+    PushLiteralVariable(usize),
+    PushLiteral(usize),
+    PushTemporary(usize),
+    PushReceiver,
+    PushTrue,
+    PushFalse,
+    PushNil,
+    PushInteger(i64),
+    PushCharacter(u32),
+    PushThisContext,
+    PushThisProcess,
+    PushNewArray(usize),
+    PopIntoNewArray(usize),
+    PushRemoteTemporary {
+        index: usize,
+        vector: usize,
+    },
+    StoreRemoteTemporary {
+        index: usize,
+        vector: usize,
+        pop: bool,
+    },
+    StoreReceiverVariable {
+        index: usize,
+        pop: bool,
+    },
+    StoreLiteralVariable {
+        index: usize,
+        pop: bool,
+    },
+    StoreTemporary {
+        index: usize,
+        pop: bool,
+    },
+    Duplicate,
+    Pop,
+    Nop,
+    ReturnReceiver,
+    ReturnTrue,
+    ReturnFalse,
+    ReturnNil,
+    ReturnTop,
+    BlockReturnNil,
+    BlockReturnTop,
+    Send {
+        selector: Selector,
+        arguments: usize,
+    },
+    SuperSend {
+        literal: usize,
+        arguments: usize,
+        /// the class to look up from is on the stack instead of being the method class
+        directed: bool,
+    },
+    Jump(usize),
+    JumpIfTrue(usize),
+    JumpIfFalse(usize),
+    CallPrimitive {
+        index: u16,
+        inlined: bool,
+    },
+    PushFullBlock {
+        literal: usize,
+        copied: usize,
+        receiver_on_stack: bool,
+        ignore_outer_context: bool,
+    },
+    PushClosure {
+        copied: usize,
+        arguments: usize,
+        size: usize,
+    },
+    BranchIfNotInstanceOf {
+        literal: usize,
+        target: usize,
+    },
+    Trap,
+    /// An unassigned or truncated bytecode
+    Unknown,
+}
+
+/// A decoded bytecode together with its extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    /// The one-based index of the first byte in the method, the same way the image counts pcs
+    pub pc: usize,
+    pub bytes: Vec<u8>,
+    pub operation: Operation,
+}
+
+/// Decode the bytecodes of a method or a block
+pub fn disassemble(method: &CompiledMethod) -> Vec<Instruction> {
+    decode(
+        method.bytecodes(),
+        method.header().bytecode_set(),
+        method.initial_pc(),
+    )
+}
+
+/// Decode bytecodes of a given set that start at a zero-based index of a method
+pub fn decode(bytecodes: &[u8], bytecode_set: BytecodeSet, initial_pc: usize) -> Vec<Instruction> {
+    let mut instructions = vec![];
+    let mut index = 0;
+    while index < bytecodes.len() {
+        let (size, operation) = match bytecode_set {
+            BytecodeSet::SistaV1 => decode_sista_v1(bytecodes, index, initial_pc),
+            BytecodeSet::V3PlusClosures => decode_v3_plus_closures(bytecodes, index, initial_pc),
+        };
+        let end = (index + size).min(bytecodes.len());
+        instructions.push(Instruction {
+            pc: initial_pc + index + 1,
+            bytes: bytecodes[index..end].to_vec(),
+            operation,
+        });
+        index = end;
+    }
+    instructions
+}
+
+/// Return the pc of a jump target given the index of the byte after the jump and the distance
+fn jump_target(initial_pc: usize, next_index: usize, distance: i64) -> usize {
+    (initial_pc as i64 + next_index as i64 + 1 + distance).max(0) as usize
+}
+
+/// Decode an instruction of the Sista V1 bytecode set at an index, including its Extend A and B prefixes.
+/// Return the amount of bytes and the operation
+fn decode_sista_v1(bytecodes: &[u8], start: usize, initial_pc: usize) -> (usize, Operation) {
+    let mut index = start;
+    let mut extend_a: i64 = 0;
+    let mut extend_b: i64 = 0;
+    let mut amount_of_extend_b = 0;
+
+    loop {
+        match (bytecodes.get(index), bytecodes.get(index + 1)) {
+            (Some(224), Some(byte)) => extend_a = (extend_a << 8) + *byte as i64,
+            (Some(225), Some(byte)) => {
+                // the first Extend B is signed, for negative integers and directed super sends
+                extend_b = if amount_of_extend_b == 0 {
+                    *byte as i8 as i64
+                } else {
+                    (extend_b << 8) + *byte as i64
+                };
+                amount_of_extend_b += 1;
+            }
+            _ => break,
+        }
+        index += 2;
+    }
+
+    let Some(byte) = bytecodes.get(index).copied() else {
+        return (bytecodes.len() - start, Operation::Unknown);
+    };
+    let size = match byte {
+        0..=223 => 1,
+        224..=247 => 2,
+        248..=255 => 3,
+    };
+    let next = index + size;
+    let Some(operands) = bytecodes.get(index + 1..next) else {
+        return (bytecodes.len() - start, Operation::Unknown);
+    };
+    let first = operands.first().copied().unwrap_or(0) as usize;
+    let second = operands.get(1).copied().unwrap_or(0) as usize;
+    let extended = |operand: usize, extension: i64, scale: i64| {
+        (operand as i64 + extension * scale).max(0) as usize
+    };
+    let jump = |distance: i64| jump_target(initial_pc, next, distance);
+
+    let operation = match byte {
+        0..=15 => Operation::PushReceiverVariable((byte & 15) as usize),
+        16..=31 => Operation::PushLiteralVariable((byte & 15) as usize),
+        32..=63 => Operation::PushLiteral((byte & 31) as usize),
+        64..=71 => Operation::PushTemporary((byte & 7) as usize),
+        72..=75 => Operation::PushTemporary((byte & 3) as usize + 8),
+        76 => Operation::PushReceiver,
+        77 => Operation::PushTrue,
+        78 => Operation::PushFalse,
+        79 => Operation::PushNil,
+        80 => Operation::PushInteger(0),
+        81 => Operation::PushInteger(1),
+        82 if extend_b == 1 => Operation::PushThisProcess,
+        82 => Operation::PushThisContext,
+        83 => Operation::Duplicate,
+        88 => Operation::ReturnReceiver,
+        89 => Operation::ReturnTrue,
+        90 => Operation::ReturnFalse,
+        91 => Operation::ReturnNil,
+        92 => Operation::ReturnTop,
+        93 => Operation::BlockReturnNil,
+        94 => Operation::BlockReturnTop,
+        95 => Operation::Nop,
+        96..=127 => special_send(byte as usize - 96),
+        128..=175 => Operation::Send {
+            selector: Selector::Literal((byte & 15) as usize),
+            arguments: (byte as usize - 128) / 16,
+        },
+        176..=183 => Operation::Jump(jump((byte & 7) as i64 + 1)),
+        184..=191 => Operation::JumpIfTrue(jump((byte & 7) as i64 + 1)),
+        192..=199 => Operation::JumpIfFalse(jump((byte & 7) as i64 + 1)),
+        200..=207 => Operation::StoreReceiverVariable {
+            index: (byte & 7) as usize,
+            pop: true,
+        },
+        208..=215 => Operation::StoreTemporary {
+            index: (byte & 7) as usize,
+            pop: true,
+        },
+        216 => Operation::Pop,
+        217 => Operation::Trap,
+        226 => Operation::PushReceiverVariable(extended(first, extend_a, 256)),
+        227 => Operation::PushLiteralVariable(extended(first, extend_a, 256)),
+        228 => Operation::PushLiteral(extended(first, extend_a, 256)),
+        229 => Operation::PushTemporary(first),
+        231 if first & 0x80 != 0 => Operation::PopIntoNewArray(first & 0x7F),
+        231 => Operation::PushNewArray(first & 0x7F),
+        232 => Operation::PushInteger(first as i64 + extend_b * 256),
+        233 => Operation::PushCharacter((first as i64 + extend_b * 256) as u32),
+        234 => Operation::Send {
+            selector: Selector::Literal(extended(first >> 3, extend_a, 32)),
+            arguments: extended(first & 7, extend_b, 8),
+        },
+        235 => Operation::SuperSend {
+            literal: extended(first >> 3, extend_a, 32),
+            arguments: extended(first & 7, extend_b & 63, 8),
+            directed: extend_b >= 64,
+        },
+        237 => Operation::Jump(jump(first as i64 + extend_b * 256)),
+        238 => Operation::JumpIfTrue(jump(first as i64 + extend_b * 256)),
+        239 => Operation::JumpIfFalse(jump(first as i64 + extend_b * 256)),
+        240 | 243 => Operation::StoreReceiverVariable {
+            index: extended(first, extend_a, 256),
+            pop: byte == 240,
+        },
+        241 | 244 => Operation::StoreLiteralVariable {
+            index: extended(first, extend_a, 256),
+            pop: byte == 241,
+        },
+        242 | 245 => Operation::StoreTemporary {
+            index: first,
+            pop: byte == 242,
+        },
+        248 => Operation::CallPrimitive {
+            index: (first | (second & 0x7F) << 8) as u16,
+            inlined: second & 0x80 != 0,
+        },
+        249 => Operation::PushFullBlock {
+            literal: extended(first, extend_a, 256),
+            copied: second & 63,
+            ignore_outer_context: second & 64 != 0,
+            receiver_on_stack: second & 128 != 0,
+        },
+        250 => Operation::PushClosure {
+            copied: ((first >> 3) & 7) + (extend_a as usize / 16) * 8,
+            arguments: (first & 7) + (extend_a as usize % 16) * 8,
+            size: extended(second, extend_b, 256),
+        },
+        251 => Operation::PushRemoteTemporary {
+            index: first,
+            vector: second & 0x7F,
+        },
+        252 | 253 => Operation::StoreRemoteTemporary {
+            index: first,
+            vector: second & 0x7F,
+            pop: byte == 253,
+        },
+        254 => Operation::BranchIfNotInstanceOf {
+            literal: extended(first, extend_a, 256),
+            target: jump(second as i64 + extend_b * 256),
+        },
+        _ => Operation::Unknown,
+    };
+    (next - start, operation)
+}
+
+/// Decode an instruction of the V3PlusClosures bytecode set at an index.
+/// Return the amount of bytes and the operation
+fn decode_v3_plus_closures(
+    bytecodes: &[u8],
+    index: usize,
+    initial_pc: usize,
+) -> (usize, Operation) {
+    let byte = bytecodes[index];
+    let size = match byte {
+        128..=131 | 133 | 134 | 138 | 160..=175 => 2,
+        132 | 139..=142 => 3,
+        143 => 4,
+        _ => 1,
+    };
+    let next = index + size;
+    let Some(operands) = bytecodes.get(index + 1..next) else {
+        return (bytecodes.len() - index, Operation::Unknown);
+    };
+    let first = operands.first().copied().unwrap_or(0) as usize;
+    let second = operands.get(1).copied().unwrap_or(0) as usize;
+    let third = operands.get(2).copied().unwrap_or(0) as usize;
+    let jump = |distance: i64| jump_target(initial_pc, next, distance);
+
+    let operation = match byte {
+        0..=15 => Operation::PushReceiverVariable((byte & 15) as usize),
+        16..=31 => Operation::PushTemporary((byte & 15) as usize),
+        32..=63 => Operation::PushLiteral((byte & 31) as usize),
+        64..=95 => Operation::PushLiteralVariable((byte & 31) as usize),
+        96..=103 => Operation::StoreReceiverVariable {
+            index: (byte & 7) as usize,
+            pop: true,
+        },
+        104..=111 => Operation::StoreTemporary {
+            index: (byte & 7) as usize,
+            pop: true,
+        },
+        112 => Operation::PushReceiver,
+        113 => Operation::PushTrue,
+        114 => Operation::PushFalse,
+        115 => Operation::PushNil,
+        116..=119 => Operation::PushInteger(byte as i64 - 117),
+        120 => Operation::ReturnReceiver,
+        121 => Operation::ReturnTrue,
+        122 => Operation::ReturnFalse,
+        123 => Operation::ReturnNil,
+        124 => Operation::ReturnTop,
+        125 => Operation::BlockReturnTop,
+        128 => match first >> 6 {
+            0 => Operation::PushReceiverVariable(first & 63),
+            1 => Operation::PushTemporary(first & 63),
+            2 => Operation::PushLiteral(first & 63),
+            _ => Operation::PushLiteralVariable(first & 63),
+        },
+        129 | 130 => {
+            let (index, pop) = (first & 63, byte == 130);
+            match first >> 6 {
+                0 => Operation::StoreReceiverVariable { index, pop },
+                1 => Operation::StoreTemporary { index, pop },
+                3 => Operation::StoreLiteralVariable { index, pop },
+                _ => Operation::Unknown,
+            }
+        }
+        131 => Operation::Send {
+            selector: Selector::Literal(first & 31),
+            arguments: first >> 5,
+        },
+        132 => match first >> 5 {
+            0 => Operation::Send {
+                selector: Selector::Literal(second),
+                arguments: first & 31,
+            },
+            1 => Operation::SuperSend {
+                literal: second,
+                arguments: first & 31,
+                directed: false,
+            },
+            2 => Operation::PushReceiverVariable(second),
+            3 => Operation::PushLiteral(second),
+            4 => Operation::PushLiteralVariable(second),
+            5 => Operation::StoreReceiverVariable {
+                index: second,
+                pop: false,
+            },
+            6 => Operation::StoreReceiverVariable {
+                index: second,
+                pop: true,
+            },
+            _ => Operation::StoreLiteralVariable {
+                index: second,
+                pop: false,
+            },
+        },
+        133 => Operation::SuperSend {
+            literal: first & 31,
+            arguments: first >> 5,
+            directed: false,
+        },
+        134 => Operation::Send {
+            selector: Selector::Literal(first & 63),
+            arguments: first >> 6,
+        },
+        135 => Operation::Pop,
+        136 => Operation::Duplicate,
+        137 => Operation::PushThisContext,
+        138 if first & 0x80 != 0 => Operation::PopIntoNewArray(first & 0x7F),
+        138 => Operation::PushNewArray(first & 0x7F),
+        139 => Operation::CallPrimitive {
+            index: (first | second << 8) as u16,
+            inlined: false,
+        },
+        140 => Operation::PushRemoteTemporary {
+            index: first,
+            vector: second,
+        },
+        141 | 142 => Operation::StoreRemoteTemporary {
+            index: first,
+            vector: second,
+            pop: byte == 142,
+        },
+        143 => Operation::PushClosure {
+            copied: first >> 4,
+            arguments: first & 15,
+            size: second << 8 | third,
+        },
+        144..=151 => Operation::Jump(jump((byte & 7) as i64 + 1)),
+        152..=159 => Operation::JumpIfFalse(jump((byte & 7) as i64 + 1)),
+        160..=167 => Operation::Jump(jump(((byte & 7) as i64 - 4) * 256 + first as i64)),
+        168..=171 => Operation::JumpIfTrue(jump((byte & 3) as i64 * 256 + first as i64)),
+        172..=175 => Operation::JumpIfFalse(jump((byte & 3) as i64 * 256 + first as i64)),
+        176..=207 => special_send(byte as usize - 176),
+        208..=255 => Operation::Send {
+            selector: Selector::Literal((byte & 15) as usize),
+            arguments: (byte as usize - 208) / 16,
+        },
+        _ => Operation::Unknown,
+    };
+    (size, operation)
+}
+
+fn special_send(index: usize) -> Operation {
+    let (selector, arguments) = SPECIAL_SELECTORS[index];
+    Operation::Send {
+        selector: Selector::Special(selector),
+        arguments,
+    }
+}
+
+/// Return a textual listing of the bytecodes of a method, one instruction per line
+/// in the form `pc <bytes> mnemonic`, with literals printed by name
+pub fn listing(method: &CompiledMethod) -> String {
+    let mut listing = String::new();
+    for instruction in disassemble(method) {
+        let bytes = instruction
+            .bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            listing,
+            "{} <{}> {}",
+            instruction.pc,
+            bytes,
+            mnemonic(&instruction.operation, method)
+        )
+        .unwrap();
+    }
+    listing
+}
+
+/// Return how an operation is shown in a listing, resolving literals of a given method
+pub fn mnemonic(operation: &Operation, method: &CompiledMethod) -> String {
+    let literal = |index: usize| {
+        method
+            .literal_at(index)
+            .map(|literal| print_string(literal, LITERAL_PRINT_DEPTH, LITERAL_PRINT_WIDTH))
+            .unwrap_or_else(|| format!("<missing literal {}>", index))
+    };
+    let selector = |index: usize| {
+        method
+            .literal_at(index)
+            .and_then(|literal| SmalltalkString::try_from(literal).ok())
+            .map(|selector| format!("#{}", selector))
+            .unwrap_or_else(|| literal(index))
+    };
+    // the name of a binding (a global, class or pool variable) is its key
+    let variable = |index: usize| {
+        method
+            .literal_at(index)
+            .and_then(|literal| literal.as_object().ok()?.inst_var_at(0))
+            .and_then(|key| SmalltalkString::try_from(key).ok())
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| literal(index))
+    };
+    let pop_or_store = |pop: bool| if pop { "popInto" } else { "storeInto" };
+
+    match *operation {
+        Operation::PushReceiverVariable(index) => format!("pushRcvr: {}", index),
+        Operation::PushLiteralVariable(index) => format!("pushLitVar: {}", variable(index)),
+        Operation::PushLiteral(index) => format!("pushConstant: {}", literal(index)),
+        Operation::PushTemporary(index) => format!("pushTemp: {}", index),
+        Operation::PushReceiver => "self".to_string(),
+        Operation::PushTrue => "pushConstant: true".to_string(),
+        Operation::PushFalse => "pushConstant: false".to_string(),
+        Operation::PushNil => "pushConstant: nil".to_string(),
+        Operation::PushInteger(value) => format!("pushConstant: {}", value),
+        Operation::PushCharacter(value) => match char::from_u32(value) {
+            Some(character) => format!("pushConstant: ${}", character),
+            None => format!("pushConstant: (Character value: {})", value),
+        },
+        Operation::PushThisContext => "pushThisContext:".to_string(),
+        Operation::PushThisProcess => "pushThisProcess".to_string(),
+        Operation::PushNewArray(size) => format!("push: (Array new: {})", size),
+        Operation::PopIntoNewArray(size) => format!("pop {} into: (Array new: {})", size, size),
+        Operation::PushRemoteTemporary { index, vector } => {
+            format!("pushTemp: {} inVectorAt: {}", index, vector)
+        }
+        Operation::StoreRemoteTemporary { index, vector, pop } => {
+            format!(
+                "{}Temp: {} inVectorAt: {}",
+                pop_or_store(pop),
+                index,
+                vector
+            )
+        }
+        Operation::StoreReceiverVariable { index, pop } => {
+            format!("{}Rcvr: {}", pop_or_store(pop), index)
+        }
+        Operation::StoreLiteralVariable { index, pop } => {
+            format!("{}LitVar: {}", pop_or_store(pop), variable(index))
+        }
+        Operation::StoreTemporary { index, pop } => format!("{}Temp: {}", pop_or_store(pop), index),
+        Operation::Duplicate => "dup".to_string(),
+        Operation::Pop => "pop".to_string(),
+        Operation::Nop => "nop".to_string(),
+        Operation::ReturnReceiver => "returnSelf".to_string(),
+        Operation::ReturnTrue => "return: true".to_string(),
+        Operation::ReturnFalse => "return: false".to_string(),
+        Operation::ReturnNil => "return: nil".to_string(),
+        Operation::ReturnTop => "returnTop".to_string(),
+        Operation::BlockReturnNil => "blockReturn: nil".to_string(),
+        Operation::BlockReturnTop => "blockReturn".to_string(),
+        Operation::Send {
+            selector: Selector::Literal(index),
+            arguments,
+        } => format!("send: {} ({} args)", selector(index), arguments),
+        Operation::Send {
+            selector: Selector::Special(name),
+            arguments,
+        } => format!("send: #{} ({} args)", name, arguments),
+        Operation::SuperSend {
+            literal,
+            arguments,
+            directed,
+        } => format!(
+            "{}: {} ({} args)",
+            if directed {
+                "directedSuperSend"
+            } else {
+                "superSend"
+            },
+            selector(literal),
+            arguments
+        ),
+        Operation::Jump(target) => format!("jumpTo: {}", target),
+        Operation::JumpIfTrue(target) => format!("jumpTrue: {}", target),
+        Operation::JumpIfFalse(target) => format!("jumpFalse: {}", target),
+        Operation::CallPrimitive { index, inlined } => {
+            if inlined {
+                format!("callInlinedPrimitive: {}", index)
+            } else {
+                format!("callPrimitive: {}", index)
+            }
+        }
+        Operation::PushFullBlock {
+            literal: index,
+            copied,
+            ..
+        } => format!("pushFullClosure: {} numCopied: {}", literal(index), copied),
+        Operation::PushClosure {
+            copied,
+            arguments,
+            size,
+        } => format!(
+            "closureNumCopied: {} numArgs: {} bytes: {}",
+            copied, arguments, size
+        ),
+        Operation::BranchIfNotInstanceOf {
+            literal: index,
+            target,
+        } => format!("branchIfNotInstanceOf: {} to: {}", literal(index), target),
+        Operation::Trap => "trap".to_string(),
+        Operation::Unknown => "unknown bytecode".to_string(),
+    }
+}
+
+/// Return a textual listing of the bytecodes of a CompiledMethod or a CompiledBlock
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveDisassembleMethod() {
+    let method = Smalltalk::stack_ref(StackOffset::new(0));
+    match CompiledMethodRef::try_from(method) {
+        Ok(method) => {
+            match StringClasses::of_image()
+                .and_then(|classes| classes.new_string(&listing(&method)))
+            {
+                Ok(string) => Smalltalk::method_return_value(ObjectPointer::from(string.as_i64())),
+                Err(error) => {
+                    error!("Failed to allocate the listing: {}", error);
+                    Smalltalk::primitive_fail();
+                }
+            }
+        }
+        Err(error) => {
+            error!("{}", error);
+            Smalltalk::primitive_fail();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{byte_symbol_class, new_method, with_test_heap, ALTERNATE_BYTECODE_SET};
+    use vm_object_model::{Immediate, ObjectFormat};
+
+    #[test]
+    fn decode_sista_v1_extensions() {
+        // push -300, a send with an extended literal index, a backward jump and a return
+        let bytecodes = [
+            225, 0xFE, 232, 0xD4, 224, 1, 234, 0x09, 225, 0xFF, 237, 0xF4, 92,
+        ];
+        let instructions = decode(&bytecodes, BytecodeSet::SistaV1, 16);
+        let operations = instructions
+            .iter()
+            .map(|each| each.operation)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            operations,
+            vec![
+                Operation::PushInteger(-300),
+                Operation::Send {
+                    selector: Selector::Literal(33),
+                    arguments: 1
+                },
+                Operation::Jump(17),
+                Operation::ReturnTop,
+            ]
+        );
+        assert_eq!(
+            instructions.iter().map(|each| each.pc).collect::<Vec<_>>(),
+            vec![17, 21, 25, 29]
+        );
+        assert_eq!(instructions[1].bytes, vec![224, 1, 234, 0x09]);
+    }
+
+    #[test]
+    fn decode_v3_plus_closures() {
+        let bytecodes = [
+            139, 60, 0, 112, 16, 176, 143, 0x10, 0, 2, 0x10, 125, 164, 1, 124,
+        ];
+        let operations = decode(&bytecodes, BytecodeSet::V3PlusClosures, 8)
+            .into_iter()
+            .map(|each| each.operation)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            operations,
+            vec![
+                Operation::CallPrimitive {
+                    index: 60,
+                    inlined: false
+                },
+                Operation::PushReceiver,
+                Operation::PushTemporary(0),
+                Operation::Send {
+                    selector: Selector::Special("+"),
+                    arguments: 1
+                },
+                Operation::PushClosure {
+                    copied: 1,
+                    arguments: 0,
+                    size: 2
+                },
+                Operation::PushTemporary(0),
+                Operation::BlockReturnTop,
+                Operation::Jump(24),
+                Operation::ReturnTop,
+            ]
+        );
+    }
+
+    #[test]
+    fn listing_resolves_literals() {
+        with_test_heap(|heap| {
+            let symbol_class = byte_symbol_class(heap);
+            let binding_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+            let transcript = heap.new_object(
+                binding_class,
+                &[
+                    heap.new_bytes(symbol_class, b"Transcript").into(),
+                    heap.nil().into(),
+                ],
+            );
+            let show = heap.new_bytes(symbol_class, b"show:");
+
+            // Transcript show: 42. ^ self
+            let method = new_method(
+                heap,
+                ALTERNATE_BYTECODE_SET | 3 | 1 << 24,
+                &[
+                    transcript.into(),
+                    show.into(),
+                    Immediate::new_i64(42).into(),
+                ],
+                &[16, 34, 145, 216, 88, 0],
+            );
+
+            assert_eq!(
+                listing(&method),
+                "33 <10> pushLitVar: Transcript\n\
+                 34 <22> pushConstant: 42\n\
+                 35 <91> send: #show: (1 args)\n\
+                 36 <D8> pop\n\
+                 37 <58> returnSelf\n"
+            );
+        })
+    }
+}
@@ -0,0 +1,522 @@
+use crate::objects::{Array, Association, Dictionary, NumberClasses, StringClasses};
+use num::BigInt;
+use std::collections::HashMap;
+use thiserror::Error;
+use vm_bindings::{ObjectPointer, Smalltalk, StackOffset};
+use vm_object_model::{AnyObjectRef, ByteArrayRef, Immediate, Object, ObjectRef, RawObjectPointer};
+use vm_object_model_derive::PharoObject;
+
+/// Nested arrays and objects deeper than this are rejected instead of exhausting the stack
+const MAX_DEPTH: usize = 512;
+
+pub type JsonResult<T> = Result<T, JsonError>;
+
+#[derive(Error, Debug)]
+pub enum JsonError {
+    #[error("{message} at position {position}")]
+    Syntax {
+        message: String,
+        /// 1-based byte position in the input, the same way Smalltalk streams count
+        position: usize,
+    },
+    #[error("Failed to allocate a json value: {0}")]
+    Object(#[from] vm_object_model::Error),
+}
+
+/// The classes of the objects that the parser allocates,
+/// the collections and strings are provided by the image
+#[derive(Debug, Copy, Clone)]
+pub struct JsonClasses {
+    pub dictionary: ObjectRef,
+    pub association: ObjectRef,
+    pub strings: StringClasses,
+    pub numbers: NumberClasses,
+}
+
+/// The error object that is answered to the image when the input is not valid json
+#[derive(Debug, PharoObject)]
+#[repr(C)]
+pub struct JsonParseError {
+    this: Object,
+    message: AnyObjectRef,
+    position: Immediate,
+}
+
+/// Parse a UTF-8 encoded json document into Pharo objects:
+/// objects become Dictionaries with String keys, arrays become Arrays,
+/// strings become ByteStrings (or WideStrings if they do not fit in Latin-1),
+/// numbers become Integers or Floats, and `true`, `false` and `null` the corresponding singletons.
+pub fn parse_json(input: &[u8], classes: JsonClasses) -> JsonResult<AnyObjectRef> {
+    if let Err(error) = std::str::from_utf8(input) {
+        return Err(JsonError::Syntax {
+            message: "Invalid UTF-8".to_string(),
+            position: error.valid_up_to() + 1,
+        });
+    }
+
+    let mut parser = JsonParser {
+        input,
+        position: 0,
+        depth: 0,
+        classes,
+    };
+
+    parser.skip_whitespace();
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        return parser.syntax_error("Unexpected trailing characters");
+    }
+    Ok(value)
+}
+
+/// A single pass parser that allocates objects as soon as a value is complete
+struct JsonParser<'input> {
+    input: &'input [u8],
+    /// 0-based index of the next byte
+    position: usize,
+    depth: usize,
+    classes: JsonClasses,
+}
+
+impl JsonParser<'_> {
+    fn parse_value(&mut self) -> JsonResult<AnyObjectRef> {
+        match self.peek() {
+            Some(b'{') => self.nested(Self::parse_object),
+            Some(b'[') => self.nested(Self::parse_array),
+            Some(b'"') => {
+                let string = self.parse_string()?;
+                Ok(self.classes.strings.new_string(&string)?)
+            }
+            Some(b't') => self.parse_literal("true", Smalltalk::true_object().as_i64()),
+            Some(b'f') => self.parse_literal("false", Smalltalk::false_object().as_i64()),
+            Some(b'n') => self.parse_literal("null", Smalltalk::nil_object().as_i64()),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => self.syntax_error("Unexpected character"),
+            None => self.syntax_error("Unexpected end of input"),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> JsonResult<AnyObjectRef>,
+    ) -> JsonResult<AnyObjectRef> {
+        if self.depth >= MAX_DEPTH {
+            return self.syntax_error("Too deeply nested");
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_object(&mut self) -> JsonResult<AnyObjectRef> {
+        self.expect(b'{')?;
+
+        // json allows duplicate keys, the last one wins but keeps the place of the first
+        let mut members: Vec<(String, AnyObjectRef)> = vec![];
+        let mut indices: HashMap<String, usize> = HashMap::new();
+
+        self.skip_whitespace();
+        if !self.consume(b'}') {
+            loop {
+                self.skip_whitespace();
+                if self.peek() != Some(b'"') {
+                    return self.syntax_error("Expected a string key");
+                }
+                let key = self.parse_string()?;
+                self.skip_whitespace();
+                self.expect(b':')?;
+                self.skip_whitespace();
+                let value = self.parse_value()?;
+
+                match indices.get(&key) {
+                    Some(index) => members[*index].1 = value,
+                    None => {
+                        indices.insert(key.clone(), members.len());
+                        members.push((key, value));
+                    }
+                }
+
+                self.skip_whitespace();
+                if self.consume(b'}') {
+                    break;
+                }
+                self.expect(b',')?;
+            }
+        }
+
+        let mut dictionary = Dictionary::with_capacity(self.classes.dictionary, members.len())?;
+        for (key, value) in members {
+            let mut association = Association::new(self.classes.association)?;
+            association.set_key(self.classes.strings.new_string(&key)?)?;
+            association.set_value(value)?;
+            dictionary.add_new_association(association)?;
+        }
+        Ok(dictionary.into())
+    }
+
+    fn parse_array(&mut self) -> JsonResult<AnyObjectRef> {
+        self.expect(b'[')?;
+
+        let mut items = vec![];
+        self.skip_whitespace();
+        if !self.consume(b']') {
+            loop {
+                self.skip_whitespace();
+                items.push(self.parse_value()?);
+                self.skip_whitespace();
+                if self.consume(b']') {
+                    break;
+                }
+                self.expect(b',')?;
+            }
+        }
+
+        let mut array = Array::new(items.len())?;
+        for (index, item) in items.into_iter().enumerate() {
+            array.insert(index, item)?;
+        }
+        Ok(array.into())
+    }
+
+    fn parse_string(&mut self) -> JsonResult<String> {
+        self.expect(b'"')?;
+
+        let mut bytes = vec![];
+        loop {
+            match self.next() {
+                None => return self.syntax_error("Unterminated string"),
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    let escaped = match self.next() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => self.parse_unicode_escape()?,
+                        _ => {
+                            self.position -= 1;
+                            return self.syntax_error("Invalid escape sequence");
+                        }
+                    };
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                Some(byte) if byte < 0x20 => {
+                    self.position -= 1;
+                    return self.syntax_error("Control character in string");
+                }
+                Some(byte) => bytes.push(byte),
+            }
+        }
+
+        // the input is valid UTF-8 and is only split at ASCII characters
+        Ok(String::from_utf8(bytes).unwrap())
+    }
+
+    /// Parse the hex digits of `\uXXXX`, combining a surrogate pair into one character
+    fn parse_unicode_escape(&mut self) -> JsonResult<char> {
+        let high = self.parse_hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high as u32)
+                .map_or_else(|| self.syntax_error("Invalid unicode escape"), Ok);
+        }
+
+        if !(self.consume(b'\\') && self.consume(b'u')) {
+            return self.syntax_error("Expected a low surrogate");
+        }
+        let low = self.parse_hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return self.syntax_error("Invalid low surrogate");
+        }
+
+        let code_point = 0x10000 + (((high as u32) - 0xD800) << 10) + ((low as u32) - 0xDC00);
+        Ok(char::from_u32(code_point).unwrap())
+    }
+
+    fn parse_hex4(&mut self) -> JsonResult<u16> {
+        let digits = self
+            .input
+            .get(self.position..self.position + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .filter(|digits| digits.bytes().all(|each| each.is_ascii_hexdigit()))
+            .and_then(|digits| u16::from_str_radix(digits, 16).ok());
+
+        match digits {
+            Some(value) => {
+                self.position += 4;
+                Ok(value)
+            }
+            None => self.syntax_error("Expected four hex digits"),
+        }
+    }
+
+    fn parse_number(&mut self) -> JsonResult<AnyObjectRef> {
+        let start = self.position;
+        let mut is_float = false;
+
+        self.consume(b'-');
+        match self.next() {
+            Some(b'0') => {}
+            Some(b'1'..=b'9') => self.skip_digits(),
+            _ => {
+                self.position = start;
+                return self.syntax_error("Invalid number");
+            }
+        }
+        if self.consume(b'.') {
+            is_float = true;
+            if !self.peek().is_some_and(|each| each.is_ascii_digit()) {
+                return self.syntax_error("Expected a digit after the decimal point");
+            }
+            self.skip_digits();
+        }
+        if self.consume(b'e') || self.consume(b'E') {
+            is_float = true;
+            if !self.consume(b'+') {
+                self.consume(b'-');
+            }
+            if !self.peek().is_some_and(|each| each.is_ascii_digit()) {
+                return self.syntax_error("Expected a digit in the exponent");
+            }
+            self.skip_digits();
+        }
+
+        // the number only consists of ASCII characters
+        let literal = std::str::from_utf8(&self.input[start..self.position]).unwrap();
+
+        if is_float {
+            Ok(self
+                .classes
+                .numbers
+                .new_float(literal.parse::<f64>().unwrap())?)
+        } else {
+            // integers that do not fit in a SmallInteger become large integers
+            Ok(self
+                .classes
+                .numbers
+                .new_integer(&literal.parse::<BigInt>().unwrap())?)
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, object: i64) -> JsonResult<AnyObjectRef> {
+        if self.input[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(AnyObjectRef::from(RawObjectPointer::from(object)))
+        } else {
+            self.syntax_error("Invalid literal")
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn skip_digits(&mut self) {
+        while self.peek().is_some_and(|each| each.is_ascii_digit()) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn consume(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> JsonResult<()> {
+        if self.consume(byte) {
+            Ok(())
+        } else {
+            self.syntax_error(&format!("Expected '{}'", byte as char))
+        }
+    }
+
+    fn syntax_error<T>(&self, message: &str) -> JsonResult<T> {
+        Err(JsonError::Syntax {
+            message: message.to_string(),
+            position: self.position + 1,
+        })
+    }
+}
+
+/// Parse a json document from a ByteString or a ByteArray.
+/// Answers the parsed value, or an instance of the error class with the message and the position
+/// if the input is malformed.
+///
+/// Stack: input, dictionary class, association class, byte string class, wide string class, error class
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveParseJson() {
+    let input = Smalltalk::stack_ref(StackOffset::new(5));
+    let classes =
+        [4, 3, 2, 1, 0].map(|offset| Smalltalk::stack_ref(StackOffset::new(offset)).as_object());
+
+    let [Ok(dictionary), Ok(association), Ok(byte_string), Ok(wide_string), Ok(error_class)] =
+        classes
+    else {
+        error!("Json classes must be objects");
+        return Smalltalk::primitive_fail();
+    };
+
+    let Ok(input) = ByteArrayRef::try_from(input) else {
+        error!("Json input must be a ByteString or a ByteArray");
+        return Smalltalk::primitive_fail();
+    };
+
+    let Some(numbers) = NumberClasses::from_class_table() else {
+        error!("The class table does not have the classes of large integers and boxed floats");
+        return Smalltalk::primitive_fail();
+    };
+
+    let classes = JsonClasses {
+        dictionary,
+        association,
+        strings: StringClasses {
+            byte_string,
+            wide_string,
+        },
+        numbers,
+    };
+
+    let result = parse_json(input.as_slice(), classes).or_else(|error| match error {
+        JsonError::Syntax { message, position } => {
+            let mut json_error = Smalltalk::instantiate::<JsonParseErrorRef>(error_class)?;
+            json_error.set_message(classes.strings.new_string(&message)?)?;
+            json_error.set_position(Immediate::new_i64(position as i64))?;
+            Ok(json_error.into())
+        }
+        JsonError::Object(error) => Err(error),
+    });
+
+    match result {
+        Ok(value) => Smalltalk::method_return_value(ObjectPointer::from(value.as_i64())),
+        Err(error) => {
+            error!("Failed to parse json: {}", error);
+            Smalltalk::primitive_fail();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{float_value, integer_value, with_test_heap, ArrayRef, DictionaryRef};
+    use vm_object_model::{ByteStringRef, ObjectFormat, TestHeap, WideStringRef};
+
+    fn json_classes(heap: &TestHeap) -> JsonClasses {
+        JsonClasses {
+            dictionary: heap.new_class(ObjectFormat::NonIndexable, 2),
+            association: heap.new_class(ObjectFormat::NonIndexable, 2),
+            strings: StringClasses {
+                byte_string: heap.class_byte_string(),
+                wide_string: heap.new_class(ObjectFormat::Indexable32(10), 0),
+            },
+            numbers: NumberClasses::from_class_table().unwrap(),
+        }
+    }
+
+    fn parse(heap: &TestHeap, input: &str) -> JsonResult<AnyObjectRef> {
+        parse_json(input.as_bytes(), json_classes(heap))
+    }
+
+    fn syntax_error_position(heap: &TestHeap, input: &str) -> usize {
+        match parse(heap, input) {
+            Err(JsonError::Syntax { position, .. }) => position,
+            other => panic!("Expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_scalars() {
+        with_test_heap(|heap| {
+            assert_eq!(parse(heap, " 42 ").unwrap().as_integer(), Some(42));
+            assert_eq!(parse(heap, "-7").unwrap().as_integer(), Some(-7));
+            let large = "-123456789012345678901234567890";
+            assert_eq!(
+                integer_value(parse(heap, large).unwrap()).unwrap(),
+                large.parse::<BigInt>().unwrap()
+            );
+            assert_eq!(float_value(parse(heap, "1e300").unwrap()).unwrap(), 1e300);
+            assert_eq!(
+                parse(heap, "1.5e2")
+                    .unwrap()
+                    .as_immediate()
+                    .unwrap()
+                    .as_float(),
+                Some(150.0)
+            );
+            assert!(parse(heap, "true")
+                .unwrap()
+                .equals(&heap.true_object().into())
+                .unwrap());
+            assert!(parse(heap, "null")
+                .unwrap()
+                .equals(&heap.nil().into())
+                .unwrap());
+        })
+    }
+
+    #[test]
+    fn parse_strings() {
+        with_test_heap(|heap| {
+            let string = ByteStringRef::try_from(parse(heap, r#""café \"x\"""#).unwrap());
+            assert_eq!(string.unwrap().as_bytes(), b"caf\xe9 \"x\"");
+
+            let wide = WideStringRef::try_from(parse(heap, r#""😀!""#).unwrap());
+            assert_eq!(wide.unwrap().as_slice(), &[0x1F600, '!' as u32]);
+        })
+    }
+
+    #[test]
+    fn parse_nested() {
+        with_test_heap(|heap| {
+            let value = parse(heap, r#"{"a": [1, 2, {}], "b": 3, "a": [4]}"#).unwrap();
+            let dictionary = DictionaryRef::try_from(value).unwrap();
+            assert_eq!(dictionary.len(), 2);
+
+            let association = dictionary
+                .associations()
+                .find(|each| ByteStringRef::try_from(each.key()).unwrap().as_bytes() == b"a")
+                .unwrap();
+            let array = ArrayRef::try_from(association.value()).unwrap();
+            assert_eq!(array.len(), 1);
+            assert_eq!(array[0].as_integer(), Some(4));
+        })
+    }
+
+    #[test]
+    fn syntax_errors() {
+        with_test_heap(|heap| {
+            assert_eq!(syntax_error_position(heap, ""), 1);
+            assert_eq!(syntax_error_position(heap, "[1, 2"), 6);
+            assert_eq!(syntax_error_position(heap, "[1,, 2]"), 4);
+            assert_eq!(syntax_error_position(heap, r#"{"a" 1}"#), 6);
+            assert_eq!(syntax_error_position(heap, "01"), 2);
+            assert_eq!(syntax_error_position(heap, r#""\x""#), 3);
+            assert_eq!(
+                syntax_error_position(heap, &"[".repeat(MAX_DEPTH + 1)),
+                MAX_DEPTH + 1
+            );
+        })
+    }
+}
@@ -11,11 +11,13 @@ extern crate log;
 pub extern crate android_activity;
 
 mod constellation;
+mod disassembler;
 mod error;
 mod event_loop;
 #[cfg(feature = "ffi")]
 mod ffi;
 mod image_finder;
+mod json;
 mod logger;
 mod version;
 mod virtual_machine;
@@ -27,11 +29,13 @@ mod pharo_compiler;
 mod telemetry;
 
 pub use constellation::Constellation;
+pub use disassembler::*;
 pub use error::{ApplicationError, Result};
 pub use event_loop::{EventLoop, EventLoopMessage, EventLoopWaker};
 #[cfg(feature = "ffi")]
 pub use ffi::{primitiveEventLoopCallout, primitiveExtractReturnValue, EventLoopCallout};
 pub use image_finder::*;
+pub use json::*;
 pub use logger::*;
 pub use telemetry::*;
 pub use version::{fetch_version, print_short_version, print_version};
@@ -5,7 +5,7 @@ use std::ffi::{CStr, CString};
 use std::fmt::Debug;
 use std::os::raw::{c_char, c_int};
 use std::sync::Mutex;
-use vm_bindings::{ObjectFieldIndex, Smalltalk, StackOffset};
+use vm_bindings::{new_array_of, ObjectPointer, Smalltalk, StackOffset, ToSmalltalk};
 
 lazy_static! {
     pub static ref VM_LOGGER: Mutex<VirtualMachineLogger> = Mutex::new(VirtualMachineLogger::new());
@@ -85,6 +85,18 @@ pub struct LogSignal {
     pub message: String,
 }
 
+/// A log signal is passed to the image as an Array of its type, file name, function name and message
+impl ToSmalltalk for LogSignal {
+    fn to_smalltalk(&self) -> vm_bindings::anyhow::Result<ObjectPointer> {
+        new_array_of(&[
+            &self.log_type,
+            &self.file_name,
+            &self.function_name,
+            &self.message,
+        ])
+    }
+}
+
 #[derive(Debug)]
 pub struct NullLogger;
 impl Logger for NullLogger {
@@ -160,55 +172,15 @@ pub fn primitiveEnableLogSignal() {
 #[no_mangle]
 #[allow(non_snake_case)]
 pub fn primitiveGetEnabledLogSignals() {
-    let proxy = vm().proxy();
-
     let logs = VM_LOGGER.lock().unwrap().enabled_types();
-
-    let return_array = Smalltalk::primitive_instantiate_indexable_class_of_size(
-        Smalltalk::primitive_class_array(),
-        logs.len(),
-    );
-    for (index, log_type) in logs.iter().enumerate() {
-        let each_type = proxy.new_string(log_type);
-        Smalltalk::item_at_put(return_array, ObjectFieldIndex::new(index + 1), each_type);
-    }
-    Smalltalk::method_return_value(return_array);
+    Smalltalk::method_return(&logs);
 }
 
 #[no_mangle]
 #[allow(non_snake_case)]
 pub fn primitivePollLogger() {
-    let proxy = vm().proxy();
-
     let logs = VM_LOGGER.lock().unwrap().poll_all();
-
-    let return_array = Smalltalk::primitive_instantiate_indexable_class_of_size(
-        Smalltalk::primitive_class_array(),
-        logs.len(),
-    );
-    for (index, log) in logs.iter().enumerate() {
-        let each_log_array = Smalltalk::primitive_instantiate_indexable_class_of_size(
-            Smalltalk::primitive_class_array(),
-            4,
-        );
-
-        let log_type = proxy.new_string(log.log_type.as_str());
-        let file_name = proxy.new_string(log.file_name.as_str());
-        let function_name = proxy.new_string(log.function_name.as_str());
-        let message = proxy.new_string(log.message.as_str());
-
-        Smalltalk::item_at_put(each_log_array, ObjectFieldIndex::new(1), log_type);
-        Smalltalk::item_at_put(each_log_array, ObjectFieldIndex::new(2), file_name);
-        Smalltalk::item_at_put(each_log_array, ObjectFieldIndex::new(3), function_name);
-        Smalltalk::item_at_put(each_log_array, ObjectFieldIndex::new(4), message);
-
-        Smalltalk::item_at_put(
-            return_array,
-            ObjectFieldIndex::new(index + 1),
-            each_log_array,
-        );
-    }
-    Smalltalk::method_return_value(return_array);
+    Smalltalk::method_return(&logs);
 }
 
 #[no_mangle]
@@ -1,4 +1,4 @@
-use crate::assign_field;
+use crate::{assign_field, assign_field_unchecked};
 use std::fmt::Debug;
 use std::ops::{Deref, DerefMut, Index};
 use vm_bindings::{ObjectPointer, Smalltalk};
@@ -19,11 +19,24 @@ impl Array {
         self.as_slice().get(index).map(|item| item.clone())
     }
 
-    pub fn insert(&mut self, index: usize, object: impl Into<AnyObjectRef>) {
-        assign_field!(self, self.as_slice_mut()[index], object.into());
+    /// Store an object at a given index, failing if the array is read-only
+    pub fn insert(&mut self, index: usize, object: impl Into<AnyObjectRef>) -> Result<()> {
+        assign_field!(self, self.as_slice_mut()[index], object.into())
     }
 
-    pub fn copy_from(&mut self, start: usize, end: usize, source_slice: &[AnyObjectRef]) {
+    /// An unchecked version of `insert` that also writes into read-only arrays
+    pub fn insert_unchecked(&mut self, index: usize, object: impl Into<AnyObjectRef>) {
+        assign_field_unchecked!(self, self.as_slice_mut()[index], object.into());
+    }
+
+    pub fn copy_from(
+        &mut self,
+        start: usize,
+        end: usize,
+        source_slice: &[AnyObjectRef],
+    ) -> Result<()> {
+        self.check_mutable()?;
+
         let target_slice = &mut self.as_slice_mut()[start..end];
         target_slice.copy_from_slice(source_slice);
 
@@ -38,6 +51,7 @@ impl Array {
                 Smalltalk::possible_old_object_store_into(this_ptr);
             }
         }
+        Ok(())
     }
 
     pub fn len(&self) -> usize {
@@ -110,7 +124,7 @@ impl TryFrom<AnyObjectRef> for ArrayRef {
     type Error = Error;
 
     fn try_from(value: AnyObjectRef) -> Result<Self> {
-        let object_ref = value.as_object()?;
+        let object_ref = value.as_object()?.follow_forwarded();
         match object_ref.object_format() {
             ObjectFormat::IndexableWithoutInstVars | ObjectFormat::WeakIndexable => {
                 Ok(ArrayRef(object_ref))
@@ -125,3 +139,58 @@ impl From<ArrayRef> for AnyObjectRef {
         value.0.into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::with_test_heap;
+    use vm_object_model::Immediate;
+
+    #[test]
+    fn insert_and_get() {
+        with_test_heap(|heap| {
+            let mut array = Array::new(300).unwrap();
+            assert_eq!(array.len(), 300);
+            assert!(array[0].equals(&heap.nil().into()).unwrap());
+
+            array.insert(299, Immediate::new_i64(42)).unwrap();
+            assert_eq!(array.get(299).unwrap().as_integer(), Some(42));
+            assert!(array.get(300).is_none());
+        })
+    }
+
+    #[test]
+    fn insert_into_read_only_array() {
+        with_test_heap(|heap| {
+            let mut array = Array::new(2).unwrap();
+            heap.set_immutable(array.0, true);
+
+            let result = array.insert(0, Immediate::new_i64(42));
+            assert!(matches!(result, Err(Error::Immutable(_))));
+            assert!(array[0].equals(&heap.nil().into()).unwrap());
+            let result = array.copy_from(0, 1, &[Immediate::new_i64(42).into()]);
+            assert!(matches!(result, Err(Error::Immutable(_))));
+
+            array.insert_unchecked(1, Immediate::new_i64(42));
+            assert_eq!(array[1].as_integer(), Some(42));
+        })
+    }
+
+    #[test]
+    fn copy_from() {
+        with_test_heap(|_heap| {
+            let items = [
+                AnyObjectRef::from(Immediate::new_i64(1)),
+                AnyObjectRef::from(Immediate::new_i64(2)),
+            ];
+            let mut array = Array::new(4).unwrap();
+            array.copy_from(1, 3, &items).unwrap();
+
+            let integers = array
+                .iter()
+                .map(|each| each.as_integer())
+                .collect::<Vec<_>>();
+            assert_eq!(integers, vec![None, Some(1), Some(2), None]);
+        })
+    }
+}
@@ -1,10 +1,9 @@
-use crate::assign_field;
 use std::fmt::Debug;
-use std::ops::{Deref, DerefMut};
 use vm_bindings::Smalltalk;
-use vm_object_model::{AnyObjectRef, Error, Object, ObjectRef, Result};
+use vm_object_model::{AnyObjectRef, Object, ObjectRef, Result};
+use vm_object_model_derive::PharoObject;
 
-#[derive(Debug)]
+#[derive(Debug, PharoObject)]
 #[repr(C)]
 pub struct Association {
     this: Object,
@@ -24,63 +23,57 @@ impl Association {
     pub fn value(&self) -> AnyObjectRef {
         self.value
     }
-
-    pub fn set_key(&mut self, key: impl Into<AnyObjectRef>) {
-        assign_field!(self.key, key.into());
-    }
-
-    pub fn set_value(&mut self, value: impl Into<AnyObjectRef>) {
-        assign_field!(self.value, value.into());
-    }
 }
 
-impl Deref for Association {
-    type Target = Object;
-    fn deref(&self) -> &Self::Target {
-        &self.this
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::with_test_heap;
+    use vm_object_model::{Error, Immediate, ObjectFormat};
 
-#[derive(Debug, Copy, Clone)]
-#[repr(transparent)]
-pub struct AssociationRef(ObjectRef);
+    #[test]
+    fn set_key_and_value() {
+        with_test_heap(|heap| {
+            let class = heap.new_class(ObjectFormat::NonIndexable, 2);
+            let mut association = Association::new(class).unwrap();
+            association.set_key(heap.true_object()).unwrap();
+            association.set_value(Immediate::new_i64(42)).unwrap();
 
-impl Deref for AssociationRef {
-    type Target = Association;
-    fn deref(&self) -> &Self::Target {
-        unsafe { self.0.cast() }
+            assert!(association
+                .key()
+                .equals(&heap.true_object().into())
+                .unwrap());
+            assert_eq!(association.value().as_integer(), Some(42));
+        })
     }
-}
 
-impl DerefMut for AssociationRef {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { self.0.cast_mut() }
-    }
-}
-
-impl TryFrom<AnyObjectRef> for AssociationRef {
-    type Error = Error;
+    #[test]
+    fn set_value_of_read_only_association() {
+        with_test_heap(|heap| {
+            let class = heap.new_class(ObjectFormat::NonIndexable, 2);
+            let mut association = Association::new(class).unwrap();
+            heap.set_immutable(association.0, true);
 
-    fn try_from(value: AnyObjectRef) -> Result<Self> {
-        const EXPECTED_AMOUNT_OF_SLOTS: usize = 2;
-        let object = value.as_object()?;
-        let actual_amount_of_slots = object.amount_of_slots();
-
-        if actual_amount_of_slots != EXPECTED_AMOUNT_OF_SLOTS {
-            return Err(Error::WrongAmountOfSlots {
-                object: object.header().clone(),
-                expected: EXPECTED_AMOUNT_OF_SLOTS,
-                actual: actual_amount_of_slots,
-            }
-            .into());
-        }
-
-        Ok(Self(object))
+            let result = association.set_value(Immediate::new_i64(42));
+            assert!(matches!(result, Err(Error::Immutable(_))));
+            assert!(association.value().equals(&heap.nil().into()).unwrap());
+        })
     }
-}
 
-impl From<AssociationRef> for AnyObjectRef {
-    fn from(value: AssociationRef) -> Self {
-        value.0.into()
+    #[test]
+    fn wrong_amount_of_slots() {
+        with_test_heap(|heap| {
+            let nil = heap.nil().into();
+            let array = heap.new_array(&[nil, nil, nil]);
+            let result = AssociationRef::try_from(AnyObjectRef::from(array));
+            assert!(matches!(
+                result,
+                Err(Error::WrongAmountOfSlots {
+                    expected: 2,
+                    actual: 3,
+                    ..
+                })
+            ));
+        })
     }
 }
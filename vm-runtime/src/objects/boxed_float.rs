@@ -0,0 +1,59 @@
+use vm_bindings::Smalltalk;
+use vm_object_model::{AnyObjectRef, Error, ObjectFormat, ObjectRef, Result, WordArrayRef};
+
+/// A BoxedFloat64: a float whose exponent does not fit in a SmallFloat64 immediate.
+/// It is stored as two 32-bit words holding the 8 bytes of the float in platform order
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct BoxedFloat64(WordArrayRef);
+
+impl BoxedFloat64 {
+    const AMOUNT_OF_WORDS: usize = 2;
+
+    pub fn new(boxed_float_class: ObjectRef, value: f64) -> Result<Self> {
+        let mut words = Smalltalk::instantiate_indexable::<WordArrayRef>(
+            boxed_float_class,
+            Self::AMOUNT_OF_WORDS,
+        )?;
+        let bytes = value.to_ne_bytes();
+        for (word, chunk) in words.as_slice_mut()?.iter_mut().zip(bytes.chunks_exact(4)) {
+            *word = u32::from_ne_bytes(chunk.try_into().unwrap());
+        }
+        Ok(Self(words))
+    }
+
+    pub fn value(&self) -> f64 {
+        let mut bytes = [0u8; 8];
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(self.0.iter()) {
+            chunk.copy_from_slice(&word.to_ne_bytes());
+        }
+        f64::from_ne_bytes(bytes)
+    }
+}
+
+impl TryFrom<AnyObjectRef> for BoxedFloat64 {
+    type Error = Error;
+
+    fn try_from(value: AnyObjectRef) -> Result<Self> {
+        let object = value.as_object()?.follow_forwarded();
+        let is_boxed_float = object.header().class_index() == Smalltalk::BOXED_FLOAT_CLASS_INDEX;
+
+        match object.object_format() {
+            ObjectFormat::Indexable32(_) if is_boxed_float => {
+                let words = WordArrayRef::try_from(AnyObjectRef::from(object))?;
+                if words.len() == Self::AMOUNT_OF_WORDS {
+                    Ok(Self(words))
+                } else {
+                    Err(Error::InvalidType("BoxedFloat64".to_string()))
+                }
+            }
+            _ => Err(Error::InvalidType("BoxedFloat64".to_string())),
+        }
+    }
+}
+
+impl From<BoxedFloat64> for AnyObjectRef {
+    fn from(value: BoxedFloat64) -> Self {
+        value.0.into()
+    }
+}
@@ -1,30 +1,541 @@
-use std::ffi::c_void;
-use vm_object_model::{AnyObjectRef, Object, ObjectFormat};
+use crate::objects::SmalltalkString;
+use std::fmt::{Debug, Formatter};
+use std::ops::{Deref, DerefMut};
+use vm_bindings::{ObjectPointer, Smalltalk};
+use vm_object_model::{
+    AnyObjectRef, ClassRef, Error, Immediate, Object, ObjectFormat, ObjectRef, Result,
+};
 
-#[derive(Debug)]
-pub struct CompiledMethod<'obj> {
-    header: &'obj Object,
+/// The bytecode set of a method, selected by the sign of its header
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BytecodeSet {
+    /// The primary set, used by images that do not use Sista
+    V3PlusClosures,
+    /// The alternate set, used by Pharo
+    SistaV1,
 }
 
-impl<'obj> CompiledMethod<'obj> {
-    pub fn set_literal(&self, literal: AnyObjectRef, literal_index: usize) {
-        let compiled_method_header = self.header.first_fixed_field_ptr();
-        let len = self.header.amount_of_indexable_units();
+/// The header of a CompiledMethod or a CompiledBlock, a SmallInteger in the first slot:
+///  - bits 0..=14: number of literals
+///  - bit 15: jit without counters (Sista optimized method)
+///  - bit 16: has primitive
+///  - bit 17: needs a large frame
+///  - bits 18..=23: number of temporaries, including the arguments
+///  - bits 24..=27: number of arguments
+///  - sign: uses the alternate bytecode set
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct MethodHeader(i64);
 
-        let mut literal_ptr =
-            unsafe { compiled_method_header.offset((1 + literal_index as isize) * 8) }
-                as *mut *const c_void;
-        unsafe { *literal_ptr = literal.as_ptr() };
+impl MethodHeader {
+    const LITERALS_MASK: i64 = 0x7FFF;
+    const HAS_PRIMITIVE_BIT: i64 = 1 << 16;
+    const LARGE_FRAME_BIT: i64 = 1 << 17;
+    const TEMPORARIES_SHIFT: i64 = 18;
+    const TEMPORARIES_MASK: i64 = 0x3F;
+    const ARGUMENTS_SHIFT: i64 = 24;
+    const ARGUMENTS_MASK: i64 = 0xF;
+
+    pub fn new(value: i64) -> Self {
+        Self(value)
+    }
+
+    pub fn value(&self) -> i64 {
+        self.0
+    }
+
+    pub fn amount_of_literals(&self) -> usize {
+        (self.0 & Self::LITERALS_MASK) as usize
+    }
+
+    pub fn has_primitive(&self) -> bool {
+        self.0 & Self::HAS_PRIMITIVE_BIT != 0
+    }
+
+    pub fn needs_large_frame(&self) -> bool {
+        self.0 & Self::LARGE_FRAME_BIT != 0
+    }
+
+    /// Return the amount of temporaries, which includes the arguments
+    pub fn amount_of_temporaries(&self) -> usize {
+        ((self.0 >> Self::TEMPORARIES_SHIFT) & Self::TEMPORARIES_MASK) as usize
+    }
+
+    pub fn amount_of_arguments(&self) -> usize {
+        ((self.0 >> Self::ARGUMENTS_SHIFT) & Self::ARGUMENTS_MASK) as usize
+    }
+
+    pub fn uses_alternate_bytecode_set(&self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn bytecode_set(&self) -> BytecodeSet {
+        if self.uses_alternate_bytecode_set() {
+            BytecodeSet::SistaV1
+        } else {
+            BytecodeSet::V3PlusClosures
+        }
+    }
+}
+
+impl Debug for MethodHeader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MethodHeader")
+            .field("literals", &self.amount_of_literals())
+            .field("arguments", &self.amount_of_arguments())
+            .field("temporaries", &self.amount_of_temporaries())
+            .field("has_primitive", &self.has_primitive())
+            .field("large_frame", &self.needs_large_frame())
+            .field("bytecode_set", &self.bytecode_set())
+            .finish()
+    }
+}
+
+/// The trailer at the end of the bytes of a method, described by its last byte
+/// (`CompiledMethodTrailer`). It is not part of the bytecodes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MethodTrailer {
+    /// Only the flag byte
+    Empty,
+    /// A pointer into the sources or changes file
+    SourcePointer { pointer: u64, size: usize },
+    /// An encoded payload (temporary names, embedded source...) of a given trailer kind
+    Encoded { kind: u8, size: usize },
+}
+
+impl MethodTrailer {
+    const NO_TRAILER: u8 = 0;
+    const SOURCE_BY_SELECTOR: u8 = 4;
+    const VAR_LENGTH_SOURCE_POINTER: u8 = 8;
+    const SOURCE_POINTER: u8 = 63;
+
+    /// Decode the trailer at the end of the bytes of a method
+    pub fn decode(bytes: &[u8]) -> Self {
+        let Some(flag) = bytes.last().copied() else {
+            return Self::Empty;
+        };
+
+        match flag >> 2 {
+            Self::NO_TRAILER | Self::SOURCE_BY_SELECTOR => Self::Empty,
+            Self::SOURCE_POINTER => {
+                let length = bytes.len();
+                if length < 4 {
+                    return Self::Empty;
+                }
+                let pointer = ((flag as u64 - 251) << 24)
+                    | ((bytes[length - 2] as u64) << 16)
+                    | ((bytes[length - 3] as u64) << 8)
+                    | bytes[length - 4] as u64;
+                Self::SourcePointer { pointer, size: 4 }
+            }
+            Self::VAR_LENGTH_SOURCE_POINTER => {
+                // 7 bits per byte, least significant first, towards the start of the method
+                let mut pointer = 0u64;
+                let mut size = 1;
+                for (shift, byte) in bytes.iter().rev().skip(1).enumerate() {
+                    size += 1;
+                    pointer |= ((byte & 0x7F) as u64) << (shift * 7);
+                    if byte & 0x80 == 0 || shift * 7 >= 56 {
+                        break;
+                    }
+                }
+                Self::SourcePointer { pointer, size }
+            }
+            kind => {
+                // the two lower bits of the flag tell the size of the length field that precedes it,
+                // which is big-endian: the byte next to the flag is the most significant one
+                let length_field_size = (flag & 3) as usize + 1;
+                let length = bytes
+                    .iter()
+                    .rev()
+                    .skip(1)
+                    .take(length_field_size)
+                    .fold(0usize, |length, byte| (length << 8) | *byte as usize);
+                Self::Encoded {
+                    kind,
+                    size: 1 + length_field_size + length,
+                }
+            }
+        }
+    }
+
+    /// Return the amount of bytes taken by the trailer, including the flag byte
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Empty => 1,
+            Self::SourcePointer { size, .. } | Self::Encoded { size, .. } => *size,
+        }
+    }
+
+    pub fn source_pointer(&self) -> Option<u64> {
+        match self {
+            Self::SourcePointer { pointer, .. } => Some(*pointer),
+            _ => None,
+        }
+    }
+}
+
+/// A CompiledMethod or a CompiledBlock: a header, the literals and the bytes of the bytecodes followed by a trailer.
+/// The last literal of a method is the binding of its class and the one before is its selector
+/// (or an AdditionalMethodState that knows it), while the last literal of a block is its outer code
+#[repr(C)]
+pub struct CompiledMethod {
+    this: Object,
+}
+
+impl CompiledMethod {
+    /// The byte that precedes the primitive index when a method has a primitive
+    const V3_CALL_PRIMITIVE: u8 = 139;
+    const SISTA_CALL_PRIMITIVE: u8 = 248;
+    /// The offset of `methodHeader` in a CogMethod, the machine code of a jitted method
+    const COG_METHOD_HEADER_OFFSET: isize = 24;
+
+    /// Return the method header. The header slot of a jitted method points to its machine code,
+    /// which holds the original header
+    pub fn header(&self) -> MethodHeader {
+        let header = self.this.inst_var_at(0).unwrap();
+        match header.as_integer() {
+            Some(header) => MethodHeader::new(header),
+            None => {
+                let cog_method = header.as_i64() as *const u8;
+                let header =
+                    unsafe { *(cog_method.offset(Self::COG_METHOD_HEADER_OFFSET) as *const i64) };
+                MethodHeader::new(Immediate(header).as_integer().unwrap_or(0))
+            }
+        }
+    }
+
+    pub fn amount_of_literals(&self) -> usize {
+        self.header().amount_of_literals()
+    }
+
+    /// Return a literal at a zero-based index
+    pub fn literal_at(&self, index: usize) -> Option<AnyObjectRef> {
+        if index < self.amount_of_literals() {
+            self.this.inst_var_at(1 + index)
+        } else {
+            None
+        }
+    }
+
+    pub fn literals(&self) -> impl Iterator<Item = AnyObjectRef> + '_ {
+        (0..self.amount_of_literals()).filter_map(|index| self.literal_at(index))
+    }
+
+    /// Replace a literal at a zero-based index.
+    /// Fails if the index is beyond the literals or the method is immutable
+    pub fn set_literal_at(&mut self, index: usize, literal: impl Into<AnyObjectRef>) -> Result<()> {
+        let size = self.amount_of_literals();
+        if index >= size {
+            return Err(Error::IndexOutOfBounds { index, size });
+        }
+        self.this.check_mutable()?;
+        let literal = literal.into();
+        Smalltalk::prepare_to_store(
+            ObjectPointer::from(self.this.as_ptr()),
+            ObjectPointer::from(literal.as_i64()),
+        );
+        self.this.inst_var_at_put(1 + index, literal)
+    }
+
+    /// Return all bytes of the method, starting with the header and the literals
+    pub fn bytes(&self) -> &[u8] {
+        let bytes_ptr = self.this.first_fixed_field_ptr() as *const u8;
+        unsafe { std::slice::from_raw_parts(bytes_ptr, self.this.amount_of_indexable_units()) }
+    }
+
+    /// Return the zero-based index of the first bytecode in `bytes()`
+    pub fn initial_pc(&self) -> usize {
+        (1 + self.amount_of_literals()) * size_of::<i64>()
+    }
+
+    pub fn trailer(&self) -> MethodTrailer {
+        MethodTrailer::decode(&self.bytes()[self.initial_pc().min(self.bytes().len())..])
+    }
+
+    /// Return the bytecodes, without the trailer
+    pub fn bytecodes(&self) -> &[u8] {
+        let bytes = self.bytes();
+        let start = self.initial_pc().min(bytes.len());
+        let end = bytes.len().saturating_sub(self.trailer().size()).max(start);
+        &bytes[start..end]
+    }
+
+    pub fn source_pointer(&self) -> Option<u64> {
+        self.trailer().source_pointer()
+    }
+
+    /// Return the index of the primitive that is called when the method is activated,
+    /// encoded by the first bytecode
+    pub fn primitive_index(&self) -> Option<u16> {
+        let header = self.header();
+        if !header.has_primitive() {
+            return None;
+        }
+
+        match (header.bytecode_set(), self.bytecodes()) {
+            (BytecodeSet::SistaV1, [Self::SISTA_CALL_PRIMITIVE, low, high, ..]) => {
+                // the highest bit marks inlined primitives
+                Some(*low as u16 | (((*high & 0x7F) as u16) << 8))
+            }
+            (BytecodeSet::V3PlusClosures, [Self::V3_CALL_PRIMITIVE, low, high, ..]) => {
+                Some(*low as u16 | ((*high as u16) << 8))
+            }
+            _ => None,
+        }
+    }
+
+    /// Return true if this is a CompiledBlock, whose last literal is the code it is nested in
+    pub fn is_block(&self) -> bool {
+        self.last_literal()
+            .and_then(|literal| CompiledMethodRef::try_from(literal).ok())
+            .is_some()
+    }
+
+    /// Return the method in which this code is defined: itself for a method, the home method for a block
+    pub fn home_method(&self) -> CompiledMethodRef {
+        let mut method = CompiledMethodRef(ObjectRef::from(&self.this));
+        while let Some(outer_code) = method
+            .last_literal()
+            .and_then(|literal| CompiledMethodRef::try_from(literal).ok())
+        {
+            method = outer_code;
+        }
+        method
+    }
+
+    /// Return the selector of the home method
+    pub fn selector(&self) -> Option<SmalltalkString> {
+        let home_method = self.home_method();
+        let literals = home_method.amount_of_literals();
+        let penultimate_literal = home_method.literal_at(literals.checked_sub(2)?)?;
+
+        if let Ok(selector) = SmalltalkString::try_from(penultimate_literal) {
+            return selector.is_symbol().then_some(selector);
+        }
+
+        // AdditionalMethodState: method, selector followed by the pragmas
+        let method_state = penultimate_literal.as_object().ok()?;
+        match method_state.object_format() {
+            ObjectFormat::IndexableWithInstVars | ObjectFormat::NonIndexable => {
+                SmalltalkString::try_from(method_state.inst_var_at(1)?)
+                    .ok()
+                    .filter(|selector| selector.is_symbol())
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the class of the home method, the value of the binding in its last literal
+    pub fn method_class(&self) -> Option<ClassRef> {
+        let binding = self.home_method().last_literal()?.as_object().ok()?;
+        ClassRef::try_from(binding.inst_var_at(1)?).ok()
+    }
+
+    fn last_literal(&self) -> Option<AnyObjectRef> {
+        self.literal_at(self.amount_of_literals().checked_sub(1)?)
+    }
+}
+
+impl Deref for CompiledMethod {
+    type Target = Object;
+
+    fn deref(&self) -> &Self::Target {
+        &self.this
+    }
+}
+
+impl Debug for CompiledMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("CompiledMethod");
+        if let Some(class) = self.method_class().and_then(|class| class.name()) {
+            debug.field("class", &class);
+        }
+        if let Some(selector) = self.selector() {
+            debug.field("selector", &selector.to_string());
+        }
+        debug
+            .field("header", &self.header())
+            .field("bytecodes", &self.bytecodes().len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct CompiledMethodRef(ObjectRef);
+
+impl Deref for CompiledMethodRef {
+    type Target = CompiledMethod;
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.0.cast() }
+    }
+}
+
+impl DerefMut for CompiledMethodRef {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.0.cast_mut() }
+    }
+}
+
+impl TryFrom<AnyObjectRef> for CompiledMethodRef {
+    type Error = Error;
+
+    fn try_from(value: AnyObjectRef) -> Result<Self> {
+        let object_ref = value.as_object()?.follow_forwarded();
+        match object_ref.object_format() {
+            ObjectFormat::CompiledMethod(_) => Ok(CompiledMethodRef(object_ref)),
+            _ => Err(Error::InvalidType("CompiledMethod".to_string())),
+        }
+    }
+}
+
+impl From<CompiledMethodRef> for AnyObjectRef {
+    fn from(value: CompiledMethodRef) -> Self {
+        value.0.into()
     }
 }
 
-impl<'obj> TryFrom<&'obj Object> for CompiledMethod<'obj> {
-    type Error = String;
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::objects::{byte_symbol_class, with_test_heap};
+    use vm_object_model::TestHeap;
+
+    /// `SmallInteger minVal`, the sign flag of a method header
+    pub(crate) const ALTERNATE_BYTECODE_SET: i64 = -(1 << 60);
 
-    fn try_from(value: &'obj Object) -> Result<Self, Self::Error> {
-        match value.object_format() {
-            ObjectFormat::CompiledMethod(_) => Ok(CompiledMethod { header: value }),
-            _ => Err("Object is not compiled method".into()),
+    /// Allocate a method with given header, literals and bytes that follow the literals
+    pub(crate) fn new_method(
+        heap: &TestHeap,
+        header: i64,
+        literals: &[AnyObjectRef],
+        bytes: &[u8],
+    ) -> CompiledMethodRef {
+        let class = heap.new_named_class(
+            "CompiledMethod",
+            None,
+            ObjectFormat::CompiledMethod(24),
+            &[],
+        );
+        let initial_pc = (1 + literals.len()) * size_of::<i64>();
+        let mut method = heap.instantiate_indexable(class, initial_pc + bytes.len());
+
+        method.inst_var_at_put_unchecked(0, Immediate::new_i64(header));
+        for (index, literal) in literals.iter().enumerate() {
+            method.inst_var_at_put_unchecked(1 + index, *literal);
         }
+        let bytes_ptr = method.first_fixed_field_ptr() as *mut u8;
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), bytes_ptr.add(initial_pc), bytes.len())
+        };
+        CompiledMethodRef::try_from(AnyObjectRef::from(method)).unwrap()
+    }
+
+    #[test]
+    fn decode_header() {
+        // 3 literals, primitive, large frame, 5 temporaries, 2 arguments, alternate bytecode set
+        let header =
+            MethodHeader::new(ALTERNATE_BYTECODE_SET | 3 | 1 << 16 | 1 << 17 | 5 << 18 | 2 << 24);
+        assert_eq!(header.amount_of_literals(), 3);
+        assert!(header.has_primitive());
+        assert!(header.needs_large_frame());
+        assert_eq!(header.amount_of_temporaries(), 5);
+        assert_eq!(header.amount_of_arguments(), 2);
+        assert_eq!(header.bytecode_set(), BytecodeSet::SistaV1);
+
+        let header = MethodHeader::new(1 << 24);
+        assert!(!header.has_primitive());
+        assert_eq!(header.bytecode_set(), BytecodeSet::V3PlusClosures);
+    }
+
+    #[test]
+    fn decode_trailers() {
+        assert_eq!(MethodTrailer::decode(&[0x5C, 0]), MethodTrailer::Empty);
+        assert_eq!(
+            MethodTrailer::decode(&[0x5C, 0x34, 0x12, 0x00, 0xFC]),
+            MethodTrailer::SourcePointer {
+                pointer: 0x01001234,
+                size: 4
+            }
+        );
+        // 300 encoded in two 7-bit groups
+        assert_eq!(
+            MethodTrailer::decode(&[0x5C, 0x02, 0xAC, 8 << 2]).source_pointer(),
+            Some(300)
+        );
+        // embedded source of 2 bytes with a 1-byte length field
+        assert_eq!(
+            MethodTrailer::decode(&[0x5C, b'a', b'b', 2, 11 << 2]),
+            MethodTrailer::Encoded { kind: 11, size: 4 }
+        );
+        // embedded source of 258 bytes with a 2-byte length field
+        let mut bytes = vec![0x5C];
+        bytes.extend([b'a'; 258]);
+        bytes.extend([0x02, 0x01, (11 << 2) | 1]);
+        assert_eq!(
+            MethodTrailer::decode(&bytes),
+            MethodTrailer::Encoded {
+                kind: 11,
+                size: 261
+            }
+        );
+    }
+
+    #[test]
+    fn literals_selector_and_class() {
+        with_test_heap(|heap| {
+            let class =
+                heap.new_named_class("Point", None, ObjectFormat::NonIndexable, &["x", "y"]);
+            let symbol_class = byte_symbol_class(heap);
+            let binding_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+
+            let selector = heap.new_bytes(symbol_class, b"x:");
+            let binding = heap.new_object(binding_class, &[heap.nil().into(), class.into()]);
+
+            // <primitive: 60> ^ 42, followed by an empty trailer
+            let header = ALTERNATE_BYTECODE_SET | 3 | 1 << 16 | 1 << 18 | 1 << 24;
+            let method = new_method(
+                heap,
+                header,
+                &[
+                    Immediate::new_i64(42).into(),
+                    selector.into(),
+                    binding.into(),
+                ],
+                &[248, 60, 0, 0x20, 0x5C, 0],
+            );
+
+            assert_eq!(method.amount_of_literals(), 3);
+            assert_eq!(method.literals().count(), 3);
+            assert_eq!(method.literal_at(0).unwrap().as_integer(), Some(42));
+            assert!(method.literal_at(3).is_none());
+
+            let mut method = method;
+            method.set_literal_at(0, Immediate::new_i64(7)).unwrap();
+            assert_eq!(method.literal_at(0).unwrap().as_integer(), Some(7));
+            assert!(matches!(
+                method.set_literal_at(3, Immediate::new_i64(7)),
+                Err(Error::IndexOutOfBounds { index: 3, size: 3 })
+            ));
+            assert_eq!(method.initial_pc(), 32);
+            assert_eq!(method.bytecodes(), &[248, 60, 0, 0x20, 0x5C]);
+            assert_eq!(method.primitive_index(), Some(60));
+            assert_eq!(method.selector().unwrap().to_string(), "x:");
+            assert_eq!(
+                method.method_class().unwrap().name().as_deref(),
+                Some("Point")
+            );
+            assert!(!method.is_block());
+
+            let block = new_method(
+                heap,
+                ALTERNATE_BYTECODE_SET | 1,
+                &[method.into()],
+                &[0x58, 0],
+            );
+            assert!(block.is_block());
+            assert_eq!(block.selector().unwrap().to_string(), "x:");
+            assert!(block.primitive_index().is_none());
+        })
     }
 }
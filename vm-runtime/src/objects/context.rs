@@ -0,0 +1,343 @@
+use crate::objects::{is_nil, CompiledMethodRef};
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::{Deref, DerefMut};
+use vm_bindings::{ObjectPointer, Smalltalk};
+use vm_object_model::{
+    AnyObjectRef, Error, Object, ObjectFormat, ObjectRef, RawObjectPointer, Result,
+};
+
+/// An activation of a method or a block (Context), followed by its temporaries and stack.
+///
+/// While an activation runs, its context may be married to a frame of the vm stack:
+/// the sender and pc then encode the frame and the actual state lives in the frame.
+/// The sender and pc of such contexts are asked to the vm, which computes them from the frame
+/// the same way as for Smalltalk code
+#[repr(C)]
+pub struct Context {
+    this: Object,
+    sender: AnyObjectRef,
+    pc: AnyObjectRef,
+    stackp: AnyObjectRef,
+    method: AnyObjectRef,
+    closure_or_nil: AnyObjectRef,
+    receiver: AnyObjectRef,
+}
+
+impl Context {
+    pub const AMOUNT_OF_FIXED_SLOTS: usize = 6;
+    const SENDER_INDEX: usize = 0;
+    const PC_INDEX: usize = 1;
+
+    /// Return true if the context is or was married to a frame of the vm stack,
+    /// in which case its stack is not meaningful
+    pub fn is_married_or_widowed(&self) -> bool {
+        self.sender.is_immediate()
+    }
+
+    /// Return the calling context, or None for the bottom context.
+    /// The vm allocates a context for the caller of a married context, which may move objects,
+    /// so references to other objects must not be used after asking for the sender
+    pub fn sender(&self) -> Option<ContextRef> {
+        ContextRef::try_from(self.field_at(Self::SENDER_INDEX, self.sender)).ok()
+    }
+
+    /// Return the one-based index of the next bytecode to execute in the method,
+    /// or None if the context has returned
+    pub fn pc(&self) -> Option<usize> {
+        self.field_at(Self::PC_INDEX, self.pc)
+            .as_integer()
+            .filter(|pc| *pc > 0)
+            .map(|pc| pc as usize)
+    }
+
+    /// Return true if the context has returned. The vm marks a context whose frame has returned as dead
+    pub fn is_dead(&self) -> bool {
+        is_nil(self.field_at(Self::PC_INDEX, self.pc))
+    }
+
+    /// Return the value of a fixed field, computed by the vm from the frame if the context is married
+    fn field_at(&self, index: usize, value: AnyObjectRef) -> AnyObjectRef {
+        if !self.is_married_or_widowed() {
+            return value;
+        }
+        let value = Smalltalk::context_inst_var_at(ObjectPointer::from(self.this.as_ptr()), index);
+        AnyObjectRef::from(RawObjectPointer::from(value.as_i64()))
+    }
+
+    /// Return the amount of temporaries and stack values that are in use.
+    /// The stack of a married or widowed context lives in its frame, so it is reported as empty
+    pub fn stack_pointer(&self) -> usize {
+        if self.is_married_or_widowed() {
+            return 0;
+        }
+        self.stackp
+            .as_integer()
+            .map(|stackp| stackp.max(0) as usize)
+            .unwrap_or(0)
+            .min(self.this.amount_of_slots() - Self::AMOUNT_OF_FIXED_SLOTS)
+    }
+
+    /// Return the value at a zero-based index of the temporaries followed by the stack
+    pub fn stack_at(&self, index: usize) -> Option<AnyObjectRef> {
+        if index < self.stack_pointer() {
+            self.this.inst_var_at(Self::AMOUNT_OF_FIXED_SLOTS + index)
+        } else {
+            None
+        }
+    }
+
+    /// Iterate over the temporaries and the stack, from the bottom
+    pub fn stack(&self) -> impl Iterator<Item = AnyObjectRef> + '_ {
+        (0..self.stack_pointer()).filter_map(|index| self.stack_at(index))
+    }
+
+    /// Return the method or block being run. Blocks are either a CompiledBlock
+    /// or, for closures of the V3PlusClosures bytecode set, their home method
+    pub fn method(&self) -> Option<CompiledMethodRef> {
+        CompiledMethodRef::try_from(self.method).ok()
+    }
+
+    /// Return the closure of a block activation, or None for a method activation
+    pub fn closure(&self) -> Option<AnyObjectRef> {
+        Some(self.closure_or_nil).filter(|closure| !is_nil(*closure))
+    }
+
+    pub fn is_block_context(&self) -> bool {
+        self.closure().is_some()
+    }
+
+    pub fn receiver(&self) -> AnyObjectRef {
+        self.receiver
+    }
+
+    /// Iterate over this context and its senders up to the bottom context.
+    /// Reading the sender of a married context may move objects,
+    /// so a context must not be used once the iterator has moved past it
+    pub fn stack_frames(&self) -> impl Iterator<Item = ContextRef> {
+        let this = ContextRef(ObjectRef::from(&self.this));
+        std::iter::successors(Some(this), |context| context.sender())
+    }
+}
+
+impl Deref for Context {
+    type Target = Object;
+
+    fn deref(&self) -> &Self::Target {
+        &self.this
+    }
+}
+
+impl Debug for Context {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Context")
+            .field("method", &self.method())
+            .field("pc", &self.pc())
+            .field("stackp", &self.stack_pointer())
+            .field("is_block", &self.is_block_context())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Print the context the same way as `Context>>#printOn:`,
+/// for example `[] in OrderedCollection(SequenceableCollection)>>do:`
+impl Display for Context {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_block_context() {
+            write!(f, "[] in ")?;
+        }
+
+        let receiver_class = Smalltalk::class_of(self.receiver).and_then(|class| class.name());
+        let method_class = self
+            .method()
+            .and_then(|method| method.method_class())
+            .and_then(|class| class.name());
+
+        match (receiver_class, method_class) {
+            (Some(receiver_class), Some(method_class)) if receiver_class != method_class => {
+                write!(f, "{}({})", receiver_class, method_class)?
+            }
+            (Some(class), _) | (None, Some(class)) => write!(f, "{}", class)?,
+            (None, None) => write!(f, "<unknown class>")?,
+        }
+
+        match self.method().and_then(|method| method.selector()) {
+            Some(selector) => write!(f, ">>{}", selector),
+            None => write!(f, ">><unknown selector>"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct ContextRef(ObjectRef);
+
+impl Deref for ContextRef {
+    type Target = Context;
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.0.cast() }
+    }
+}
+
+impl DerefMut for ContextRef {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.0.cast_mut() }
+    }
+}
+
+impl TryFrom<AnyObjectRef> for ContextRef {
+    type Error = Error;
+
+    fn try_from(value: AnyObjectRef) -> Result<Self> {
+        let object_ref = value.as_object()?.follow_forwarded();
+        let amount_of_fixed_slots = Smalltalk::class_of(object_ref)
+            .and_then(|class| class.amount_of_fixed_slots().ok())
+            .unwrap_or(0);
+
+        match object_ref.object_format() {
+            ObjectFormat::IndexableWithInstVars
+                if amount_of_fixed_slots == Context::AMOUNT_OF_FIXED_SLOTS =>
+            {
+                Ok(ContextRef(object_ref))
+            }
+            _ => Err(Error::InvalidType("Context".to_string())),
+        }
+    }
+}
+
+impl From<ContextRef> for AnyObjectRef {
+    fn from(value: ContextRef) -> Self {
+        value.0.into()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::objects::{byte_symbol_class, new_method, with_test_heap, ALTERNATE_BYTECODE_SET};
+    use vm_object_model::{Immediate, TestHeap};
+
+    /// Allocate a context of a method, with a given sender, pc and stack
+    pub(crate) fn new_context(
+        heap: &TestHeap,
+        sender: AnyObjectRef,
+        method: CompiledMethodRef,
+        receiver: AnyObjectRef,
+        pc: usize,
+        stack: &[AnyObjectRef],
+    ) -> ContextRef {
+        let context_class = heap.new_named_class(
+            "Context",
+            None,
+            ObjectFormat::IndexableWithInstVars,
+            &[
+                "sender",
+                "pc",
+                "stackp",
+                "method",
+                "closureOrNil",
+                "receiver",
+            ],
+        );
+        let mut context = heap.instantiate_indexable(context_class, 8);
+        let fields = [
+            sender,
+            Immediate::new_i64(pc as i64).into(),
+            Immediate::new_i64(stack.len() as i64).into(),
+            method.into(),
+            heap.nil().into(),
+            receiver,
+        ];
+        for (index, field) in fields.iter().chain(stack).enumerate() {
+            context.inst_var_at_put_unchecked(index, *field);
+        }
+        ContextRef::try_from(AnyObjectRef::from(context)).unwrap()
+    }
+
+    /// Allocate a method `Class>>selector` of a named class
+    pub(crate) fn new_method_of(
+        heap: &TestHeap,
+        class_name: &str,
+        selector: &str,
+    ) -> CompiledMethodRef {
+        let class = heap.new_named_class(class_name, None, ObjectFormat::NonIndexable, &[]);
+        let symbol_class = byte_symbol_class(heap);
+        let binding_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+        let selector = heap.new_bytes(symbol_class, selector.as_bytes());
+        let binding = heap.new_object(binding_class, &[heap.nil().into(), class.into()]);
+
+        new_method(
+            heap,
+            ALTERNATE_BYTECODE_SET | 2,
+            &[selector.into(), binding.into()],
+            &[88],
+        )
+    }
+
+    #[test]
+    fn walk_senders() {
+        with_test_heap(|heap| {
+            let receiver_class =
+                heap.new_named_class("OrderedCollection", None, ObjectFormat::NonIndexable, &[]);
+            let receiver = heap.instantiate(receiver_class);
+
+            let bottom = new_context(
+                heap,
+                heap.nil().into(),
+                new_method_of(heap, "UndefinedObject", "DoIt"),
+                heap.nil().into(),
+                20,
+                &[],
+            );
+            let top = new_context(
+                heap,
+                bottom.into(),
+                new_method_of(heap, "SequenceableCollection", "do:"),
+                receiver.into(),
+                25,
+                &[Immediate::new_i64(1).into(), heap.true_object().into()],
+            );
+
+            assert_eq!(top.pc(), Some(25));
+            assert_eq!(top.stack_pointer(), 2);
+            assert_eq!(top.stack_at(0).unwrap().as_integer(), Some(1));
+            assert!(top.stack_at(2).is_none());
+            assert!(!top.is_block_context());
+            assert!(!top.is_dead());
+
+            let frames = top
+                .stack_frames()
+                .map(|context| context.to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                frames,
+                vec![
+                    "OrderedCollection(SequenceableCollection)>>do:",
+                    "UndefinedObject>>DoIt"
+                ]
+            );
+        })
+    }
+
+    #[test]
+    fn widowed_context_is_dead() {
+        with_test_heap(|heap| {
+            let context = new_context(
+                heap,
+                Immediate::new_i64(0x1000).into(),
+                new_method_of(heap, "Object", "yourself"),
+                heap.nil().into(),
+                20,
+                &[Immediate::new_i64(1).into()],
+            );
+
+            assert!(context.is_married_or_widowed());
+            assert!(context.sender().is_none());
+            assert!(context.pc().is_none());
+            assert!(context.is_dead());
+            assert_eq!(context.stack_pointer(), 0);
+            assert!(context.stack_at(0).is_none());
+            assert_eq!(context.stack().count(), 0);
+            assert_eq!(context.stack_frames().count(), 1);
+        })
+    }
+}
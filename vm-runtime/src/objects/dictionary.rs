@@ -0,0 +1,252 @@
+use crate::objects::{
+    equality_hash, is_equal, is_nil, scan_for_equal, Array, ArrayRef, Association, AssociationRef,
+};
+use vm_bindings::Smalltalk;
+use vm_object_model::{AnyObjectRef, Error, Immediate, Object, ObjectRef, Result};
+use vm_object_model_derive::PharoObject;
+
+/// A Pharo Dictionary: a hashed collection of associations
+/// that are looked up with `=` and `hash` of their keys
+#[derive(Debug, PharoObject)]
+#[repr(C)]
+pub struct Dictionary {
+    this: Object,
+    tally: Immediate,
+    array: ArrayRef,
+}
+
+impl Dictionary {
+    /// Create a dictionary that can hold a given amount of associations without growing,
+    /// sized the same way as `HashedCollection class>>#sizeFor:`
+    pub fn with_capacity(dictionary_class: ObjectRef, capacity: usize) -> Result<DictionaryRef> {
+        let mut dictionary = Smalltalk::instantiate::<DictionaryRef>(dictionary_class)?;
+        dictionary.set_array(Array::new(good_prime_at_least((capacity * 4).div_ceil(3)))?)?;
+        dictionary.set_tally(Immediate::new_i64(0))?;
+        Ok(dictionary)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tally.as_integer().unwrap() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add an association whose key is not in the dictionary yet, growing it if needed like `#add:`.
+    /// Fails if the dictionary already includes an equal key
+    pub fn add_new_association(&mut self, association: AssociationRef) -> Result<()> {
+        let index = self.index_for_key(association.key().follow_forwarded())?;
+        if self.association_at(index).is_some() {
+            return Err(Error::DuplicateKey(*self.header()));
+        }
+
+        self.array.insert(index, association)?;
+        self.set_tally(Immediate::new_i64(self.len() as i64 + 1))?;
+        self.full_check()
+    }
+
+    pub fn associations(&self) -> impl Iterator<Item = AssociationRef> + '_ {
+        self.array
+            .iter()
+            .filter_map(|each| AssociationRef::try_from(*each).ok())
+    }
+
+    /// Return the value associated with a key equal to a given one
+    pub fn at(&self, key: impl Into<AnyObjectRef>) -> Option<AnyObjectRef> {
+        let index = self.scan_for(key.into())?;
+        self.association_at(index)
+            .map(|association| association.value())
+    }
+
+    pub fn includes_key(&self, key: impl Into<AnyObjectRef>) -> bool {
+        self.at(key).is_some()
+    }
+
+    /// Associate a value with a key, replacing the value of an equal key if there is one.
+    /// Pharo dictionaries do not know the class of their associations, so it must be given
+    pub fn at_put(
+        &mut self,
+        association_class: ObjectRef,
+        key: impl Into<AnyObjectRef>,
+        value: impl Into<AnyObjectRef>,
+    ) -> Result<()> {
+        let key = key.into().follow_forwarded();
+        let index = self.index_for_key(key)?;
+
+        if let Some(mut association) = self.association_at(index) {
+            return association.set_value(value);
+        }
+
+        let mut association = Association::new(association_class)?;
+        association.set_key(key)?;
+        association.set_value(value)?;
+        self.array.insert(index, association)?;
+        self.set_tally(Immediate::new_i64(self.len() as i64 + 1))?;
+        self.full_check()
+    }
+
+    /// Return the index of the association with a key equal to a given one
+    /// or of the empty slot where it would be, probing like `Dictionary>>#scanFor:`.
+    /// Return None if the dictionary is full and does not include the key
+    pub fn scan_for(&self, key: AnyObjectRef) -> Option<usize> {
+        scan_for_equal(self.array.len(), equality_hash(key), |index| {
+            self.association_at(index)
+                .is_none_or(|association| is_equal(association.key(), key))
+        })
+    }
+
+    /// Return the index of the association with a key equal to a given one
+    /// or of the empty slot where it would be, growing the dictionary if it is full
+    fn index_for_key(&mut self, key: AnyObjectRef) -> Result<usize> {
+        if let Some(index) = self.scan_for(key) {
+            return Ok(index);
+        }
+        self.grow()?;
+        self.scan_for(key)
+            .ok_or_else(|| Error::NoEmptySlot(*self.header()))
+    }
+
+    fn association_at(&self, index: usize) -> Option<AssociationRef> {
+        let association_or_nil = self.array[index];
+        if is_nil(association_or_nil) {
+            None
+        } else {
+            AssociationRef::try_from(association_or_nil).ok()
+        }
+    }
+
+    fn full_check(&mut self) -> Result<()> {
+        let available_space = self.array.len() - self.len();
+        if available_space < (self.array.len() / 4).max(1) {
+            self.grow()?;
+        }
+        Ok(())
+    }
+
+    /// Grow the array and rehash the associations, like `HashedCollection>>#grow`
+    fn grow(&mut self) -> Result<()> {
+        let old_associations = self.array;
+        let capacity = old_associations.len();
+        self.set_array(Array::new(good_prime_at_least(capacity + capacity.max(2)))?)?;
+
+        for each in old_associations.iter() {
+            if let Ok(association) = AssociationRef::try_from(*each) {
+                let index = self
+                    .scan_for(association.key())
+                    .ok_or_else(|| Error::NoEmptySlot(*self.header()))?;
+                self.array.insert(index, association)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Return the smallest prime that is not smaller than a given number (at least 5)
+pub(crate) fn good_prime_at_least(number: usize) -> usize {
+    fn is_prime(number: usize) -> bool {
+        (2..)
+            .take_while(|divisor| divisor * divisor <= number)
+            .all(|divisor| !number.is_multiple_of(divisor))
+    }
+
+    (number.max(5)..).find(|each| is_prime(*each)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{byte_string_class, with_test_heap, Association};
+    use vm_object_model::ObjectFormat;
+
+    #[test]
+    fn add_new_associations() {
+        with_test_heap(|heap| {
+            let dictionary_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+            let association_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+            let string_class = byte_string_class(heap);
+            let new_string = |string: &str| heap.new_bytes(string_class, string.as_bytes());
+
+            let mut dictionary = Dictionary::with_capacity(dictionary_class, 3).unwrap();
+            assert!(dictionary.is_empty());
+            assert_eq!(dictionary.array.len(), 5);
+
+            let keys = ["a", "b", "c", "d", "e", "f", "g"];
+            for (index, key) in keys.iter().enumerate() {
+                let mut association = Association::new(association_class).unwrap();
+                association.set_key(new_string(key)).unwrap();
+                association
+                    .set_value(Immediate::new_i64(index as i64))
+                    .unwrap();
+                dictionary.add_new_association(association).unwrap();
+            }
+            assert_eq!(dictionary.len(), keys.len());
+            assert!(dictionary.array.len() > keys.len());
+
+            let mut values = dictionary
+                .associations()
+                .map(|each| each.value().as_integer().unwrap())
+                .collect::<Vec<_>>();
+            values.sort();
+            assert_eq!(values, (0..keys.len() as i64).collect::<Vec<_>>());
+            assert_eq!(
+                dictionary.at(new_string("c")).unwrap().as_integer(),
+                Some(2)
+            );
+
+            let mut duplicate = Association::new(association_class).unwrap();
+            duplicate.set_key(new_string("a")).unwrap();
+            assert!(matches!(
+                dictionary.add_new_association(duplicate),
+                Err(Error::DuplicateKey(_))
+            ));
+            assert_eq!(dictionary.len(), keys.len());
+        })
+    }
+
+    #[test]
+    fn at_put_with_equal_keys() {
+        with_test_heap(|heap| {
+            let dictionary_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+            let association_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+            let string_class = byte_string_class(heap);
+
+            let mut dictionary = Dictionary::with_capacity(dictionary_class, 1).unwrap();
+            for index in 0..10 {
+                let key = heap.new_bytes(string_class, format!("key{}", index).as_bytes());
+                dictionary
+                    .at_put(association_class, key, Immediate::new_i64(index))
+                    .unwrap();
+            }
+            dictionary
+                .at_put(
+                    association_class,
+                    Immediate::new_i64(42),
+                    heap.true_object(),
+                )
+                .unwrap();
+            assert_eq!(dictionary.len(), 11);
+            assert!(dictionary.array.len() > 11);
+
+            // a different string with the same characters is an equal key
+            let key = heap.new_bytes(string_class, b"key3");
+            assert_eq!(dictionary.at(key).unwrap().as_integer(), Some(3));
+            dictionary
+                .at_put(association_class, key, Immediate::new_i64(33))
+                .unwrap();
+            assert_eq!(dictionary.len(), 11);
+            assert_eq!(dictionary.at(key).unwrap().as_integer(), Some(33));
+
+            assert!(dictionary.includes_key(Immediate::new_i64(42)));
+            assert!(!dictionary.includes_key(Immediate::new_i64(43)));
+            assert!(!dictionary.includes_key(heap.new_bytes(string_class, b"key10")));
+        })
+    }
+
+    #[test]
+    fn prime_sizes() {
+        assert_eq!(good_prime_at_least(0), 5);
+        assert_eq!(good_prime_at_least(8), 11);
+        assert_eq!(good_prime_at_least(13), 13);
+    }
+}
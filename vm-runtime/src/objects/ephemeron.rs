@@ -0,0 +1,85 @@
+use vm_bindings::Smalltalk;
+use vm_object_model::{AnyObjectRef, Object, ObjectFormat, ObjectRef, Result};
+use vm_object_model_derive::PharoObject;
+
+/// A Pharo Ephemeron: an association whose key does not keep the value alive.
+/// Once the key is only reachable through ephemerons, the vm queues the ephemeron
+/// for finalization and the image sends it `#mourn`, which finalizes the value
+/// and removes the ephemeron from its container (a FinalizationRegistry).
+#[derive(Debug, PharoObject)]
+#[pharo(format = ObjectFormat::WeakNonIndexable)]
+#[repr(C)]
+pub struct Ephemeron {
+    this: Object,
+    key: AnyObjectRef,
+    value: AnyObjectRef,
+    container: AnyObjectRef,
+}
+
+impl Ephemeron {
+    /// Create an ephemeron that lets the image finalize `value` (for example a handle to an external resource)
+    /// after `key` is garbage collected. The container must keep the ephemeron alive until it is mourned,
+    /// otherwise it is collected together with the key and nothing is finalized.
+    pub fn new(
+        ephemeron_class: ObjectRef,
+        key: impl Into<AnyObjectRef>,
+        value: impl Into<AnyObjectRef>,
+        container: impl Into<AnyObjectRef>,
+    ) -> Result<EphemeronRef> {
+        let key = key.into();
+        // immediates are never collected, so the ephemeron would never fire
+        key.as_object()?;
+
+        let mut ephemeron = Smalltalk::instantiate::<EphemeronRef>(ephemeron_class)?;
+        ephemeron.set_key(key)?;
+        ephemeron.set_value(value)?;
+        ephemeron.set_container(container)?;
+        Ok(ephemeron)
+    }
+
+    pub fn key(&self) -> AnyObjectRef {
+        self.key
+    }
+
+    pub fn value(&self) -> AnyObjectRef {
+        self.value
+    }
+
+    pub fn container(&self) -> AnyObjectRef {
+        self.container
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::with_test_heap;
+    use vm_object_model::{Error, Immediate};
+
+    #[test]
+    fn new_ephemeron() {
+        with_test_heap(|heap| {
+            let class = heap.new_class(ObjectFormat::WeakNonIndexable, 3);
+            let key = heap.new_byte_string("resource");
+            let registry = heap.new_array(&[]);
+
+            let ephemeron = Ephemeron::new(class, key, Immediate::new_i64(42), registry).unwrap();
+            assert_eq!(ephemeron.object_format(), ObjectFormat::WeakNonIndexable);
+            assert!(ephemeron.key().equals(&key.into()).unwrap());
+            assert_eq!(ephemeron.value().as_integer(), Some(42));
+            assert!(ephemeron.container().equals(&registry.into()).unwrap());
+
+            let immediate_key = Ephemeron::new(class, Immediate::new_i64(1), heap.nil(), registry);
+            assert!(matches!(immediate_key, Err(Error::NotAnObject(_))));
+        })
+    }
+
+    #[test]
+    fn association_is_not_an_ephemeron() {
+        with_test_heap(|heap| {
+            let class = heap.new_class(ObjectFormat::NonIndexable, 3);
+            let result = Ephemeron::new(class, heap.new_byte_string("key"), heap.nil(), heap.nil());
+            assert!(matches!(result, Err(Error::InvalidType(_))));
+        })
+    }
+}
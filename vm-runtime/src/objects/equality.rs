@@ -0,0 +1,126 @@
+use crate::objects::{hash_of, scaled_identity_hash, SmalltalkString};
+use vm_object_model::AnyObjectRef;
+
+/// How an object takes part in `#=` and `#hash` of equality-based collections
+enum EqualityKey {
+    /// Strings are equal when they have the same characters, regardless of being byte or wide
+    String(String),
+    /// Symbols are unique, so they are only identical to other symbols,
+    /// but are equal to strings with the same characters and hash like strings
+    Symbol(String),
+    /// Any other object is only equal to itself
+    Identity(AnyObjectRef),
+}
+
+impl EqualityKey {
+    fn of(object: AnyObjectRef) -> Self {
+        let object = object.follow_forwarded();
+        match SmalltalkString::try_from(object) {
+            Ok(string) if string.is_symbol() => Self::Symbol(string.to_string()),
+            Ok(string) => Self::String(string.to_string()),
+            Err(_) => Self::Identity(object),
+        }
+    }
+}
+
+/// Return the `#hash` of an object, as used by Dictionary and Set.
+/// Strings and symbols hash their characters, SmallIntegers are their own hash,
+/// characters hash as their code point and other objects use their scaled identity hash (`Object>>#hash`)
+pub fn equality_hash(object: impl Into<AnyObjectRef>) -> i64 {
+    match EqualityKey::of(object.into()) {
+        EqualityKey::String(string) | EqualityKey::Symbol(string) => hash_of(&string) as i64,
+        EqualityKey::Identity(object) => match object.as_immediate() {
+            Ok(immediate) => immediate
+                .as_integer()
+                .or_else(|| immediate.character_value().map(|value| value as i64))
+                .unwrap_or(immediate.0 >> 3),
+            Err(_) => scaled_identity_hash(object) as i64,
+        },
+    }
+}
+
+/// Return true if two objects are equal according to `#=`: strings and symbols with the same characters
+/// are equal, while two symbols and other objects must be identical
+pub fn is_equal(first: impl Into<AnyObjectRef>, second: impl Into<AnyObjectRef>) -> bool {
+    let first = first.into().follow_forwarded();
+    let second = second.into().follow_forwarded();
+    if first.as_i64() == second.as_i64() {
+        return true;
+    }
+
+    match (EqualityKey::of(first), EqualityKey::of(second)) {
+        (EqualityKey::String(first), EqualityKey::String(second))
+        | (EqualityKey::String(first), EqualityKey::Symbol(second))
+        | (EqualityKey::Symbol(first), EqualityKey::String(second)) => first == second,
+        _ => false,
+    }
+}
+
+/// Return the index of the first slot, probing from `hash \\ size` and wrapping around like `#scanFor:`,
+/// that is either nil or holds an element for which the block answers true.
+/// Return None if there is no such slot
+pub(crate) fn scan_for_equal(
+    size: usize,
+    hash: i64,
+    is_nil_or_equal: impl Fn(usize) -> bool,
+) -> Option<usize> {
+    if size == 0 {
+        return None;
+    }
+    let start = hash.rem_euclid(size as i64) as usize;
+    (start..size)
+        .chain(0..start)
+        .find(|index| is_nil_or_equal(*index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{byte_string_class, byte_symbol_class, with_test_heap};
+    use vm_object_model::Immediate;
+
+    #[test]
+    fn strings_are_equal_by_characters() {
+        with_test_heap(|heap| {
+            let string_class = byte_string_class(heap);
+            let symbol_class = byte_symbol_class(heap);
+
+            let first = heap.new_bytes(string_class, b"key");
+            let second = heap.new_bytes(string_class, b"key");
+            let symbol = heap.new_bytes(symbol_class, b"key");
+            let other_symbol = heap.new_bytes(symbol_class, b"key");
+
+            assert!(is_equal(first, second));
+            assert_eq!(equality_hash(first), hash_of("key") as i64);
+            assert_eq!(equality_hash(symbol), hash_of("key") as i64);
+            assert!(is_equal(symbol, symbol));
+            assert!(!is_equal(symbol, other_symbol));
+            assert!(is_equal(first, symbol));
+            assert!(is_equal(symbol, first));
+            assert!(!is_equal(heap.new_bytes(string_class, b"other"), symbol));
+        })
+    }
+
+    #[test]
+    fn objects_hash_as_their_scaled_identity_hash() {
+        with_test_heap(|heap| {
+            let object = heap.new_array(&[]);
+            let hash = equality_hash(object);
+            assert_eq!(hash, scaled_identity_hash(object.into()) as i64);
+            assert_eq!(hash & 0xFF, 0);
+        })
+    }
+
+    #[test]
+    fn immediates_are_their_own_hash() {
+        with_test_heap(|_heap| {
+            assert_eq!(equality_hash(Immediate::new_i64(-42)), -42);
+            assert_eq!(equality_hash(Immediate::new_character('a')), 97);
+            assert!(is_equal(Immediate::new_i64(3), Immediate::new_i64(3)));
+            assert!(!is_equal(
+                Immediate::new_i64(3),
+                Immediate::new_character('3')
+            ));
+        })
+    }
+}
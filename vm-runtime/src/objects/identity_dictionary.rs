@@ -1,13 +1,9 @@
-use crate::assign_field;
-use crate::objects::{Array, ArrayRef, Association, AssociationRef};
-use num_traits::Zero;
-use std::ops::{Deref, DerefMut};
+use crate::objects::{good_prime_at_least, is_nil, Array, ArrayRef, Association, AssociationRef};
 use vm_bindings::{ObjectPointer, Smalltalk};
-use vm_object_model::{
-    AnyObjectRef, Error, Immediate, Object, ObjectRef, RawObjectPointer, Result,
-};
+use vm_object_model::{AnyObjectRef, Immediate, Object, ObjectRef, RawObjectPointer, Result};
+use vm_object_model_derive::PharoObject;
 
-#[derive(Debug)]
+#[derive(Debug, PharoObject)]
 #[repr(C)]
 pub struct IdentityDictionary {
     this: Object,
@@ -17,242 +13,348 @@ pub struct IdentityDictionary {
 }
 
 impl IdentityDictionary {
+    pub fn len(&self) -> usize {
+        self.tally() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the value associated with a key
+    pub fn at(&self, key: impl Into<AnyObjectRef>) -> Option<AnyObjectRef> {
+        let index = self.scan_for(key.into())?;
+        self.association_at(index)
+            .map(|association| association.value())
+    }
+
+    /// Associate a value with a key, replacing the previous value
+    pub fn at_put(
+        &mut self,
+        key: impl Into<AnyObjectRef>,
+        value: impl Into<AnyObjectRef>,
+    ) -> Result<()> {
+        let key = key.into().follow_forwarded();
+        let index = self.scan_for_room(key)?;
+
+        match self.association_at(index) {
+            None => self.at_new_index_put(index, key, value.into()),
+            Some(mut association) => association.set_value(value),
+        }
+    }
+
     pub fn get_or_insert(
         &mut self,
         key: impl Into<AnyObjectRef>,
         default_value: impl FnOnce() -> AnyObjectRef,
-    ) -> AnyObjectRef {
-        let key = key.into();
-        let index = self.scan_for(key).unwrap_or_else(|| {
-            panic!(
-                "Dictionary must have empty space; has {:?}. tally is {}. key is {:?}",
-                self.array.len(),
-                self.tally(),
-                key
-            )
-        });
-
-        let association = self.association_at(index);
-        match association {
+    ) -> Result<AnyObjectRef> {
+        let key = key.into().follow_forwarded();
+        let index = self.scan_for_room(key)?;
+
+        match self.association_at(index) {
             None => {
                 let default = default_value();
-                self.at_new_index_put(index, key, default);
-                default
+                self.at_new_index_put(index, key, default)?;
+                Ok(default)
             }
-            Some(association) => association.value(),
+            Some(association) => Ok(association.value()),
         }
     }
 
-    fn association_at(&self, index: usize) -> Option<AssociationRef> {
-        let association_or_nil = self.array[index];
-        if association_or_nil
-            .as_object()
-            .unwrap()
-            .amount_of_slots()
-            .is_zero()
-        {
-            None
-        } else {
-            Some(association_or_nil.try_into().unwrap())
-        }
+    /// Remove a key returning its value, or None if the key is absent.
+    /// The following associations that collided with the removed one are moved closer to their hash
+    /// (`#fixCollisionsFrom:`), so that the lookup does not stop at the removed slot
+    pub fn remove_key(&mut self, key: impl Into<AnyObjectRef>) -> Result<Option<AnyObjectRef>> {
+        let Some(index) = self.scan_for(key.into()) else {
+            return Ok(None);
+        };
+        let Some(association) = self.association_at(index) else {
+            return Ok(None);
+        };
+
+        self.array.insert(index, nil())?;
+        self.set_tally(Immediate::new_i64(self.tally() - 1))?;
+        self.fix_collisions_from(index)?;
+        Ok(Some(association.value()))
     }
 
-    fn at_new_index_put(&mut self, index: usize, key: AnyObjectRef, value: AnyObjectRef) {
-        let mut association = Association::new(self.association_class).unwrap();
-
-        association.set_key(key);
-        association.set_value(value);
+    pub fn iter(&self) -> impl Iterator<Item = AssociationRef> + '_ {
+        self.array
+            .iter()
+            .filter(|each| !is_nil(**each))
+            .filter_map(|each| AssociationRef::try_from(*each).ok())
+    }
 
-        self.array.insert(index, association);
-        self.tally = Immediate::new_i64(self.tally() + 1);
-        self.full_check();
+    /// Return the index of the association with a given key or of an empty slot where it would be,
+    /// or None if the dictionary is full and does not include the key
+    pub fn scan_for(&self, key: AnyObjectRef) -> Option<usize> {
+        // the hash of a forwarder is not the hash of the object it points to
+        let key = key.follow_forwarded();
+        let size = self.array.len();
+        if size == 0 {
+            return None;
+        }
+        let start = scaled_identity_hash(key).rem_euclid(size as i128) as usize;
+        scan_for_identical_key(&self.array, start, key)
     }
 
-    fn scan_for(&self, key: AnyObjectRef) -> Option<usize> {
-        if key.is_immediate() {
-            panic!("Immediate objects are not supported yet");
+    /// Like `scan_for`, but grows the dictionary if it is full
+    fn scan_for_room(&mut self, key: AnyObjectRef) -> Result<usize> {
+        if let Some(index) = self.scan_for(key) {
+            return Ok(index);
         }
+        self.grow()?;
+        Ok(self
+            .scan_for(key)
+            .expect("Grown dictionary must have empty space"))
+    }
 
-        let hash = Smalltalk::identity_hash(ObjectPointer::from(key.as_i64()));
-        let finish = self.array.len();
-        let start = hash.rem_euclid(finish as u64) as usize;
+    fn association_at(&self, index: usize) -> Option<AssociationRef> {
+        let association_or_nil = self.array[index];
+        if is_nil(association_or_nil) {
+            None
+        } else {
+            Some(association_or_nil.try_into().unwrap())
+        }
+    }
 
-        self.find_item_or_empty_slot(start, finish, key)
+    fn at_new_index_put(
+        &mut self,
+        index: usize,
+        key: AnyObjectRef,
+        value: AnyObjectRef,
+    ) -> Result<()> {
+        let mut association = Association::new(self.association_class)?;
+
+        association.set_key(key)?;
+        association.set_value(value)?;
+
+        self.array.insert(index, association)?;
+        self.set_tally(Immediate::new_i64(self.tally() + 1))?;
+        self.full_check()
     }
 
     fn tally(&self) -> i64 {
         self.tally.as_integer().unwrap()
     }
 
-    fn find_item_or_empty_slot(
-        &self,
-        start: usize,
-        finish: usize,
-        object: AnyObjectRef,
-    ) -> Option<usize> {
-        let nil_object =
-            AnyObjectRef::from(RawObjectPointer::from(Smalltalk::nil_object().as_i64()))
-                .as_object()
-                .unwrap();
-
-        for (index, association) in self.array.as_slice()[start..finish]
-            .iter()
-            .map(|each| each.as_object().unwrap())
-            .enumerate()
-        {
-            let index = index + start;
-            if association.equals(&nil_object).unwrap() {
-                return Some(index);
-            }
-
-            if association.is_forwarded() {
-                panic!(
-                    "Association is forwarded: {:?} {:?}",
-                    association,
-                    association.header()
-                );
-            }
-
-            let association = AssociationRef::try_from(AnyObjectRef::from(association)).unwrap_or_else(|error| {
-                panic!(
-                    "[Forward] Failed to convert an object to association {:?} {:?}. Error: {}. nil object: {:?}",
-                    association,
-                    association.deref(),
-                    error,
-                    nil_object
-                )});
-            if association.key().equals(&object).unwrap_or_else(|error| {
-                panic!(
-                    "[Forward] Failed to compare association key {:?} with object {:?}. Error: {}",
-                    association.key(),
-                    object,
-                    error
-                );
-            }) {
-                return Some(index);
+    fn fix_collisions_from(&mut self, start: usize) -> Result<()> {
+        let size = self.array.len();
+        let mut index = (start + 1) % size;
+        while let Some(association) = self.association_at(index) {
+            let new_index = self
+                .scan_for(association.key())
+                .expect("Dictionary must have empty space after removal");
+            if new_index != index {
+                let other = self.array[new_index];
+                self.array.insert(new_index, association)?;
+                self.array.insert(index, other)?;
             }
+            index = (index + 1) % size;
         }
-
-        for (index, association) in self.array.as_slice()[0..start]
-            .iter()
-            .map(|each| each.as_object().unwrap())
-            .enumerate()
-        {
-            if association.equals(&nil_object).unwrap() {
-                return Some(index);
-            }
-
-            let association = AssociationRef::try_from(AnyObjectRef::from(association)).unwrap();
-            if association
-                .key()
-                .equals(&object)
-                .expect("[Backward] Compare association key with object")
-            {
-                return Some(index);
-            }
-        }
-        None
+        Ok(())
     }
 
-    fn full_check(&mut self) {
+    fn full_check(&mut self) -> Result<()> {
         let available_space = self.array.len() - (self.tally() as usize);
         if available_space < (self.array.len() / 4).max(1) {
-            self.grow();
-        }
-    }
-
-    fn scan_all_for(&self, key: AnyObjectRef) -> Vec<usize> {
-        let nil_object =
-            AnyObjectRef::from(RawObjectPointer::from(Smalltalk::nil_object().as_i64()))
-                .as_object()
-                .unwrap();
-
-        let mut indices = vec![];
-
-        for (index, each) in self
-            .array
-            .iter()
-            .map(|each| each.as_object().unwrap())
-            .enumerate()
-        {
-            if !each.equals(&nil_object).unwrap() {
-                let association = AssociationRef::try_from(AnyObjectRef::from(each)).unwrap();
-                if association.key().equals(&key).unwrap() {
-                    indices.push(index);
-                }
-            }
+            self.grow()?;
         }
-        indices
+        Ok(())
     }
 
-    fn grow(&mut self) {
+    /// Grow the array to `capacity + growSize` rounded to a prime and rehash the associations,
+    /// like `HashedCollection>>#grow`
+    fn grow(&mut self) -> Result<()> {
         let old_elements = self.array;
+        let capacity = old_elements.len();
 
-        let new_array_len = old_elements.len() * 2;
-        let new_array = Array::new(new_array_len).unwrap();
-        assign_field!(self.this, self.array, new_array);
-
-        if new_array.len() != new_array_len {
-            panic!(
-                "Failed to allocate an array of the requested size; expected: {}, actual: {}",
-                self.array.len(),
-                new_array_len
-            );
-        }
-
-        self.tally = Immediate::new_i64(0);
+        let new_array = Array::new(good_prime_at_least(capacity + capacity.max(2)))?;
+        self.set_array(new_array)?;
+        self.set_tally(Immediate::new_i64(0))?;
 
-        let nil_object =
-            AnyObjectRef::from(RawObjectPointer::from(Smalltalk::nil_object().as_i64()));
         for each in old_elements.iter() {
-            if !each.equals(&nil_object).unwrap() {
-                self.no_check_add(each.clone().try_into().unwrap());
+            if !is_nil(*each) {
+                self.no_check_add((*each).try_into()?)?;
             }
         }
+        Ok(())
     }
 
-    fn no_check_add(&mut self, association: AssociationRef) {
-        let key = association.key();
-        let index = self.scan_for(key).expect("Find an available slot");
-        self.array.insert(index, association);
-        self.tally = Immediate::new_i64(self.tally() + 1);
+    fn no_check_add(&mut self, association: AssociationRef) -> Result<()> {
+        let index = self
+            .scan_for(association.key())
+            .expect("Find an available slot");
+        self.array.insert(index, association)?;
+        self.set_tally(Immediate::new_i64(self.tally() + 1))
     }
 }
 
-impl Deref for IdentityDictionary {
-    type Target = Object;
-    fn deref(&self) -> &Self::Target {
-        &self.this
+/// Return the hash of an object as used by identity-based collections (`#scaledIdentityHash`).
+/// The identity hash of an immediate is its value
+pub fn scaled_identity_hash(object: AnyObjectRef) -> i128 {
+    match object.as_immediate() {
+        Ok(immediate) => {
+            let value = immediate
+                .as_integer()
+                .or_else(|| immediate.character_value().map(|value| value as i64))
+                // the bits of a SmallFloat64 without the tag
+                .unwrap_or(immediate.0 >> 3);
+            (value as i128) << 8
+        }
+        Err(_) => Smalltalk::identity_hash(ObjectPointer::from(object.as_i64())) as i128,
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-#[repr(transparent)]
-pub struct IdentityDictionaryRef(ObjectRef);
+/// Find the index of an association with a key identical to a given object or of the first empty slot,
+/// probing from the start index and wrapping around like `IdentityDictionary>>#scanFor:`.
+/// Works on the association array of any identity dictionary, including the ones that come from the image.
+/// Return None if the array is full and does not include the key
+pub fn scan_for_identical_key(
+    associations: &Array,
+    start: usize,
+    key: AnyObjectRef,
+) -> Option<usize> {
+    let key = key.follow_forwarded();
+    let size = associations.len();
+
+    (start..size).chain(0..start).find(|index| {
+        let association = associations[*index];
+        is_nil(association)
+            || association
+                .as_object()
+                .ok()
+                .and_then(|association| association.inst_var_at(0))
+                .is_some_and(|each| each.follow_forwarded().as_i64() == key.as_i64())
+    })
+}
+
+fn nil() -> AnyObjectRef {
+    AnyObjectRef::from(RawObjectPointer::from(Smalltalk::nil_object().as_i64()))
+}
 
-impl Deref for IdentityDictionaryRef {
-    type Target = IdentityDictionary;
-    fn deref(&self) -> &Self::Target {
-        unsafe { self.0.cast() }
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::objects::with_test_heap;
+    use vm_object_model::{ObjectFormat, TestHeap};
+
+    pub(crate) fn new_identity_dictionary(
+        heap: &TestHeap,
+        capacity: usize,
+    ) -> IdentityDictionaryRef {
+        let class = heap.new_class(ObjectFormat::NonIndexable, 3);
+        let association_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+        let array = heap.instantiate_indexable(heap.class_array(), capacity);
+        let dictionary = heap.new_object(
+            class,
+            &[
+                Immediate::new_i64(0).into(),
+                array.into(),
+                association_class.into(),
+            ],
+        );
+        IdentityDictionaryRef::try_from(AnyObjectRef::from(dictionary)).unwrap()
     }
-}
 
-impl DerefMut for IdentityDictionaryRef {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { self.0.cast_mut() }
+    #[test]
+    fn get_or_insert() {
+        with_test_heap(|heap| {
+            let mut dictionary = new_identity_dictionary(heap, 4);
+            let keys = (0..10)
+                .map(|index| heap.new_byte_symbol(&format!("key{}", index)))
+                .collect::<Vec<_>>();
+
+            for (index, key) in keys.iter().enumerate() {
+                let value = dictionary
+                    .get_or_insert(*key, || Immediate::new_i64(index as i64).into())
+                    .unwrap();
+                assert_eq!(value.as_integer(), Some(index as i64));
+            }
+            assert_eq!(dictionary.tally(), 10);
+            assert!(dictionary.array.len() > 10);
+
+            for (index, key) in keys.iter().enumerate() {
+                let value = dictionary.get_or_insert(*key, || unreachable!()).unwrap();
+                assert_eq!(value.as_integer(), Some(index as i64));
+            }
+            assert_eq!(dictionary.tally(), 10);
+        })
+    }
+    #[test]
+    fn get_or_insert_forwarded_key() {
+        with_test_heap(|heap| {
+            let mut dictionary = new_identity_dictionary(heap, 4);
+            let key = heap.new_byte_symbol("key");
+            dictionary
+                .get_or_insert(key, || Immediate::new_i64(42).into())
+                .unwrap();
+
+            let forwarder = heap.new_forwarder(key);
+            let value = dictionary
+                .get_or_insert(forwarder, || unreachable!())
+                .unwrap();
+            assert_eq!(value.as_integer(), Some(42));
+        })
     }
-}
 
-impl TryFrom<AnyObjectRef> for IdentityDictionaryRef {
-    type Error = Error;
+    #[test]
+    fn immediate_keys() {
+        with_test_heap(|heap| {
+            let mut dictionary = new_identity_dictionary(heap, 4);
+            for each in [-3, 0, 7, 1 << 58] {
+                dictionary
+                    .at_put(Immediate::new_i64(each), Immediate::new_i64(each * 2))
+                    .unwrap();
+            }
+            dictionary
+                .at_put(Immediate::new_character('a'), heap.true_object())
+                .unwrap();
 
-    fn try_from(value: AnyObjectRef) -> Result<Self> {
-        let object = value.as_object()?;
+            assert_eq!(dictionary.len(), 5);
+            assert_eq!(
+                dictionary.at(Immediate::new_i64(-3)).unwrap().as_integer(),
+                Some(-6)
+            );
+            assert!(dictionary.at(Immediate::new_character('a')).is_some());
+            assert!(dictionary.at(Immediate::new_i64(8)).is_none());
 
-        if object.amount_of_slots() != 3 {
-            return Err(Error::InvalidType("IdentityDictionary".to_string()));
-        }
+            dictionary
+                .at_put(Immediate::new_i64(7), Immediate::new_i64(0))
+                .unwrap();
+            assert_eq!(dictionary.len(), 5);
+            assert_eq!(
+                dictionary.at(Immediate::new_i64(7)).unwrap().as_integer(),
+                Some(0)
+            );
+        })
+    }
+
+    #[test]
+    fn remove_key_keeps_colliding_keys_reachable() {
+        with_test_heap(|heap| {
+            let mut dictionary = new_identity_dictionary(heap, 17);
+            // the keys collide as their scaled hashes are equal modulo 17
+            let keys = [1, 18, 35].map(Immediate::new_i64);
+            for (index, key) in keys.iter().enumerate() {
+                dictionary
+                    .at_put(*key, Immediate::new_i64(index as i64))
+                    .unwrap();
+            }
 
-        Ok(Self(object))
+            let removed = dictionary.remove_key(keys[0]).unwrap();
+            assert_eq!(removed.unwrap().as_integer(), Some(0));
+            assert!(dictionary.remove_key(keys[0]).unwrap().is_none());
+            assert_eq!(dictionary.len(), 2);
+            assert_eq!(dictionary.at(keys[1]).unwrap().as_integer(), Some(1));
+            assert_eq!(dictionary.at(keys[2]).unwrap().as_integer(), Some(2));
+
+            let mut values = dictionary
+                .iter()
+                .map(|each| each.value().as_integer().unwrap())
+                .collect::<Vec<_>>();
+            values.sort();
+            assert_eq!(values, vec![1, 2]);
+        })
     }
 }
@@ -0,0 +1,98 @@
+use num::bigint::Sign;
+use num::BigInt;
+use vm_bindings::Smalltalk;
+use vm_object_model::{AnyObjectRef, ByteArrayRef, Error, ObjectFormat, ObjectRef, Result};
+
+/// A LargePositiveInteger or a LargeNegativeInteger: the magnitude as little-endian bytes,
+/// while the sign is given by the class.
+/// The format does not tell a large integer from a ByteArray, so the kind is decided by the class index,
+/// which Spur fixes for the large integer classes
+#[derive(Debug, Copy, Clone)]
+pub enum LargeInteger {
+    Positive(ByteArrayRef),
+    Negative(ByteArrayRef),
+}
+
+impl LargeInteger {
+    /// Allocate an instance of a large integer class with the magnitude of a given integer.
+    /// The class must match the sign of the integer.
+    /// The magnitude is normalized, without leading zero bytes
+    pub fn new(class: ObjectRef, value: &BigInt) -> Result<Self> {
+        let (sign, magnitude) = value.to_bytes_le();
+        let mut bytes = Smalltalk::instantiate_indexable::<ByteArrayRef>(class, magnitude.len())?;
+        bytes.as_slice_mut()?.copy_from_slice(&magnitude);
+        match sign {
+            Sign::Minus => Ok(Self::Negative(bytes)),
+            _ => Ok(Self::Positive(bytes)),
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        matches!(self, Self::Negative(_))
+    }
+
+    /// Return the little-endian bytes of the absolute value
+    pub fn magnitude(&self) -> &[u8] {
+        match self {
+            Self::Positive(bytes) | Self::Negative(bytes) => bytes.as_slice(),
+        }
+    }
+
+    pub fn to_big_int(&self) -> BigInt {
+        let sign = if self.is_negative() {
+            Sign::Minus
+        } else {
+            Sign::Plus
+        };
+        BigInt::from_bytes_le(sign, self.magnitude())
+    }
+}
+
+impl TryFrom<AnyObjectRef> for LargeInteger {
+    type Error = Error;
+
+    fn try_from(value: AnyObjectRef) -> Result<Self> {
+        let object = value.as_object()?.follow_forwarded();
+
+        let large_integer = match (object.object_format(), object.header().class_index()) {
+            (ObjectFormat::Indexable8(_), Smalltalk::LARGE_POSITIVE_INTEGER_CLASS_INDEX) => {
+                Self::Positive(ByteArrayRef::try_from(AnyObjectRef::from(object))?)
+            }
+            (ObjectFormat::Indexable8(_), Smalltalk::LARGE_NEGATIVE_INTEGER_CLASS_INDEX) => {
+                Self::Negative(ByteArrayRef::try_from(AnyObjectRef::from(object))?)
+            }
+            _ => return Err(Error::InvalidType("LargeInteger".to_string())),
+        };
+        Ok(large_integer)
+    }
+}
+
+impl From<LargeInteger> for AnyObjectRef {
+    fn from(value: LargeInteger) -> Self {
+        match value {
+            LargeInteger::Positive(bytes) | LargeInteger::Negative(bytes) => bytes.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::with_test_heap;
+
+    #[test]
+    fn magnitude_is_little_endian() {
+        with_test_heap(|heap| {
+            let class = heap.class_large_negative_integer();
+            let value = -(BigInt::from(1) << 64u32) - 2;
+            let large_integer = LargeInteger::try_from(AnyObjectRef::from(
+                LargeInteger::new(class, &value).unwrap(),
+            ))
+            .unwrap();
+
+            assert!(large_integer.is_negative());
+            assert_eq!(large_integer.magnitude(), &[2, 0, 0, 0, 0, 0, 0, 0, 1]);
+            assert_eq!(large_integer.to_big_int(), value);
+        })
+    }
+}
@@ -0,0 +1,127 @@
+use crate::objects::{ArrayRef, CompiledMethodRef, SmalltalkString};
+use vm_bindings::Smalltalk;
+use vm_object_model::{AnyObjectRef, ClassRef, Error, ObjectFormat, ObjectRef, Result};
+
+/// A MethodDictionary: the selectors are stored in its indexable slots
+/// and the methods in its array at the same index
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct MethodDictionaryRef(ObjectRef);
+
+impl MethodDictionaryRef {
+    /// tally and array
+    const AMOUNT_OF_FIXED_SLOTS: usize = 2;
+    const ARRAY_INDEX: usize = 1;
+
+    /// Return the method with a given selector, comparing the characters of the selectors
+    pub fn method_at(&self, selector: &str) -> Option<CompiledMethodRef> {
+        let methods = ArrayRef::try_from(self.0.inst_var_at(Self::ARRAY_INDEX)?).ok()?;
+        let index = (Self::AMOUNT_OF_FIXED_SLOTS..self.0.amount_of_slots()).position(|slot| {
+            self.0
+                .inst_var_at(slot)
+                .and_then(|key| SmalltalkString::try_from(key).ok())
+                .is_some_and(|key| key.chars().eq(selector.chars()))
+        })?;
+        CompiledMethodRef::try_from(methods.get(index)?).ok()
+    }
+}
+
+impl TryFrom<AnyObjectRef> for MethodDictionaryRef {
+    type Error = Error;
+
+    fn try_from(value: AnyObjectRef) -> Result<Self> {
+        let object_ref = value.as_object()?.follow_forwarded();
+        let amount_of_fixed_slots = Smalltalk::class_of(object_ref)
+            .and_then(|class| class.amount_of_fixed_slots().ok())
+            .unwrap_or(0);
+
+        match object_ref.object_format() {
+            ObjectFormat::IndexableWithInstVars
+                if amount_of_fixed_slots == Self::AMOUNT_OF_FIXED_SLOTS =>
+            {
+                Ok(Self(object_ref))
+            }
+            _ => Err(Error::InvalidType("MethodDictionary".to_string())),
+        }
+    }
+}
+
+impl From<MethodDictionaryRef> for AnyObjectRef {
+    fn from(value: MethodDictionaryRef) -> Self {
+        value.0.into()
+    }
+}
+
+/// Return the method that is activated when a message is sent to an instance of a class,
+/// looking it up in the class and its superclasses
+pub fn lookup_method(class: ClassRef, selector: &str) -> Option<CompiledMethodRef> {
+    std::iter::once(class)
+        .chain(class.superclasses())
+        .find_map(|class| {
+            MethodDictionaryRef::try_from(class.method_dictionary().ok()?)
+                .ok()?
+                .method_at(selector)
+        })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::objects::context::tests::new_method_of;
+    use crate::objects::{byte_symbol_class, with_test_heap};
+    use vm_object_model::TestHeap;
+
+    /// Install a method dictionary with given selectors and methods in a class
+    pub(crate) fn set_methods(
+        heap: &TestHeap,
+        class: ObjectRef,
+        methods: &[(&str, CompiledMethodRef)],
+    ) {
+        let dictionary_class = heap.new_class(ObjectFormat::IndexableWithInstVars, 2);
+        let symbol_class = byte_symbol_class(heap);
+
+        let array = heap.new_array(
+            &methods
+                .iter()
+                .map(|(_, method)| AnyObjectRef::from(*method))
+                .collect::<Vec<_>>(),
+        );
+        let mut dictionary = heap.instantiate_indexable(dictionary_class, methods.len());
+        dictionary.inst_var_at_put_unchecked(1, array);
+        for (index, (selector, _)) in methods.iter().enumerate() {
+            dictionary.inst_var_at_put_unchecked(
+                2 + index,
+                heap.new_bytes(symbol_class, selector.as_bytes()),
+            );
+        }
+
+        let mut class = class;
+        class.inst_var_at_put_unchecked(ClassRef::METHOD_DICTIONARY_INDEX, dictionary);
+    }
+
+    #[test]
+    fn lookup_in_superclasses() {
+        with_test_heap(|heap| {
+            let superclass =
+                heap.new_named_class("Collection", None, ObjectFormat::NonIndexable, &[]);
+            let class = heap.new_named_class(
+                "OrderedCollection",
+                Some(superclass),
+                ObjectFormat::NonIndexable,
+                &[],
+            );
+            let size = new_method_of(heap, "Collection", "size");
+            let add = new_method_of(heap, "OrderedCollection", "add:");
+            set_methods(heap, superclass, &[("size", size), ("add:", size)]);
+            set_methods(heap, class, &[("add:", add)]);
+
+            let class = ClassRef::try_from(AnyObjectRef::from(class)).unwrap();
+            let found = |selector| {
+                lookup_method(class, selector).map(|method| AnyObjectRef::from(method).as_i64())
+            };
+            assert_eq!(found("add:"), Some(AnyObjectRef::from(add).as_i64()));
+            assert_eq!(found("size"), Some(AnyObjectRef::from(size).as_i64()));
+            assert_eq!(found("remove:"), None);
+        })
+    }
+}
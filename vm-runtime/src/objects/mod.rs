@@ -1,40 +1,180 @@
 mod array;
 mod association;
+mod boxed_float;
 mod byte_symbol;
 mod compiled_method;
+mod context;
+mod dictionary;
+mod ephemeron;
+mod equality;
 pub mod identity_dictionary;
+mod large_integer;
+mod method_dictionary;
+mod number;
 mod ordered_collection;
+mod print_string;
+mod process;
+mod semaphore;
+mod set;
+mod smalltalk_string;
+mod weak_array;
+mod weak_set;
 mod weak_symbol_set;
 mod wide_symbol;
 
 pub use array::*;
 pub use association::*;
+pub use boxed_float::*;
 pub use byte_symbol::*;
 pub use compiled_method::*;
+pub use context::*;
+pub use dictionary::*;
+pub use ephemeron::*;
+pub use equality::*;
 pub use identity_dictionary::*;
+pub use large_integer::*;
+pub use method_dictionary::*;
+pub use number::*;
 pub use ordered_collection::*;
+pub use print_string::*;
+pub use process::*;
+pub use semaphore::*;
+pub use set::*;
+pub use smalltalk_string::*;
+pub use weak_array::*;
+pub use weak_set::*;
 pub use weak_symbol_set::*;
 pub use wide_symbol::*;
 
+#[cfg(test)]
+use vm_object_model::{ObjectFormat, ObjectRef, TestHeap};
+
+/// Store a value in a field of an object through the write barrier,
+/// evaluating to `Err(Error::Immutable)` if the object is read-only
 #[macro_export]
 macro_rules! assign_field {
     ($obj:ident . $field:ident, $value:expr) => {
-        assign_field!($obj, $obj . $field, $value)
+        assign_field!($obj, $obj.$field, $value)
     };
     ($obj:expr, $setter:expr, $value:expr) => {
-        {
-            fn prepare_to_store_in(object: &vm_object_model::Object, value: impl Into<vm_object_model::AnyObjectRef>) {
-                use vm_bindings::{ObjectPointer, Smalltalk};
-
-                let object_ptr = ObjectPointer::from(object.as_ptr());
-                let value_ptr = ObjectPointer::from(value.into().as_i64());
-                Smalltalk::prepare_to_store(object_ptr, value_ptr);
+        match $obj.check_mutable() {
+            Ok(()) => {
+                $crate::assign_field_unchecked!($obj, $setter, $value);
+                vm_object_model::Result::Ok(())
             }
-
-            let value_to_store = $value;
-            prepare_to_store_in(&$obj, value_to_store);
-            $setter = value_to_store;
+            Err(error) => Err(error),
         }
     };
 }
 
+/// An unchecked version of `assign_field!` that also writes into read-only objects
+#[macro_export]
+macro_rules! assign_field_unchecked {
+    ($obj:ident . $field:ident, $value:expr) => {
+        assign_field_unchecked!($obj, $obj.$field, $value)
+    };
+    ($obj:expr, $setter:expr, $value:expr) => {{
+        fn prepare_to_store_in(
+            object: &vm_object_model::Object,
+            value: impl Into<vm_object_model::AnyObjectRef>,
+        ) {
+            use vm_bindings::{ObjectPointer, Smalltalk};
+
+            let object_ptr = ObjectPointer::from(object.as_ptr());
+            let value_ptr = ObjectPointer::from(value.into().as_i64());
+            Smalltalk::prepare_to_store(object_ptr, value_ptr);
+        }
+
+        let value_to_store = $value;
+        prepare_to_store_in(&$obj, value_to_store);
+        $setter = value_to_store;
+    }};
+}
+
+/// Evaluate a block with an in-memory heap installed as the object memory,
+/// so that objects can be allocated and modified without a running vm
+#[cfg(test)]
+pub(crate) fn with_test_heap<R>(block: impl FnOnce(&vm_object_model::TestHeap) -> R) -> R {
+    let heap = std::rc::Rc::new(vm_object_model::TestHeap::new());
+    vm_bindings::with_object_memory(heap.clone(), || block(&heap))
+}
+
+/// Return the class of the test heap with a given name, creating it the first time it is asked for,
+/// so that all fixtures of a test share the same string and symbol classes
+#[cfg(test)]
+pub(crate) fn test_class_named(heap: &TestHeap, name: &str, format: ObjectFormat) -> ObjectRef {
+    vm_bindings::Smalltalk::class_named(name)
+        .map(ObjectRef::from)
+        .unwrap_or_else(|| heap.new_named_class(name, None, format, &[]))
+}
+
+#[cfg(test)]
+pub(crate) fn byte_string_class(heap: &TestHeap) -> ObjectRef {
+    test_class_named(heap, "ByteString", ObjectFormat::Indexable8(16))
+}
+
+#[cfg(test)]
+pub(crate) fn wide_string_class(heap: &TestHeap) -> ObjectRef {
+    test_class_named(heap, "WideString", ObjectFormat::Indexable32(10))
+}
+
+#[cfg(test)]
+pub(crate) fn byte_symbol_class(heap: &TestHeap) -> ObjectRef {
+    test_class_named(heap, "ByteSymbol", ObjectFormat::Indexable8(16))
+}
+
+#[cfg(test)]
+pub(crate) fn wide_symbol_class(heap: &TestHeap) -> ObjectRef {
+    test_class_named(heap, "WideSymbol", ObjectFormat::Indexable32(10))
+}
+
+#[cfg(test)]
+pub(crate) use compiled_method::tests::{new_method, ALTERNATE_BYTECODE_SET};
+#[cfg(test)]
+pub(crate) use identity_dictionary::tests::new_identity_dictionary;
+#[cfg(test)]
+pub(crate) use method_dictionary::tests::set_methods;
+#[cfg(test)]
+pub(crate) use weak_set::tests::new_weak_set;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vm_bindings::{FromSmalltalk, ToSmalltalk};
+    use vm_object_model::{AnyObjectRef, RawObjectPointer};
+    use vm_object_model_derive::{FromSmalltalk, ToSmalltalk};
+
+    #[derive(Debug, PartialEq, ToSmalltalk, FromSmalltalk)]
+    struct Sample {
+        count: u32,
+        enabled: bool,
+        ratio: Option<f64>,
+        pairs: Vec<(i64, bool)>,
+    }
+
+    #[derive(Debug, PartialEq, ToSmalltalk, FromSmalltalk)]
+    struct Point(i64, i64);
+
+    #[test]
+    fn derived_conversions_round_trip() {
+        with_test_heap(|_heap| {
+            let sample = Sample {
+                count: 3,
+                enabled: true,
+                ratio: None,
+                pairs: vec![(-1, false), (1 << 40, true)],
+            };
+            let object = sample.to_smalltalk().unwrap();
+            let array = AnyObjectRef::from(RawObjectPointer::from(object.as_i64()));
+            assert_eq!(ArrayRef::try_from(array).unwrap().len(), 4);
+            assert_eq!(Sample::from_smalltalk(object).unwrap(), sample);
+
+            let point = Point(3, -4);
+            assert_eq!(
+                Point::from_smalltalk(point.to_smalltalk().unwrap()).unwrap(),
+                point
+            );
+            assert!(Point::from_smalltalk(sample.to_smalltalk().unwrap()).is_err());
+        })
+    }
+}
@@ -0,0 +1,150 @@
+use crate::objects::{BoxedFloat64, LargeInteger};
+use num::bigint::Sign;
+use num::{BigInt, ToPrimitive};
+use vm_bindings::Smalltalk;
+use vm_object_model::{AnyObjectRef, Error, Immediate, ObjectRef, Result};
+
+/// The classes of the numbers that do not fit in an immediate
+#[derive(Debug, Copy, Clone)]
+pub struct NumberClasses {
+    pub large_positive_integer: ObjectRef,
+    pub large_negative_integer: ObjectRef,
+    pub boxed_float: ObjectRef,
+}
+
+impl NumberClasses {
+    /// Return the classes registered at their fixed indices of the class table of the running image
+    pub fn from_class_table() -> Option<Self> {
+        let class_at = |index| Smalltalk::class_at_index(index).map(ObjectRef::from);
+        Some(Self {
+            large_positive_integer: class_at(Smalltalk::LARGE_POSITIVE_INTEGER_CLASS_INDEX)?,
+            large_negative_integer: class_at(Smalltalk::LARGE_NEGATIVE_INTEGER_CLASS_INDEX)?,
+            boxed_float: class_at(Smalltalk::BOXED_FLOAT_CLASS_INDEX)?,
+        })
+    }
+
+    /// Return a SmallInteger if the value fits, otherwise allocate a large integer
+    pub fn new_integer(&self, value: &BigInt) -> Result<AnyObjectRef> {
+        if let Some(integer) = value
+            .to_i64()
+            .filter(|integer| Immediate::is_small_integer_value(*integer))
+        {
+            return Ok(Immediate::new_i64(integer).into());
+        }
+
+        let class = if value.sign() == Sign::Minus {
+            self.large_negative_integer
+        } else {
+            self.large_positive_integer
+        };
+        Ok(LargeInteger::new(class, value)?.into())
+    }
+
+    pub fn new_i128(&self, value: i128) -> Result<AnyObjectRef> {
+        self.new_integer(&BigInt::from(value))
+    }
+
+    pub fn new_u128(&self, value: u128) -> Result<AnyObjectRef> {
+        self.new_integer(&BigInt::from(value))
+    }
+
+    /// Return a SmallFloat64 if the exponent fits, otherwise allocate a BoxedFloat64
+    pub fn new_float(&self, value: f64) -> Result<AnyObjectRef> {
+        match Immediate::try_new_float(value) {
+            Some(immediate) => Ok(immediate.into()),
+            None => Ok(BoxedFloat64::new(self.boxed_float, value)?.into()),
+        }
+    }
+}
+
+/// Return the value of a SmallInteger or a large integer
+pub fn integer_value(object: AnyObjectRef) -> Result<BigInt> {
+    if let Some(integer) = object.as_integer() {
+        return Ok(BigInt::from(integer));
+    }
+    Ok(LargeInteger::try_from(object)?.to_big_int())
+}
+
+/// Return the value of an integer that fits in an i128
+pub fn i128_value(object: AnyObjectRef) -> Result<i128> {
+    integer_value(object)?
+        .to_i128()
+        .ok_or_else(|| Error::InvalidType("i128".to_string()))
+}
+
+/// Return the value of a non-negative integer that fits in an u128
+pub fn u128_value(object: AnyObjectRef) -> Result<u128> {
+    integer_value(object)?
+        .to_u128()
+        .ok_or_else(|| Error::InvalidType("u128".to_string()))
+}
+
+/// Return the value of a SmallFloat64 or a BoxedFloat64
+pub fn float_value(object: AnyObjectRef) -> Result<f64> {
+    if let Some(float) = object
+        .as_immediate()
+        .ok()
+        .and_then(|immediate| immediate.as_float())
+    {
+        return Ok(float);
+    }
+    Ok(BoxedFloat64::try_from(object)?.value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::with_test_heap;
+
+    #[test]
+    fn integers_choose_immediate_when_they_fit() {
+        with_test_heap(|_heap| {
+            let classes = NumberClasses::from_class_table().unwrap();
+
+            let small = classes
+                .new_i128(Immediate::MAX_SMALL_INTEGER as i128)
+                .unwrap();
+            assert!(small.is_immediate());
+            assert_eq!(
+                i128_value(small).unwrap(),
+                Immediate::MAX_SMALL_INTEGER as i128
+            );
+
+            for value in [
+                Immediate::MAX_SMALL_INTEGER as i128 + 1,
+                Immediate::MIN_SMALL_INTEGER as i128 - 1,
+                i128::MIN,
+                i128::MAX,
+            ] {
+                let large = classes.new_i128(value).unwrap();
+                assert!(!large.is_immediate());
+                assert_eq!(i128_value(large).unwrap(), value);
+            }
+
+            let large = classes.new_u128(u128::MAX).unwrap();
+            assert_eq!(u128_value(large).unwrap(), u128::MAX);
+            assert!(i128_value(large).is_err());
+            assert!(u128_value(classes.new_i128(-1).unwrap()).is_err());
+        })
+    }
+
+    #[test]
+    fn floats_choose_immediate_when_they_fit() {
+        with_test_heap(|_heap| {
+            let classes = NumberClasses::from_class_table().unwrap();
+
+            let small = classes.new_float(1.5).unwrap();
+            assert!(small.is_immediate());
+            assert_eq!(float_value(small).unwrap(), 1.5);
+
+            for value in [1.0e300, -1.0e-300, f64::INFINITY] {
+                let boxed = classes.new_float(value).unwrap();
+                assert!(!boxed.is_immediate());
+                assert_eq!(float_value(boxed).unwrap().to_bits(), value.to_bits());
+            }
+            let nan = classes.new_float(f64::NAN).unwrap();
+            assert!(float_value(nan).unwrap().is_nan());
+            assert!(float_value(Immediate::new_i64(1).into()).is_err());
+        })
+    }
+}
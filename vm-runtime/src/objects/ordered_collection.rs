@@ -1,10 +1,9 @@
-use crate::assign_field;
 use crate::objects::{Array, ArrayRef};
-use std::ops::{Deref, DerefMut};
 use vm_bindings::Smalltalk;
-use vm_object_model::{AnyObjectRef, Error, Immediate, Object, ObjectRef, Result};
+use vm_object_model::{AnyObjectRef, Immediate, Object, ObjectRef, RawObjectPointer, Result};
+use vm_object_model_derive::PharoObject;
 
-#[derive(Debug)]
+#[derive(Debug, PharoObject)]
 #[repr(C)]
 pub struct OrderedCollection {
     this: Object,
@@ -25,24 +24,84 @@ impl OrderedCollection {
         let mut ordered_collection =
             Smalltalk::instantiate::<OrderedCollectionRef>(ordered_collection_class)?;
 
-        assign_field!(ordered_collection.array, Array::new(capacity)?);
-        ordered_collection.first_index = Immediate::new_i64(1);
-        ordered_collection.last_index = Immediate::new_i64(0);
+        ordered_collection.set_array(Array::new(capacity)?)?;
+        ordered_collection.set_first_index(Immediate::new_i64(1))?;
+        ordered_collection.set_last_index(Immediate::new_i64(0))?;
         Ok(ordered_collection)
     }
 
-    pub fn add_last(&mut self, object: impl Into<AnyObjectRef>) {
+    pub fn add_first(&mut self, object: impl Into<AnyObjectRef>) -> Result<()> {
+        if self.first_index() == 1 {
+            self.make_room_at_first()?;
+        }
+        let first_index = self.first_index() - 1;
+
+        self.array.insert(first_index - 1, object)?;
+        self.set_first_index(Immediate::new_i64(first_index as i64))
+    }
+
+    pub fn add_last(&mut self, object: impl Into<AnyObjectRef>) -> Result<()> {
         if self.last_index() == self.array.len() {
-            self.make_room_at_last();
+            self.make_room_at_last()?;
+        }
+        let last_index = self.last_index();
+
+        self.array.insert(last_index, object)?;
+        self.set_last_index(Immediate::new_i64(last_index as i64 + 1))
+    }
+
+    /// Remove and return the first element, or None if the collection is empty
+    pub fn remove_first(&mut self) -> Result<Option<AnyObjectRef>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let first_index = self.first_index();
+        let first_object = self.array.get(first_index - 1);
+
+        self.array.insert(first_index - 1, nil())?;
+        self.set_first_index(Immediate::new_i64(first_index as i64 + 1))?;
+        Ok(first_object)
+    }
+
+    /// Remove and return the last element, or None if the collection is empty
+    pub fn remove_last(&mut self) -> Result<Option<AnyObjectRef>> {
+        if self.is_empty() {
+            return Ok(None);
         }
         let last_index = self.last_index();
+        let last_object = self.array.get(last_index - 1);
 
-        self.array.insert(last_index, object);
-        self.last_index = Immediate::new_i64(last_index as i64 + 1);
+        self.array.insert(last_index - 1, nil())?;
+        self.set_last_index(Immediate::new_i64(last_index as i64 - 1))?;
+        Ok(last_object)
+    }
+
+    /// Remove all elements keeping the capacity, like `#removeAll`
+    pub fn remove_all(&mut self) -> Result<()> {
+        let capacity = self.array.len();
+        self.set_array(Array::new(capacity)?)?;
+        self.reset_to((capacity / 3).max(1))
+    }
+
+    /// Return an element at a given zero-based index
+    pub fn get(&self, index: usize) -> Option<AnyObjectRef> {
+        if index < self.len() {
+            self.array.get(self.first_index() - 1 + index)
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = AnyObjectRef> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index))
     }
 
     pub fn len(&self) -> usize {
-        self.last_index() - self.first_index() + 1
+        self.last_index() + 1 - self.first_index()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     fn first_index(&self) -> usize {
@@ -53,109 +112,225 @@ impl OrderedCollection {
         self.last_index.as_integer().unwrap() as usize
     }
 
-    fn make_room_at_last(&mut self) {
+    fn reset_to(&mut self, index: usize) -> Result<()> {
+        self.set_first_index(Immediate::new_i64(index as i64))?;
+        self.set_last_index(Immediate::new_i64(index as i64 - 1))
+    }
+
+    /// Make some empty slots at the front of the array. If we have more than 50% free space,
+    /// then just move the elements, so that the first 50% of the slots are free,
+    /// otherwise add new free slots to the front by growing. Precondition: first_index = 1
+    fn make_room_at_first(&mut self) -> Result<()> {
+        let tally = self.len();
+        let capacity = self.array.len();
+        if tally * 2 >= capacity {
+            return self.grow_at_first();
+        }
+        if tally == 0 {
+            return self.reset_to(capacity + 1);
+        }
+
+        let first_index = self.first_index();
+        let last_index = self.last_index();
+        let new_first_index = capacity / 2 + 1;
+        let new_last_index = new_first_index + last_index - first_index;
+
+        self.move_elements(new_first_index)?;
+        for index in first_index..new_first_index {
+            self.array.insert(index - 1, nil())?;
+        }
+
+        self.set_first_index(Immediate::new_i64(new_first_index as i64))?;
+        self.set_last_index(Immediate::new_i64(new_last_index as i64))
+    }
+
+    /// Make some empty slots at the end of the array. If we have more than 50% free space,
+    /// then just move the elements, so that the last 50% of the slots are free,
+    /// otherwise add new free slots to the end by growing. Precondition: last_index = array size
+    fn make_room_at_last(&mut self) -> Result<()> {
         let tally = self.len();
         if (tally * 2) >= self.last_index() {
             return self.grow_at_last();
         }
+        if tally == 0 {
+            return self.reset_to(1);
+        }
 
-        todo!()
-    }
-
-    //// makeRoomAtLast
-    //     // 	"Make some empty slots at the end of the array. If we have more than 50% free space, then just move the elements, so that the last 50% of the slots are free, otherwise add new free slots to the end by growing. Precondition: lastIndex = array size"
-    //     //
-    //     // 	| tally newFirstIndex newLastIndex |
-    //     // 	tally := self size.
-    //     // 	tally * 2 >= lastIndex ifTrue: [ ^self growAtLast ].
-    //     // 	tally = 0 ifTrue: [ ^self resetTo: 1 ].
-    //     // 	newLastIndex := lastIndex // 2.
-    //     // 	newFirstIndex := newLastIndex - lastIndex + firstIndex.
-    //     // 	array
-    //     // 		replaceFrom: newFirstIndex
-    //     // 		to: newLastIndex
-    //     // 		with: array
-    //     // 		startingAt: firstIndex.
-    //     // 	array from: newLastIndex + 1 to: lastIndex put: nil.
-    //     // 	firstIndex := newFirstIndex.
-    //     // 	lastIndex := newLastIndex
-
-    fn grow_at_last(&mut self) {
-        let mut new_array = Array::new((self.array.len() * 2).max(1)).unwrap();
+        let first_index = self.first_index();
+        let last_index = self.last_index();
+        let new_last_index = last_index / 2;
+        let new_first_index = new_last_index + first_index - last_index;
+
+        self.move_elements(new_first_index)?;
+        for index in new_last_index + 1..=last_index {
+            self.array.insert(index - 1, nil())?;
+        }
+
+        self.set_first_index(Immediate::new_i64(new_first_index as i64))?;
+        self.set_last_index(Immediate::new_i64(new_last_index as i64))
+    }
+
+    /// Copy the elements within the array so that the first one is at a new (one-based) index
+    fn move_elements(&mut self, new_first_index: usize) -> Result<()> {
+        let elements = self.array.as_slice()[self.first_index() - 1..self.last_index()].to_vec();
+        let start_index = new_first_index - 1;
+
+        self.array
+            .copy_from(start_index, start_index + elements.len(), &elements)
+    }
+
+    fn grow_at_first(&mut self) -> Result<()> {
+        let mut new_array = Array::new((self.array.len() * 2).max(1))?;
+
+        let first_index = self.first_index();
+        let last_index = self.last_index();
+        let new_first_index = new_array.len() - self.array.len() + first_index;
+        let new_last_index = new_first_index + last_index - first_index;
+
+        let source_slice = &self.array.as_slice()[first_index - 1..last_index];
+        new_array.copy_from(new_first_index - 1, new_last_index, source_slice)?;
+
+        self.set_array(new_array)?;
+        self.set_first_index(Immediate::new_i64(new_first_index as i64))?;
+        self.set_last_index(Immediate::new_i64(new_last_index as i64))
+    }
+
+    fn grow_at_last(&mut self) -> Result<()> {
+        let mut new_array = Array::new((self.array.len() * 2).max(1))?;
 
         let start_index = self.first_index() - 1;
         let end_index = self.last_index();
 
         let source_slice = &self.array.as_slice()[start_index..end_index];
 
-        new_array.copy_from(start_index, end_index, source_slice);
+        new_array.copy_from(start_index, end_index, source_slice)?;
 
-        assign_field!(self.array, new_array);
+        self.set_array(new_array)
     }
+}
 
-    pub fn validate_non_forward(&self) {
-        if self.array.is_forwarded() {
-            panic!("The array is forwarded!");
-        }
-    }
+fn nil() -> AnyObjectRef {
+    AnyObjectRef::from(RawObjectPointer::from(Smalltalk::nil_object().as_i64()))
 }
 
-impl Deref for OrderedCollection {
-    type Target = Object;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::with_test_heap;
+    use vm_object_model::ObjectFormat;
 
-    fn deref(&self) -> &Self::Target {
-        &self.this
+    fn items(collection: &OrderedCollection) -> Vec<i64> {
+        collection
+            .iter()
+            .map(|each| each.as_integer().unwrap())
+            .collect()
     }
-}
 
-#[derive(Debug, Copy, Clone)]
-#[repr(transparent)]
-pub struct OrderedCollectionRef(ObjectRef);
+    #[test]
+    fn add_last_grows_the_array() {
+        with_test_heap(|heap| {
+            let class = heap.new_class(ObjectFormat::NonIndexable, 3);
+            let mut collection = OrderedCollection::with_capacity(class, 2).unwrap();
+            assert_eq!(collection.len(), 0);
+
+            for each in 0..5 {
+                collection.add_last(Immediate::new_i64(each)).unwrap();
+            }
+            assert_eq!(collection.len(), 5);
+            assert_eq!(collection.array.len(), 8);
 
-impl Deref for OrderedCollectionRef {
-    type Target = OrderedCollection;
-    fn deref(&self) -> &Self::Target {
-        let c: &OrderedCollection = unsafe { self.0.cast() };
-        c.validate_non_forward();
-        c
+            let items = collection.array.as_slice()[0..5]
+                .iter()
+                .map(|each| each.as_integer().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(items, vec![0, 1, 2, 3, 4]);
+        })
     }
-}
 
-impl DerefMut for OrderedCollectionRef {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        let c: &mut OrderedCollection = unsafe { self.0.cast_mut() };
-        c.validate_non_forward();
-        c
+    #[test]
+    fn add_first_grows_the_array() {
+        with_test_heap(|heap| {
+            let class = heap.new_class(ObjectFormat::NonIndexable, 3);
+            let mut collection = OrderedCollection::with_capacity(class, 2).unwrap();
+
+            for each in 0..5 {
+                collection.add_first(Immediate::new_i64(each)).unwrap();
+            }
+            collection.add_last(Immediate::new_i64(5)).unwrap();
+            assert_eq!(items(&collection), vec![4, 3, 2, 1, 0, 5]);
+            assert_eq!(collection.get(1).unwrap().as_integer(), Some(3));
+            assert!(collection.get(6).is_none());
+        })
     }
-}
 
-impl TryFrom<AnyObjectRef> for OrderedCollectionRef {
-    type Error = Error;
+    #[test]
+    fn make_room_by_moving_elements() {
+        with_test_heap(|heap| {
+            let class = heap.new_class(ObjectFormat::NonIndexable, 3);
+            let mut collection = OrderedCollection::with_capacity(class, 4).unwrap();
+            for each in 0..4 {
+                collection.add_last(Immediate::new_i64(each)).unwrap();
+            }
+            for _ in 0..3 {
+                collection.remove_first().unwrap();
+            }
 
-    fn try_from(value: AnyObjectRef) -> Result<Self> {
-        const EXPECTED_AMOUNT_OF_SLOTS: usize = 3;
-        let object = value.as_object()?;
-        if object.is_forwarded() {
-            panic!("Object is forwarded!");
-        }
+            // more than half of the array is free, the elements are moved to the front
+            collection.add_last(Immediate::new_i64(4)).unwrap();
+            assert_eq!(collection.array.len(), 4);
+            assert_eq!(items(&collection), vec![3, 4]);
+
+            collection.remove_last().unwrap();
+            collection.remove_last().unwrap();
+            assert!(collection.remove_last().unwrap().is_none());
 
-        let actual_amount_of_slots = object.amount_of_slots();
+            // the elements are moved to the back
+            collection.add_first(Immediate::new_i64(6)).unwrap();
+            collection.add_first(Immediate::new_i64(5)).unwrap();
+            assert_eq!(collection.array.len(), 4);
+            assert_eq!(items(&collection), vec![5, 6]);
+        })
+    }
 
-        if actual_amount_of_slots != EXPECTED_AMOUNT_OF_SLOTS {
-            return Err(Error::WrongAmountOfSlots {
-                object: object.header().clone(),
-                expected: EXPECTED_AMOUNT_OF_SLOTS,
-                actual: actual_amount_of_slots,
+    #[test]
+    fn remove_first_and_last() {
+        with_test_heap(|heap| {
+            let class = heap.new_class(ObjectFormat::NonIndexable, 3);
+            let mut collection = OrderedCollection::new(class).unwrap();
+            for each in 0..3 {
+                collection.add_last(Immediate::new_i64(each)).unwrap();
             }
-            .into());
-        }
 
-        Ok(Self(object))
+            let first = collection.remove_first().unwrap().unwrap();
+            let last = collection.remove_last().unwrap().unwrap();
+            assert_eq!(first.as_integer(), Some(0));
+            assert_eq!(last.as_integer(), Some(2));
+            assert_eq!(items(&collection), vec![1]);
+
+            collection.remove_all().unwrap();
+            assert!(collection.is_empty());
+            assert!(collection.remove_first().unwrap().is_none());
+            collection.add_last(Immediate::new_i64(7)).unwrap();
+            assert_eq!(items(&collection), vec![7]);
+        })
     }
-}
 
-impl From<OrderedCollectionRef> for AnyObjectRef {
-    fn from(value: OrderedCollectionRef) -> Self {
-        value.0.into()
+    #[test]
+    fn add_last_to_forwarded_array() {
+        with_test_heap(|heap| {
+            let class = heap.new_class(ObjectFormat::NonIndexable, 3);
+            let mut collection = OrderedCollection::with_capacity(class, 4).unwrap();
+            collection.add_last(Immediate::new_i64(1)).unwrap();
+
+            // as if the array was moved by become:
+            let forwarder = heap.new_forwarder(collection.array);
+            collection.this.inst_var_at_put(0, forwarder).unwrap();
+            let array = collection.this.inst_var_at(0).unwrap();
+            assert!(array.as_object().unwrap().is_forwarded());
+
+            collection.add_last(Immediate::new_i64(2)).unwrap();
+            assert_eq!(collection.len(), 2);
+            assert_eq!(collection.array.get(1).unwrap().as_integer(), Some(2));
+        })
     }
 }
@@ -0,0 +1,443 @@
+use crate::objects::{ArrayRef, SmalltalkString, StringClasses};
+use std::fmt::Write;
+use std::sync::atomic::AtomicU32;
+use vm_bindings::{ObjectPointer, Smalltalk, StackOffset};
+use vm_object_model::{
+    AnyObjectRef, ByteArrayRef, ClassRef, Immediate, ImmediateValue, ObjectFormat, ObjectRef,
+};
+
+/// The depth and width used when printing objects for debugging purposes
+pub const DEFAULT_PRINT_DEPTH: usize = 3;
+pub const DEFAULT_PRINT_WIDTH: usize = 16;
+
+const ELLIPSIS: &str = "...";
+
+/// Class indices never change, so the index of ByteArray is remembered after the first lookup
+static BYTE_ARRAY_CLASS_INDEX: AtomicU32 = AtomicU32::new(0);
+
+/// Render an object the way Smalltalk's `printString` would for the most common objects:
+/// immediates, strings, symbols, arrays, byte arrays, associations and classes.
+/// Everything else is printed as `a ClassName`.
+///
+/// Collections nested deeper than `depth` are replaced with `...`,
+/// and only the first `width` elements of a collection are printed.
+/// Objects that are already being printed (cycles) are printed as `...` as well.
+/// Does not send any messages, so it is safe to use when the image can not print anything itself.
+pub fn print_string(object: impl Into<AnyObjectRef>, depth: usize, width: usize) -> String {
+    let mut printer = Printer {
+        width,
+        visiting: vec![],
+        stream: String::new(),
+    };
+    printer.print(object.into(), depth);
+    printer.stream
+}
+
+struct Printer {
+    width: usize,
+    /// objects that are currently being printed, to detect cycles
+    visiting: Vec<ObjectRef>,
+    stream: String,
+}
+
+impl Printer {
+    fn print(&mut self, object: AnyObjectRef, depth: usize) {
+        match object.as_immediate() {
+            Ok(immediate) => self.print_immediate(immediate),
+            Err(_) => match object.as_object() {
+                Ok(object) => self.print_object(object.follow_forwarded(), depth),
+                Err(_) => self.stream.push_str(ELLIPSIS),
+            },
+        }
+    }
+
+    fn print_immediate(&mut self, immediate: Immediate) {
+        match immediate.value() {
+            Ok(ImmediateValue::SmallInteger(value)) => write!(self.stream, "{}", value).unwrap(),
+            Ok(ImmediateValue::Character(character)) => self.print_character(character),
+            Ok(ImmediateValue::SmallFloat(value)) => self.print_float(value),
+            Err(_) => write!(self.stream, "an Immediate({:#x})", immediate.0).unwrap(),
+        }
+    }
+
+    fn print_character(&mut self, character: char) {
+        if character == ' ' || !(character.is_control() || character.is_whitespace()) {
+            write!(self.stream, "${}", character).unwrap();
+        } else {
+            write!(self.stream, "(Character value: {})", character as u32).unwrap();
+        }
+    }
+
+    fn print_float(&mut self, value: f64) {
+        if value.is_nan() {
+            return self.stream.push_str("Float nan");
+        }
+        if value.is_infinite() {
+            return self.stream.push_str(if value.is_sign_positive() {
+                "Float infinity"
+            } else {
+                "Float negativeInfinity"
+            });
+        }
+
+        // the shortest representation that reads back as the same float,
+        // Smalltalk always has a fraction part in the mantissa: 1.0e20
+        let printed = format!("{:?}", value);
+        match printed.split_once('e') {
+            Some((mantissa, exponent)) if !mantissa.contains('.') => {
+                write!(self.stream, "{}.0e{}", mantissa, exponent).unwrap()
+            }
+            _ => self.stream.push_str(&printed),
+        }
+    }
+
+    fn print_object(&mut self, object: ObjectRef, depth: usize) {
+        if let Some(name) = Self::well_known_object_name(object) {
+            return self.stream.push_str(name);
+        }
+
+        if let Some(class_name) = Self::name_if_class(object) {
+            return self.stream.push_str(&class_name);
+        }
+
+        let class_name = Smalltalk::class_of(object).and_then(|class| class.name());
+        let class_name = class_name.as_deref();
+
+        if let Ok(string) = SmalltalkString::try_from(AnyObjectRef::from(object)) {
+            return if string.is_symbol() {
+                self.print_symbol(&string.to_string())
+            } else {
+                self.print_string(&string.to_string())
+            };
+        }
+
+        if Self::is_byte_array(object, class_name) {
+            return self.print_byte_array(object, depth);
+        }
+
+        if Self::is_array(object) {
+            return self.print_array(object, depth);
+        }
+
+        if class_name == Some("Association") && object.amount_of_slots() == 2 {
+            return self.print_association(object, depth);
+        }
+
+        self.print_instance_of(class_name);
+    }
+
+    fn print_instance_of(&mut self, class_name: Option<&str>) {
+        match class_name {
+            Some(class_name) => {
+                let article = match class_name.chars().next() {
+                    Some('A' | 'E' | 'I' | 'O' | 'U' | 'a' | 'e' | 'i' | 'o' | 'u') => "an",
+                    _ => "a",
+                };
+                write!(self.stream, "{} {}", article, class_name).unwrap();
+            }
+            None => self.stream.push_str("an unknown object"),
+        }
+    }
+
+    fn print_string(&mut self, string: &str) {
+        self.stream.push('\'');
+        self.stream.push_str(&string.replace('\'', "''"));
+        self.stream.push('\'');
+    }
+
+    fn print_symbol(&mut self, symbol: &str) {
+        self.stream.push('#');
+        if is_literal_symbol(symbol) {
+            self.stream.push_str(symbol);
+        } else {
+            self.print_string(symbol);
+        }
+    }
+
+    fn print_byte_array(&mut self, object: ObjectRef, depth: usize) {
+        let Ok(bytes) = ByteArrayRef::try_from(AnyObjectRef::from(object)) else {
+            return self.print_instance_of(Some("ByteArray"));
+        };
+
+        self.print_elements(
+            "#[",
+            "]",
+            bytes.as_slice().iter().copied(),
+            depth,
+            |printer, byte, _| write!(printer.stream, "{}", byte).unwrap(),
+        );
+    }
+
+    fn print_array(&mut self, object: ObjectRef, depth: usize) {
+        let Ok(array) = ArrayRef::try_from(AnyObjectRef::from(object)) else {
+            return self.print_instance_of(Some("Array"));
+        };
+
+        self.print_nested(object, depth, |printer| {
+            printer.print_elements(
+                "#(",
+                ")",
+                array.as_slice().iter().copied(),
+                depth,
+                |printer, each, depth| printer.print(each, depth - 1),
+            );
+        });
+    }
+
+    fn print_association(&mut self, object: ObjectRef, depth: usize) {
+        self.print_nested(object, depth, |printer| {
+            printer.print(object.inst_var_at(0).unwrap(), depth - 1);
+            printer.stream.push_str("->");
+            printer.print(object.inst_var_at(1).unwrap(), depth - 1);
+        });
+    }
+
+    /// Print a composite object unless it is too deep or is already being printed
+    fn print_nested(&mut self, object: ObjectRef, depth: usize, block: impl FnOnce(&mut Self)) {
+        if depth == 0 || self.visiting.contains(&object) {
+            return self.stream.push_str(ELLIPSIS);
+        }
+
+        self.visiting.push(object);
+        block(self);
+        self.visiting.pop();
+    }
+
+    fn print_elements<T>(
+        &mut self,
+        prefix: &str,
+        suffix: &str,
+        elements: impl ExactSizeIterator<Item = T>,
+        depth: usize,
+        print_element: impl Fn(&mut Self, T, usize),
+    ) {
+        if depth == 0 {
+            return self.stream.push_str(ELLIPSIS);
+        }
+
+        let length = elements.len();
+        self.stream.push_str(prefix);
+        for (index, each) in elements.take(self.width).enumerate() {
+            if index > 0 {
+                self.stream.push(' ');
+            }
+            print_element(self, each, depth);
+        }
+        if length > self.width {
+            if self.width > 0 {
+                self.stream.push(' ');
+            }
+            self.stream.push_str(ELLIPSIS);
+        }
+        self.stream.push_str(suffix);
+    }
+
+    fn well_known_object_name(object: ObjectRef) -> Option<&'static str> {
+        let is = |pointer: vm_bindings::ObjectPointer| object.as_ptr() as i64 == pointer.as_i64();
+
+        if is(Smalltalk::nil_object()) {
+            Some("nil")
+        } else if is(Smalltalk::true_object()) {
+            Some("true")
+        } else if is(Smalltalk::false_object()) {
+            Some("false")
+        } else {
+            None
+        }
+    }
+
+    /// A class is printed as its name. In Spur the identity hash of a class
+    /// is its index in the class table, which lets us tell classes apart from other objects
+    fn name_if_class(object: ObjectRef) -> Option<String> {
+        let class = ClassRef::try_from(AnyObjectRef::from(object)).ok()?;
+        let registered_class = Smalltalk::class_at_index(class.class_index())?;
+        if registered_class.as_ptr() != object.as_ptr() {
+            return None;
+        }
+        class.name()
+    }
+
+    fn is_array(object: ObjectRef) -> bool {
+        object.object_format() == ObjectFormat::IndexableWithoutInstVars
+            && object.header().class_index() == Smalltalk::class_array().header().identity_hash()
+    }
+
+    /// Only instances of ByteArray itself, other classes of bytes are printed as instances
+    fn is_byte_array(object: ObjectRef, class_name: Option<&str>) -> bool {
+        class_name == Some("ByteArray")
+            && Smalltalk::class_named_at("ByteArray", &BYTE_ARRAY_CLASS_INDEX)
+                .is_some_and(|class| class.class_index() == object.header().class_index())
+    }
+}
+
+/// Return true if a symbol can be printed without quotes: `#foo`, `#at:put:` or `#+`
+fn is_literal_symbol(symbol: &str) -> bool {
+    const BINARY_CHARACTERS: &str = "~!@%&*-+=|\\<>,?/";
+
+    fn is_identifier(string: &str) -> bool {
+        let mut characters = string.chars();
+        matches!(characters.next(), Some(first) if first.is_ascii_alphabetic() || first == '_')
+            && characters.all(|each| each.is_ascii_alphanumeric() || each == '_')
+    }
+
+    if symbol.is_empty() {
+        return false;
+    }
+
+    if symbol.chars().all(|each| BINARY_CHARACTERS.contains(each)) {
+        return true;
+    }
+
+    match symbol.strip_suffix(':') {
+        Some(keywords) => keywords.split(':').all(is_identifier),
+        None => is_identifier(symbol),
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitivePrintString() {
+    let object = Smalltalk::stack_ref(StackOffset::new(2));
+    let depth = Smalltalk::stack_ref(StackOffset::new(1)).as_integer();
+    let width = Smalltalk::stack_ref(StackOffset::new(0)).as_integer();
+
+    match (depth, width) {
+        (Some(depth), Some(width)) if depth >= 0 && width >= 0 => {
+            let printed = print_string(object, depth as usize, width as usize);
+            match StringClasses::of_image().and_then(|classes| classes.new_string(&printed)) {
+                Ok(string) => Smalltalk::method_return_value(ObjectPointer::from(string.as_i64())),
+                Err(error) => {
+                    error!("Failed to allocate the printed string: {}", error);
+                    Smalltalk::primitive_fail();
+                }
+            }
+        }
+        _ => {
+            error!("Depth and width must be non-negative SmallIntegers");
+            Smalltalk::primitive_fail();
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveLogPrintString() {
+    let object = Smalltalk::stack_ref(StackOffset::new(0));
+    info!(
+        "{}",
+        print_string(object, DEFAULT_PRINT_DEPTH, DEFAULT_PRINT_WIDTH)
+    );
+    Smalltalk::method_return_boolean(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{byte_string_class, byte_symbol_class, with_test_heap};
+    use vm_object_model::TestHeap;
+
+    fn print(object: impl Into<AnyObjectRef>) -> String {
+        print_string(object, DEFAULT_PRINT_DEPTH, DEFAULT_PRINT_WIDTH)
+    }
+
+    fn new_named_object(heap: &TestHeap, class_name: &str, fields: &[AnyObjectRef]) -> ObjectRef {
+        let names = (0..fields.len())
+            .map(|index| format!("field{}", index))
+            .collect::<Vec<_>>();
+        let names = names.iter().map(|each| each.as_str()).collect::<Vec<_>>();
+        let class = heap.new_named_class(class_name, None, ObjectFormat::NonIndexable, &names);
+        heap.new_object(class, fields)
+    }
+
+    #[test]
+    fn print_immediates() {
+        with_test_heap(|heap| {
+            assert_eq!(print(Immediate::new_i64(-42)), "-42");
+            assert_eq!(print(Immediate::new_character('a')), "$a");
+            assert_eq!(
+                print(Immediate::new_character('\n')),
+                "(Character value: 10)"
+            );
+            assert_eq!(print(Immediate::try_new_float(2.5).unwrap()), "2.5");
+            assert_eq!(print(Immediate::try_new_float(1e20).unwrap()), "1.0e20");
+            assert_eq!(print(heap.nil()), "nil");
+            assert_eq!(print(heap.true_object()), "true");
+            assert_eq!(print(heap.false_object()), "false");
+        })
+    }
+
+    #[test]
+    fn print_strings_and_symbols() {
+        with_test_heap(|heap| {
+            let string_class = byte_string_class(heap);
+            let symbol_class = byte_symbol_class(heap);
+
+            assert_eq!(print(heap.new_bytes(string_class, b"it's")), "'it''s'");
+            assert_eq!(print(heap.new_bytes(symbol_class, b"at:put:")), "#at:put:");
+            assert_eq!(print(heap.new_bytes(symbol_class, b"+")), "#+");
+            assert_eq!(
+                print(heap.new_bytes(symbol_class, b"hello world")),
+                "#'hello world'"
+            );
+
+            let like_a_string_class =
+                heap.new_named_class("FooString", None, ObjectFormat::Indexable8(16), &[]);
+            assert_eq!(
+                print(heap.new_bytes(like_a_string_class, b"foo")),
+                "a FooString"
+            );
+
+            let byte_array_class =
+                heap.new_named_class("ByteArray", None, ObjectFormat::Indexable8(16), &[]);
+            assert_eq!(print(heap.new_bytes(byte_array_class, &[1, 2])), "#[1 2]");
+        })
+    }
+
+    #[test]
+    fn print_arrays_and_associations() {
+        with_test_heap(|heap| {
+            let inner = heap.new_array(&[Immediate::new_i64(2).into(), heap.true_object().into()]);
+            let array = heap.new_array(&[
+                Immediate::new_i64(1).into(),
+                inner.into(),
+                Immediate::new_character('c').into(),
+            ]);
+            assert_eq!(print(array), "#(1 #(2 true) $c)");
+            assert_eq!(print_string(array, 1, 10), "#(1 ... $c)");
+            assert_eq!(print_string(array, 2, 2), "#(1 #(2 true) ...)");
+
+            let association = new_named_object(
+                heap,
+                "Association",
+                &[Immediate::new_i64(1).into(), array.into()],
+            );
+            assert_eq!(print(association), "1->#(1 #(2 true) $c)");
+        })
+    }
+
+    #[test]
+    fn print_cycles() {
+        with_test_heap(|heap| {
+            let mut array = heap.new_array(&[Immediate::new_i64(1).into(), heap.nil().into()]);
+            let itself = array;
+            array.inst_var_at_put(1, itself).unwrap();
+            assert_eq!(print(array), "#(1 ...)");
+        })
+    }
+
+    #[test]
+    fn print_instances_and_classes() {
+        with_test_heap(|heap| {
+            let object = new_named_object(heap, "Object", &[]);
+            let point = new_named_object(heap, "Point", &[heap.nil().into(), heap.nil().into()]);
+            let anonymous = heap.instantiate(heap.new_class(ObjectFormat::NonIndexable, 0));
+
+            assert_eq!(print(object), "an Object");
+            assert_eq!(print(point), "a Point");
+            assert_eq!(print(anonymous), "an unknown object");
+
+            let point_class = Smalltalk::class_of(point).unwrap();
+            assert_eq!(print(point_class), "Point");
+        })
+    }
+}
@@ -0,0 +1,397 @@
+use crate::objects::{is_nil, ArrayRef, ContextRef, SemaphoreRef, SmalltalkString, StringClasses};
+use std::fmt::{Debug, Formatter, Write};
+use std::ops::{Deref, DerefMut};
+use vm_bindings::{ObjectPointer, Smalltalk, StackOffset};
+use vm_object_model::{
+    AnyObjectRef, Error, Object, ObjectFormat, ObjectRef, RawObjectPointer, Result,
+};
+
+/// A Pharo Process. Processes are links of the list they wait in:
+/// a run queue of the scheduler while runnable or a Semaphore while blocked.
+/// Subclasses and newer images may define more instance variables after the ones we read
+#[repr(C)]
+pub struct Process {
+    this: Object,
+    next_link: AnyObjectRef,
+    suspended_context: AnyObjectRef,
+    priority: AnyObjectRef,
+    my_list: AnyObjectRef,
+    name: AnyObjectRef,
+    env: AnyObjectRef,
+    effective_process: AnyObjectRef,
+}
+
+impl Process {
+    pub const AMOUNT_OF_SLOTS: usize = 7;
+
+    /// Return the next process in the list the process waits in
+    pub fn next_link(&self) -> Option<ProcessRef> {
+        ProcessRef::try_from(self.next_link).ok()
+    }
+
+    /// Return the context the process resumes from,
+    /// or None if it is the active process or was terminated
+    pub fn suspended_context(&self) -> Option<ContextRef> {
+        ContextRef::try_from(self.suspended_context).ok()
+    }
+
+    pub fn priority(&self) -> Option<i64> {
+        self.priority.as_integer()
+    }
+
+    /// Return the list the process is waiting in, if any
+    pub fn my_list(&self) -> Option<AnyObjectRef> {
+        Some(self.my_list).filter(|list| !is_nil(*list))
+    }
+
+    /// Return the semaphore the process is blocked on
+    pub fn waiting_on(&self) -> Option<SemaphoreRef> {
+        SemaphoreRef::try_from(self.my_list()?).ok()
+    }
+
+    pub fn name(&self) -> Option<String> {
+        SmalltalkString::try_from(self.name)
+            .ok()
+            .map(|name| name.to_string())
+    }
+
+    /// Return the process on whose behalf this one runs code (see `Process>>#evaluate:onBehalfOf:`),
+    /// which is the process itself unless it is a debugger stepping through another one
+    pub fn effective_process(&self) -> ProcessRef {
+        ProcessRef::try_from(self.effective_process)
+            .unwrap_or_else(|_| ProcessRef(ObjectRef::from(&self.this)))
+    }
+
+    /// Return a description of the process followed by its stack, one context per line.
+    /// Contexts married to a frame are read through the frame, which may move objects,
+    /// including the process itself
+    pub fn stack_description(&self) -> String {
+        let mut description = format!(
+            "Process {} (priority {})\n",
+            self.name().as_deref().unwrap_or("<unnamed>"),
+            self.priority()
+                .map(|priority| priority.to_string())
+                .unwrap_or_else(|| "?".to_string())
+        );
+
+        match self.suspended_context() {
+            Some(context) => {
+                for each in context.stack_frames() {
+                    writeln!(description, "  {}", *each).unwrap();
+                }
+            }
+            None => writeln!(description, "  <active or terminated>").unwrap(),
+        }
+        description
+    }
+}
+
+impl Deref for Process {
+    type Target = Object;
+
+    fn deref(&self) -> &Self::Target {
+        &self.this
+    }
+}
+
+impl Debug for Process {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Process")
+            .field("name", &self.name())
+            .field("priority", &self.priority())
+            .field("suspended_context", &self.suspended_context())
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct ProcessRef(ObjectRef);
+
+impl Deref for ProcessRef {
+    type Target = Process;
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.0.cast() }
+    }
+}
+
+impl DerefMut for ProcessRef {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.0.cast_mut() }
+    }
+}
+
+impl TryFrom<AnyObjectRef> for ProcessRef {
+    type Error = Error;
+
+    fn try_from(value: AnyObjectRef) -> Result<Self> {
+        let object_ref = value.as_object()?.follow_forwarded();
+        match object_ref.object_format() {
+            ObjectFormat::NonIndexable
+                if object_ref.amount_of_slots() >= Process::AMOUNT_OF_SLOTS =>
+            {
+                Ok(ProcessRef(object_ref))
+            }
+            _ => Err(Error::InvalidType("Process".to_string())),
+        }
+    }
+}
+
+impl From<ProcessRef> for AnyObjectRef {
+    fn from(value: ProcessRef) -> Self {
+        value.0.into()
+    }
+}
+
+/// The ProcessorScheduler (`Processor`): a run queue per priority and the active process
+#[repr(C)]
+pub struct ProcessorScheduler {
+    this: Object,
+    quiescent_process_lists: AnyObjectRef,
+    active_process: AnyObjectRef,
+}
+
+impl ProcessorScheduler {
+    pub const AMOUNT_OF_SLOTS: usize = 2;
+    /// The slot of a LinkedList that holds its first link
+    const FIRST_LINK_INDEX: usize = 0;
+
+    pub fn active_process(&self) -> Option<ProcessRef> {
+        ProcessRef::try_from(self.active_process).ok()
+    }
+
+    /// Return the amount of priorities, the lowest one being 1
+    pub fn amount_of_priorities(&self) -> usize {
+        ArrayRef::try_from(self.quiescent_process_lists)
+            .map(|lists| lists.len())
+            .unwrap_or(0)
+    }
+
+    /// Return the runnable processes of a priority in the order they will be resumed
+    pub fn runnable_processes(&self, priority: usize) -> impl Iterator<Item = ProcessRef> {
+        let first_link = ArrayRef::try_from(self.quiescent_process_lists)
+            .ok()
+            .and_then(|lists| lists.get(priority.checked_sub(1)?))
+            .and_then(|list| list.as_object().ok()?.inst_var_at(Self::FIRST_LINK_INDEX));
+
+        std::iter::successors(
+            first_link.and_then(|link| ProcessRef::try_from(link).ok()),
+            |process| process.next_link(),
+        )
+    }
+}
+
+impl Deref for ProcessorScheduler {
+    type Target = Object;
+
+    fn deref(&self) -> &Self::Target {
+        &self.this
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct ProcessorSchedulerRef(ObjectRef);
+
+impl ProcessorSchedulerRef {
+    /// Return the scheduler of the running image
+    pub fn of_image() -> Option<Self> {
+        let scheduler = Smalltalk::scheduler();
+        Self::try_from(AnyObjectRef::from(RawObjectPointer::from(
+            scheduler.as_i64(),
+        )))
+        .ok()
+    }
+}
+
+impl Deref for ProcessorSchedulerRef {
+    type Target = ProcessorScheduler;
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.0.cast() }
+    }
+}
+
+impl TryFrom<AnyObjectRef> for ProcessorSchedulerRef {
+    type Error = Error;
+
+    fn try_from(value: AnyObjectRef) -> Result<Self> {
+        let object_ref = value.as_object()?.follow_forwarded();
+        match object_ref.object_format() {
+            ObjectFormat::NonIndexable
+                if object_ref.amount_of_slots() >= ProcessorScheduler::AMOUNT_OF_SLOTS =>
+            {
+                Ok(ProcessorSchedulerRef(object_ref))
+            }
+            _ => Err(Error::InvalidType("ProcessorScheduler".to_string())),
+        }
+    }
+}
+
+impl From<ProcessorSchedulerRef> for AnyObjectRef {
+    fn from(value: ProcessorSchedulerRef) -> Self {
+        value.0.into()
+    }
+}
+
+/// Return the stacks of the active process and of the runnable processes from the highest priority,
+/// walking the run queues (`quiescentProcessLists`) of the scheduler.
+/// Processes blocked on a semaphore are not in a run queue and are not included.
+/// Walking a stack may move objects, so the scheduler and the next process are looked up again after each one
+pub fn processes_stack_description(
+    scheduler: impl Fn() -> Option<ProcessorSchedulerRef>,
+) -> String {
+    let mut description = String::new();
+    if let Some(active_process) = scheduler().and_then(|scheduler| scheduler.active_process()) {
+        description.push_str(&active_process.stack_description());
+    }
+
+    let amount_of_priorities = scheduler()
+        .map(|scheduler| scheduler.amount_of_priorities())
+        .unwrap_or(0);
+    for priority in (1..=amount_of_priorities).rev() {
+        for index in 0.. {
+            let process =
+                scheduler().and_then(|scheduler| scheduler.runnable_processes(priority).nth(index));
+            match process {
+                Some(process) => description.push_str(&process.stack_description()),
+                None => break,
+            }
+        }
+    }
+    description
+}
+
+fn return_string(string: &str) {
+    match StringClasses::of_image().and_then(|classes| classes.new_string(string)) {
+        Ok(string) => Smalltalk::method_return_value(ObjectPointer::from(string.as_i64())),
+        Err(error) => {
+            error!("Failed to allocate the stack description: {}", error);
+            Smalltalk::primitive_fail();
+        }
+    }
+}
+
+/// Return the stack of a suspended process as a string, read without running any Smalltalk code
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveProcessStackDescription() {
+    let process = Smalltalk::stack_ref(StackOffset::new(0));
+    match ProcessRef::try_from(process) {
+        Ok(process) => return_string(&process.stack_description()),
+        Err(error) => {
+            error!("{}", error);
+            Smalltalk::primitive_fail();
+        }
+    }
+}
+
+/// Return the stacks of the active and the runnable processes as a string,
+/// read without running any Smalltalk code
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveAllProcessesStackDescription() {
+    return_string(&processes_stack_description(
+        ProcessorSchedulerRef::of_image,
+    ));
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::objects::context::tests::{new_context, new_method_of};
+    use crate::objects::{byte_string_class, with_test_heap};
+    use vm_object_model::{Immediate, TestHeap};
+
+    /// Allocate a process with the instance variables of Pharo 12
+    pub(crate) fn new_process(
+        heap: &TestHeap,
+        name: &str,
+        suspended_context: AnyObjectRef,
+    ) -> ProcessRef {
+        let string_class = byte_string_class(heap);
+        let process_class = heap.new_class(ObjectFormat::NonIndexable, 9);
+        let process = heap.new_object(
+            process_class,
+            &[
+                heap.nil().into(),
+                suspended_context,
+                Immediate::new_i64(40).into(),
+                heap.nil().into(),
+                heap.new_bytes(string_class, name.as_bytes()).into(),
+                heap.nil().into(),
+                heap.nil().into(),
+                heap.false_object().into(),
+                Immediate::new_i64(0).into(),
+            ],
+        );
+        ProcessRef::try_from(AnyObjectRef::from(process)).unwrap()
+    }
+
+    #[test]
+    fn describe_suspended_process() {
+        with_test_heap(|heap| {
+            let context = new_context(
+                heap,
+                heap.nil().into(),
+                new_method_of(heap, "Delay", "wait"),
+                heap.nil().into(),
+                20,
+                &[],
+            );
+            let process = new_process(heap, "idle", context.into());
+
+            assert_eq!(process.name().as_deref(), Some("idle"));
+            assert_eq!(process.priority(), Some(40));
+            assert!(process.my_list().is_none());
+            assert!(process.waiting_on().is_none());
+            assert!(AnyObjectRef::from(process.effective_process())
+                .equals(&process.into())
+                .unwrap());
+            assert_eq!(
+                process.stack_description(),
+                "Process idle (priority 40)\n  Delay>>wait\n"
+            );
+        })
+    }
+
+    #[test]
+    fn describe_runnable_processes() {
+        with_test_heap(|heap| {
+            let suspended_process = |name, selector| {
+                let method = new_method_of(heap, "Object", selector);
+                let context =
+                    new_context(heap, heap.nil().into(), method, heap.nil().into(), 20, &[]);
+                new_process(heap, name, context.into())
+            };
+            let first = suspended_process("first", "yield");
+            let second = suspended_process("second", "wait");
+            let low = suspended_process("low", "idle");
+            let active = new_process(heap, "active", heap.nil().into());
+            AnyObjectRef::from(first)
+                .as_object()
+                .unwrap()
+                .inst_var_at_put_unchecked(0, second);
+
+            let list_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+            let empty_list = heap.new_object(list_class, &[heap.nil().into(), heap.nil().into()]);
+            let high_list = heap.new_object(list_class, &[first.into(), second.into()]);
+            let low_list = heap.new_object(list_class, &[low.into(), low.into()]);
+            let lists = heap.new_array(&[low_list.into(), empty_list.into(), high_list.into()]);
+
+            let scheduler_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+            let scheduler = heap.new_object(scheduler_class, &[lists.into(), active.into()]);
+            let scheduler = ProcessorSchedulerRef::try_from(AnyObjectRef::from(scheduler)).unwrap();
+
+            assert_eq!(scheduler.amount_of_priorities(), 3);
+            assert_eq!(scheduler.runnable_processes(3).count(), 2);
+            assert_eq!(scheduler.runnable_processes(2).count(), 0);
+            assert_eq!(
+                processes_stack_description(|| Some(scheduler)),
+                "Process active (priority 40)\n  <active or terminated>\n\
+                 Process first (priority 40)\n  Object>>yield\n\
+                 Process second (priority 40)\n  Object>>wait\n\
+                 Process low (priority 40)\n  Object>>idle\n"
+            );
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use crate::objects::ProcessRef;
+use vm_object_model::{AnyObjectRef, Immediate, Object};
+use vm_object_model_derive::PharoObject;
+
+/// A Pharo Semaphore: a linked list of the processes waiting on it
+/// and the amount of signals that no process has consumed yet
+#[derive(Debug, PharoObject)]
+#[repr(C)]
+pub struct Semaphore {
+    this: Object,
+    #[pharo(no_setter)]
+    first_link: AnyObjectRef,
+    #[pharo(no_setter)]
+    last_link: AnyObjectRef,
+    #[pharo(no_setter)]
+    excess_signals: Immediate,
+}
+
+impl Semaphore {
+    pub fn excess_signals(&self) -> usize {
+        self.excess_signals.as_integer().unwrap_or(0).max(0) as usize
+    }
+
+    /// Iterate over the waiting processes in the order they will be resumed
+    pub fn waiting_processes(&self) -> impl Iterator<Item = ProcessRef> {
+        std::iter::successors(ProcessRef::try_from(self.first_link).ok(), |process| {
+            process.next_link()
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting_processes().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::process::tests::new_process;
+    use crate::objects::with_test_heap;
+    use vm_object_model::ObjectFormat;
+
+    #[test]
+    fn waiting_processes() {
+        with_test_heap(|heap| {
+            let semaphore_class = heap.new_class(ObjectFormat::NonIndexable, 3);
+            let first = new_process(heap, "first", heap.nil().into());
+            let second = new_process(heap, "second", heap.nil().into());
+
+            let semaphore = heap.new_object(
+                semaphore_class,
+                &[first.into(), second.into(), Immediate::new_i64(0).into()],
+            );
+            let mut first_object = AnyObjectRef::from(first).as_object().unwrap();
+            first_object.inst_var_at_put_unchecked(0, second);
+            first_object.inst_var_at_put_unchecked(3, semaphore);
+            let mut second_object = AnyObjectRef::from(second).as_object().unwrap();
+            second_object.inst_var_at_put_unchecked(3, semaphore);
+
+            let semaphore = SemaphoreRef::try_from(AnyObjectRef::from(semaphore)).unwrap();
+            let names = semaphore
+                .waiting_processes()
+                .filter_map(|process| process.name())
+                .collect::<Vec<_>>();
+            assert_eq!(names, vec!["first", "second"]);
+            assert_eq!(semaphore.excess_signals(), 0);
+            assert!(!semaphore.is_empty());
+            assert!(second.waiting_on().is_some());
+            assert!(second.next_link().is_none());
+        })
+    }
+}
@@ -0,0 +1,126 @@
+use crate::objects::{
+    equality_hash, good_prime_at_least, is_equal, is_nil, scan_for_equal, Array, ArrayRef,
+};
+use vm_bindings::Smalltalk;
+use vm_object_model::{AnyObjectRef, Immediate, Object, ObjectRef, Result};
+use vm_object_model_derive::PharoObject;
+
+/// A Pharo Set: a hashed collection of elements that are compared with `=` and `hash`.
+/// The elements are stored directly in the array, where empty slots hold nil
+#[derive(Debug, PharoObject)]
+#[repr(C)]
+pub struct Set {
+    this: Object,
+    tally: Immediate,
+    array: ArrayRef,
+}
+
+impl Set {
+    /// Create a set that can hold a given amount of elements without growing,
+    /// sized the same way as `HashedCollection class>>#sizeFor:`
+    pub fn with_capacity(set_class: ObjectRef, capacity: usize) -> Result<SetRef> {
+        let mut set = Smalltalk::instantiate::<SetRef>(set_class)?;
+        set.set_array(Array::new(good_prime_at_least((capacity * 4).div_ceil(3)))?)?;
+        set.set_tally(Immediate::new_i64(0))?;
+        Ok(set)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tally.as_integer().unwrap() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return true if the set includes an element equal to a given object
+    pub fn includes(&self, element: impl Into<AnyObjectRef>) -> bool {
+        self.scan_for(element.into())
+            .is_some_and(|index| !is_nil(self.array[index]))
+    }
+
+    /// Add an element unless the set already includes an equal one
+    pub fn add(&mut self, element: impl Into<AnyObjectRef>) -> Result<()> {
+        let element = element.into().follow_forwarded();
+        let index = match self.scan_for(element) {
+            Some(index) => index,
+            None => {
+                self.grow()?;
+                self.scan_for(element)
+                    .expect("Grown set must have empty space")
+            }
+        };
+        if !is_nil(self.array[index]) {
+            return Ok(());
+        }
+
+        self.array.insert(index, element)?;
+        self.set_tally(Immediate::new_i64(self.len() as i64 + 1))?;
+        self.full_check()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = AnyObjectRef> + '_ {
+        self.array.iter().copied().filter(|each| !is_nil(*each))
+    }
+
+    /// Return the index of an element equal to a given one or of the empty slot where it would be,
+    /// probing like `Set>>#scanFor:`. Return None if the set is full and does not include the element
+    pub fn scan_for(&self, element: AnyObjectRef) -> Option<usize> {
+        scan_for_equal(self.array.len(), equality_hash(element), |index| {
+            let each = self.array[index];
+            is_nil(each) || is_equal(each, element)
+        })
+    }
+
+    fn full_check(&mut self) -> Result<()> {
+        let available_space = self.array.len() - self.len();
+        if available_space < (self.array.len() / 4).max(1) {
+            self.grow()?;
+        }
+        Ok(())
+    }
+
+    /// Grow the array and rehash the elements, like `HashedCollection>>#grow`
+    fn grow(&mut self) -> Result<()> {
+        let old_elements = self.array;
+        let capacity = old_elements.len();
+        self.set_array(Array::new(good_prime_at_least(capacity + capacity.max(2)))?)?;
+
+        for each in old_elements.iter().filter(|each| !is_nil(**each)) {
+            let index = self
+                .scan_for(*each)
+                .expect("Grown set must have empty space");
+            self.array.insert(index, *each)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{byte_string_class, with_test_heap};
+    use vm_object_model::ObjectFormat;
+
+    #[test]
+    fn add_equal_elements() {
+        with_test_heap(|heap| {
+            let set_class = heap.new_class(ObjectFormat::NonIndexable, 2);
+            let string_class = byte_string_class(heap);
+
+            let mut set = Set::with_capacity(set_class, 2).unwrap();
+            for each in ["a", "b", "a", "c", "d", "b", "e", "f"] {
+                set.add(heap.new_bytes(string_class, each.as_bytes()))
+                    .unwrap();
+            }
+            set.add(Immediate::new_character('a')).unwrap();
+            set.add(Immediate::new_character('a')).unwrap();
+
+            assert_eq!(set.len(), 7);
+            assert_eq!(set.iter().count(), 7);
+            assert!(set.includes(heap.new_bytes(string_class, b"f")));
+            assert!(set.includes(Immediate::new_character('a')));
+            assert!(!set.includes(heap.new_bytes(string_class, b"g")));
+        })
+    }
+}
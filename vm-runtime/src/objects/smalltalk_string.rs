@@ -0,0 +1,159 @@
+use std::fmt::{Display, Formatter};
+use vm_object_model::{AnyObjectRef, ByteStringRef, Error, Result, WideStringRef};
+
+// strings are allocated by `vm_bindings`, so that converted Rust strings are allocated the same way
+pub use vm_bindings::{is_latin1, new_byte_string, new_wide_string, StringClasses, StringKind};
+
+/// A string of the image: a ByteString or a WideString, or one of the symbol variants.
+/// ByteStrings are Latin-1 encoded, while WideStrings hold UTF-32 code points.
+/// The format does not tell a string from a ByteArray or a WordArray,
+/// so the kind is decided by the class, see `StringKind`
+#[derive(Debug, Copy, Clone)]
+pub enum SmalltalkString {
+    ByteString(ByteStringRef),
+    WideString(WideStringRef),
+    ByteSymbol(ByteStringRef),
+    WideSymbol(WideStringRef),
+}
+
+impl SmalltalkString {
+    pub fn is_symbol(&self) -> bool {
+        matches!(self, Self::ByteSymbol(_) | Self::WideSymbol(_))
+    }
+
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Self::WideString(_) | Self::WideSymbol(_))
+    }
+
+    /// Return the amount of characters
+    pub fn len(&self) -> usize {
+        match self {
+            Self::ByteString(string) | Self::ByteSymbol(string) => string.len(),
+            Self::WideString(string) | Self::WideSymbol(string) => string.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Characters of the string, invalid code points of a wide string are replaced with `char::REPLACEMENT_CHARACTER`
+    pub fn chars(&self) -> Box<dyn Iterator<Item = char> + '_> {
+        match self {
+            Self::ByteString(string) | Self::ByteSymbol(string) => Box::new(string.chars()),
+            Self::WideString(string) | Self::WideSymbol(string) => Box::new(string.chars_lossy()),
+        }
+    }
+}
+
+impl TryFrom<AnyObjectRef> for SmalltalkString {
+    type Error = Error;
+
+    fn try_from(value: AnyObjectRef) -> Result<Self> {
+        let object = AnyObjectRef::from(value.as_object()?.follow_forwarded());
+
+        let string = match StringKind::of(object) {
+            Some(StringKind::ByteString) => Self::ByteString(ByteStringRef::try_from(object)?),
+            Some(StringKind::WideString) => Self::WideString(WideStringRef::try_from(object)?),
+            Some(StringKind::ByteSymbol) => Self::ByteSymbol(ByteStringRef::try_from(object)?),
+            Some(StringKind::WideSymbol) => Self::WideSymbol(WideStringRef::try_from(object)?),
+            None => return Err(Error::InvalidType("String".to_string())),
+        };
+        Ok(string)
+    }
+}
+
+impl From<SmalltalkString> for AnyObjectRef {
+    fn from(value: SmalltalkString) -> Self {
+        match value {
+            SmalltalkString::ByteString(string) | SmalltalkString::ByteSymbol(string) => {
+                string.into()
+            }
+            SmalltalkString::WideString(string) | SmalltalkString::WideSymbol(string) => {
+                string.into()
+            }
+        }
+    }
+}
+
+impl Display for SmalltalkString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ByteString(string) | Self::ByteSymbol(string) => string.fmt(f),
+            Self::WideString(string) | Self::WideSymbol(string) => string.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{byte_string_class, byte_symbol_class, wide_string_class, with_test_heap};
+    use vm_object_model::{ObjectFormat, ObjectRef, TestHeap};
+
+    fn string_classes(heap: &TestHeap) -> StringClasses {
+        StringClasses {
+            byte_string: byte_string_class(heap),
+            wide_string: wide_string_class(heap),
+        }
+    }
+
+    #[test]
+    fn latin1_and_wide_round_trip() {
+        with_test_heap(|heap| {
+            let classes = string_classes(heap);
+
+            let byte_string =
+                SmalltalkString::try_from(classes.new_string("café").unwrap()).unwrap();
+            assert!(matches!(byte_string, SmalltalkString::ByteString(_)));
+            assert_eq!(byte_string.len(), 4);
+            assert_eq!(byte_string.to_string(), "café");
+
+            let wide_string =
+                SmalltalkString::try_from(classes.new_string("λ😀").unwrap()).unwrap();
+            assert!(matches!(wide_string, SmalltalkString::WideString(_)));
+            assert_eq!(wide_string.len(), 2);
+            assert_eq!(wide_string.to_string(), "λ😀");
+
+            assert!(new_byte_string(classes.byte_string, "λ").is_err());
+        })
+    }
+
+    #[test]
+    fn find_string_classes_of_image() {
+        with_test_heap(|heap| {
+            assert!(StringClasses::of_image().is_err());
+
+            let classes = string_classes(heap);
+            let found = StringClasses::of_image().unwrap();
+            let address = |class: ObjectRef| AnyObjectRef::from(class).as_i64();
+            assert_eq!(address(found.byte_string), address(classes.byte_string));
+            assert_eq!(address(found.wide_string), address(classes.wide_string));
+        })
+    }
+
+    #[test]
+    fn symbols_and_other_bytes() {
+        with_test_heap(|heap| {
+            let symbol_class = byte_symbol_class(heap);
+            let byte_array_class =
+                heap.new_named_class("ByteArray", None, ObjectFormat::Indexable8(16), &[]);
+
+            let symbol = SmalltalkString::try_from(AnyObjectRef::from(
+                heap.new_bytes(symbol_class, &[b'n', 0xE9]),
+            ))
+            .unwrap();
+            assert!(symbol.is_symbol());
+            assert!(!symbol.is_wide());
+            assert_eq!(symbol.to_string(), "né");
+
+            let byte_array = heap.new_bytes(byte_array_class, b"bytes");
+            assert!(SmalltalkString::try_from(AnyObjectRef::from(byte_array)).is_err());
+
+            let like_a_string_class =
+                heap.new_named_class("FooString", None, ObjectFormat::Indexable8(16), &[]);
+            let like_a_string = heap.new_bytes(like_a_string_class, b"foo");
+            assert!(SmalltalkString::try_from(AnyObjectRef::from(like_a_string)).is_err());
+        })
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt::Debug;
+use std::ops::{Deref, DerefMut};
+use vm_bindings::{ObjectPointer, Smalltalk};
+use vm_object_model::{AnyObjectRef, Error, Object, ObjectFormat, ObjectRef, Result};
+
+/// A weak indexable object (WeakArray et al).
+/// The fixed fields are strong references, while the indexable fields are weak:
+/// once an element is no longer strongly referenced, the garbage collector replaces it with nil.
+/// It means that an element may turn into nil between any two allocations.
+#[repr(C)]
+pub struct WeakArray {
+    this: Object,
+}
+
+impl WeakArray {
+    pub fn new(weak_array_class: ObjectRef, size: usize) -> Result<WeakArrayRef> {
+        Smalltalk::instantiate_indexable(weak_array_class, size)
+    }
+
+    /// Return the amount of strong fixed fields, as defined by the class
+    pub fn amount_of_fixed_fields(&self) -> usize {
+        Smalltalk::class_of(ObjectRef::from(&self.this))
+            .and_then(|class| class.amount_of_fixed_slots().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn fixed_field(&self, index: usize) -> Option<AnyObjectRef> {
+        if index < self.amount_of_fixed_fields() {
+            self.this.inst_var_at(index)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_fixed_field(&mut self, index: usize, object: impl Into<AnyObjectRef>) -> Result<()> {
+        assert!(
+            index < self.amount_of_fixed_fields(),
+            "Fixed field index {} is out of bounds",
+            index
+        );
+        self.store(index, object.into())
+    }
+
+    /// Return the amount of weak indexable fields
+    pub fn len(&self) -> usize {
+        self.this.amount_of_slots() - self.amount_of_fixed_fields()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the object at a given index of the weak part, which is nil if it was collected
+    pub fn get(&self, index: usize) -> Option<AnyObjectRef> {
+        if index < self.len() {
+            self.this.inst_var_at(self.amount_of_fixed_fields() + index)
+        } else {
+            None
+        }
+    }
+
+    /// Return the object at a given index unless it is nil (was never set or was collected)
+    pub fn get_alive(&self, index: usize) -> Option<AnyObjectRef> {
+        self.get(index).filter(|each| !is_nil(*each))
+    }
+
+    pub fn insert(&mut self, index: usize, object: impl Into<AnyObjectRef>) -> Result<()> {
+        assert!(index < self.len(), "Index {} is out of bounds", index);
+        self.store(self.amount_of_fixed_fields() + index, object.into())
+    }
+
+    /// Iterate over the weak part, including nils
+    pub fn iter(&self) -> impl Iterator<Item = AnyObjectRef> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+
+    /// Iterate over the objects of the weak part that are still alive
+    pub fn alive(&self) -> impl Iterator<Item = AnyObjectRef> + '_ {
+        self.iter().filter(|each| !is_nil(*each))
+    }
+
+    fn store(&mut self, slot_index: usize, object: AnyObjectRef) -> Result<()> {
+        self.this.check_mutable()?;
+        Smalltalk::prepare_to_store(
+            ObjectPointer::from(self.this.as_ptr()),
+            ObjectPointer::from(object.as_i64()),
+        );
+        self.this.inst_var_at_put(slot_index, object)
+    }
+}
+
+/// Return true if an object is nil, which is how weak slots of collected objects read
+pub(crate) fn is_nil(object: AnyObjectRef) -> bool {
+    object.as_i64() == Smalltalk::nil_object().as_i64()
+}
+
+impl Deref for WeakArray {
+    type Target = Object;
+
+    fn deref(&self) -> &Self::Target {
+        &self.this
+    }
+}
+
+impl Debug for WeakArray {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeakArray")
+            .field("header", &self.this)
+            .field("items", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct WeakArrayRef(ObjectRef);
+
+impl Deref for WeakArrayRef {
+    type Target = WeakArray;
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.0.cast() }
+    }
+}
+
+impl DerefMut for WeakArrayRef {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.0.cast_mut() }
+    }
+}
+
+impl TryFrom<AnyObjectRef> for WeakArrayRef {
+    type Error = Error;
+
+    fn try_from(value: AnyObjectRef) -> Result<Self> {
+        let object_ref = value.as_object()?.follow_forwarded();
+        match object_ref.object_format() {
+            ObjectFormat::WeakIndexable => Ok(WeakArrayRef(object_ref)),
+            _ => Err(Error::InvalidType("WeakArray".to_string())),
+        }
+    }
+}
+
+impl From<WeakArrayRef> for AnyObjectRef {
+    fn from(value: WeakArrayRef) -> Self {
+        value.0.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::with_test_heap;
+    use vm_object_model::Immediate;
+
+    #[test]
+    fn fixed_and_weak_fields() {
+        with_test_heap(|heap| {
+            let class = heap.new_class(ObjectFormat::WeakIndexable, 1);
+            let mut array = WeakArray::new(class, 3).unwrap();
+            assert_eq!(array.amount_of_fixed_fields(), 1);
+            assert_eq!(array.len(), 3);
+
+            array.set_fixed_field(0, Immediate::new_i64(7)).unwrap();
+            array.insert(1, heap.true_object()).unwrap();
+
+            assert_eq!(array.fixed_field(0).unwrap().as_integer(), Some(7));
+            assert!(array.fixed_field(1).is_none());
+            assert!(array.get_alive(0).is_none());
+            assert!(array.get(0).is_some());
+            assert!(array
+                .get_alive(1)
+                .unwrap()
+                .equals(&heap.true_object().into())
+                .unwrap());
+            assert!(array.get(3).is_none());
+            assert_eq!(array.iter().count(), 3);
+            assert_eq!(array.alive().count(), 1);
+        })
+    }
+
+    #[test]
+    fn strong_array_is_not_weak() {
+        with_test_heap(|heap| {
+            let array = heap.new_array(&[]);
+            assert!(WeakArrayRef::try_from(AnyObjectRef::from(array)).is_err());
+        })
+    }
+}
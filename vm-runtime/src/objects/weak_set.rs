@@ -0,0 +1,310 @@
+use crate::objects::{equality_hash, good_prime_at_least, is_nil, WeakArray, WeakArrayRef};
+use vm_bindings::Smalltalk;
+use vm_object_model::{AnyObjectRef, Error, Immediate, Object, ObjectRef, Result};
+use vm_object_model_derive::PharoObject;
+
+/// A Pharo WeakSet: a hashed collection whose elements are held by a weak array.
+/// Empty slots hold the `flag` object, while slots whose element was garbage collected
+/// read as nil and act as tombstones: a lookup continues past them.
+#[derive(Debug, PharoObject)]
+#[repr(C)]
+pub struct WeakSet {
+    this: Object,
+    tally: Immediate,
+    array: WeakArrayRef,
+    flag: AnyObjectRef,
+}
+
+/// The state of a slot of a weak set
+#[derive(Debug, Copy, Clone)]
+pub enum WeakSetSlot {
+    /// The slot was never used
+    Empty,
+    /// The slot held an element that was garbage collected
+    Collected,
+    Element(AnyObjectRef),
+}
+
+impl WeakSet {
+    /// Return the amount of added elements.
+    /// The tally is not decremented when an element is collected, so it is an upper bound
+    pub fn len(&self) -> usize {
+        self.tally.as_integer().unwrap() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.array.len()
+    }
+
+    pub fn array(&self) -> WeakArrayRef {
+        self.array
+    }
+
+    pub fn slot_at(&self, index: usize) -> WeakSetSlot {
+        let element = self.array.get(index).unwrap();
+        if element.as_i64() == self.flag.as_i64() {
+            WeakSetSlot::Empty
+        } else if is_nil(element) {
+            WeakSetSlot::Collected
+        } else {
+            WeakSetSlot::Element(element)
+        }
+    }
+
+    /// Return the index of the element for which the block answers true, or of the empty slot
+    /// where such an element would be added, probing from `hash \\ capacity` like `WeakSet>>#scanFor:`.
+    /// Return None if the set is full and does not include the element
+    pub fn scan_for(&self, hash: u32, is_element: impl Fn(AnyObjectRef) -> bool) -> Option<usize> {
+        let size = self.capacity();
+        if size == 0 {
+            return None;
+        }
+
+        let start = hash as usize % size;
+        let mut index = start;
+        loop {
+            match self.slot_at(index) {
+                WeakSetSlot::Empty => return Some(index),
+                WeakSetSlot::Element(element) if is_element(element) => return Some(index),
+                WeakSetSlot::Element(_) | WeakSetSlot::Collected => {}
+            }
+
+            index = (index + 1) % size;
+            if index == start {
+                return None;
+            }
+        }
+    }
+
+    /// Return the index of the first slot that is empty or whose element was collected,
+    /// probing from `hash \\ capacity` like `WeakSet>>#scanForEmptySlotFor:`.
+    /// Return None if every slot holds an element
+    pub fn scan_for_empty_slot(&self, hash: u32) -> Option<usize> {
+        let size = self.capacity();
+        if size == 0 {
+            return None;
+        }
+
+        let start = hash as usize % size;
+        (start..size)
+            .chain(0..start)
+            .find(|index| !matches!(self.slot_at(*index), WeakSetSlot::Element(_)))
+    }
+
+    /// Return an element for which the block answers true
+    pub fn find(
+        &self,
+        hash: u32,
+        is_element: impl Fn(AnyObjectRef) -> bool,
+    ) -> Option<AnyObjectRef> {
+        match self.slot_at(self.scan_for(hash, is_element)?) {
+            WeakSetSlot::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Iterate over the elements that are still alive
+    pub fn iter(&self) -> impl Iterator<Item = AnyObjectRef> + '_ {
+        (0..self.capacity()).filter_map(|index| match self.slot_at(index) {
+            WeakSetSlot::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Put an element in a slot found by `scan_for`.
+    /// The caller is responsible for growing the set, the same way `#atNewIndex:put:` does
+    pub fn at_new_index_put(
+        &mut self,
+        index: usize,
+        element: impl Into<AnyObjectRef>,
+    ) -> Result<()> {
+        self.array.insert(index, element)?;
+        self.set_tally(Immediate::new_i64(self.len() as i64 + 1))
+    }
+
+    /// Put an element in a slot found by `scan_for` and make room for the following elements
+    /// if less than a quarter of the slots is empty, like `#atNewIndex:put:` followed by `#fullCheck`
+    pub fn add_at_new_index(
+        &mut self,
+        index: usize,
+        element: impl Into<AnyObjectRef>,
+    ) -> Result<()> {
+        self.at_new_index_put(index, element)?;
+        self.full_check()
+    }
+
+    /// Put an element in a slot found by `scan_for_empty_slot`.
+    /// A collected element is still counted by the tally, so its slot is reused without changing the tally
+    pub fn add_at_empty_slot(
+        &mut self,
+        index: usize,
+        element: impl Into<AnyObjectRef>,
+    ) -> Result<()> {
+        match self.slot_at(index) {
+            WeakSetSlot::Collected => self.array.insert(index, element),
+            _ => self.add_at_new_index(index, element),
+        }
+    }
+
+    /// Grow the array to `capacity + growSize` rounded to a prime, like `HashedCollection>>#grow`
+    pub fn grow(&mut self) -> Result<()> {
+        let capacity = self.capacity();
+        self.grow_to(good_prime_at_least(capacity + capacity.max(2)))
+    }
+
+    fn full_check(&mut self) -> Result<()> {
+        let capacity = self.capacity();
+        let minimum_free_space = (capacity / 4).max(1);
+        if capacity.saturating_sub(self.len()) >= minimum_free_space {
+            return Ok(());
+        }
+
+        // the tally also counts collected elements, dropping them may be enough to make room
+        let alive = self.iter().count();
+        if capacity - alive >= minimum_free_space * 2 {
+            self.grow_to(capacity)
+        } else {
+            self.grow()
+        }
+    }
+
+    /// Move the elements that are still alive to a new array of a given capacity, rehashing them
+    /// with `#hash` and dropping the collected ones, like `WeakSet>>#growTo:`
+    fn grow_to(&mut self, capacity: usize) -> Result<()> {
+        let elements = self.iter().collect::<Vec<_>>();
+        let weak_array_class = Smalltalk::class_of(self.array)
+            .ok_or_else(|| Error::InvalidType("WeakArray".to_string()))?;
+
+        let mut array = WeakArray::new(ObjectRef::from(weak_array_class), capacity)?;
+        for index in 0..capacity {
+            array.insert(index, self.flag)?;
+        }
+        self.set_array(array)?;
+        self.set_tally(Immediate::new_i64(0))?;
+
+        for element in elements {
+            let index = self
+                .scan_for(equality_hash(element) as u32, |_| false)
+                .expect("Grown set must have empty space");
+            self.at_new_index_put(index, element)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::objects::{byte_symbol_class, hash_of, with_test_heap, WeakArray};
+    use vm_object_model::{ObjectFormat, TestHeap};
+
+    /// Allocate an empty weak set with a given capacity
+    pub(crate) fn new_weak_set(heap: &TestHeap, capacity: usize) -> WeakSetRef {
+        let flag = heap.instantiate(heap.new_class(ObjectFormat::ZeroSized, 0));
+        let mut array =
+            WeakArray::new(heap.new_class(ObjectFormat::WeakIndexable, 0), capacity).unwrap();
+        for index in 0..capacity {
+            array.insert(index, flag).unwrap();
+        }
+
+        let class = heap.new_class(ObjectFormat::NonIndexable, 3);
+        let set = heap.new_object(
+            class,
+            &[Immediate::new_i64(0).into(), array.into(), flag.into()],
+        );
+        WeakSetRef::try_from(AnyObjectRef::from(set)).unwrap()
+    }
+
+    fn is_string(string: &str) -> impl Fn(AnyObjectRef) -> bool + '_ {
+        move |each| {
+            vm_object_model::ByteStringRef::try_from(each)
+                .map(|each| each.as_bytes() == string.as_bytes())
+                .unwrap_or(false)
+        }
+    }
+
+    #[test]
+    fn add_and_find() {
+        with_test_heap(|heap| {
+            let mut set = new_weak_set(heap, 5);
+            for each in ["foo", "bar"] {
+                let index = set.scan_for(hash_of(each), is_string(each)).unwrap();
+                set.at_new_index_put(index, heap.new_byte_string(each))
+                    .unwrap();
+            }
+            assert_eq!(set.len(), 2);
+            assert_eq!(set.iter().count(), 2);
+            assert!(set.find(hash_of("foo"), is_string("foo")).is_some());
+            assert!(set.find(hash_of("baz"), is_string("baz")).is_none());
+        })
+    }
+
+    #[test]
+    fn grow_drops_collected_elements() {
+        with_test_heap(|heap| {
+            let symbol_class = byte_symbol_class(heap);
+            let mut set = new_weak_set(heap, 5);
+            for each in ["a", "b", "c", "d", "e", "f"] {
+                let symbol = heap.new_bytes(symbol_class, each.as_bytes());
+                let index = set.scan_for(hash_of(each), is_string(each)).unwrap();
+                set.add_at_new_index(index, symbol).unwrap();
+            }
+            assert_eq!(set.len(), 6);
+            assert!(set.capacity() > 6);
+            assert!(set.find(hash_of("a"), is_string("a")).is_some());
+
+            let index = set.scan_for(hash_of("b"), is_string("b")).unwrap();
+            set.array().insert(index, heap.nil()).unwrap();
+            set.grow().unwrap();
+            assert_eq!(set.len(), 5);
+            assert!(set.find(hash_of("b"), is_string("b")).is_none());
+            assert!(set.find(hash_of("f"), is_string("f")).is_some());
+        })
+    }
+
+    #[test]
+    fn lookup_continues_past_collected_elements() {
+        with_test_heap(|heap| {
+            let mut set = new_weak_set(heap, 5);
+            let start = hash_of("foo") as usize % 5;
+
+            // an element with the same hash was added first and then collected
+            set.array().insert(start, heap.nil()).unwrap();
+            set.at_new_index_put((start + 1) % 5, heap.new_byte_string("foo"))
+                .unwrap();
+
+            assert!(matches!(set.slot_at(start), WeakSetSlot::Collected));
+            assert_eq!(
+                set.scan_for(hash_of("foo"), is_string("foo")),
+                Some((start + 1) % 5)
+            );
+            assert_eq!(set.iter().count(), 1);
+        })
+    }
+
+    #[test]
+    fn reuse_slots_of_collected_elements() {
+        with_test_heap(|heap| {
+            let mut set = new_weak_set(heap, 5);
+            let start = hash_of("foo") as usize % 5;
+
+            set.at_new_index_put(start, heap.new_byte_string("bar"))
+                .unwrap();
+            set.array().insert(start, heap.nil()).unwrap();
+            assert_eq!(set.scan_for_empty_slot(hash_of("foo")), Some(start));
+
+            set.add_at_empty_slot(start, heap.new_byte_string("foo"))
+                .unwrap();
+            assert_eq!(set.len(), 1);
+            assert!(set.find(hash_of("foo"), is_string("foo")).is_some());
+            assert_eq!(
+                set.scan_for_empty_slot(hash_of("foo")),
+                Some((start + 1) % 5)
+            );
+        })
+    }
+}
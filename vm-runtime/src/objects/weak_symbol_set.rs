@@ -1,88 +1,164 @@
-use crate::objects::{hash_of, ArrayRef, ByteSymbol};
-use std::hash::Hash;
-use std::ops::Deref;
-use vm_object_model::Result;
-use vm_object_model::{AnyObjectRef, Error, Object, ObjectRef};
-
-#[derive(Debug)]
+use crate::objects::{
+    hash_of, is_latin1, is_nil, new_byte_string, new_wide_string, WeakArrayRef, WeakSetRef,
+};
+use vm_object_model::{
+    AnyObjectRef, ByteStringRef, Error, Object, ObjectRef, Result, WideStringRef,
+};
+use vm_object_model_derive::PharoObject;
+
+/// The symbol table of the image (`Symbol class>>#SymbolTable`): a WeakSet of ByteSymbols and WideSymbols.
+/// Symbols hash like strings, so they are looked up by their characters
+#[derive(Debug, PharoObject)]
 #[repr(C)]
 pub struct WeakSymbolSet {
     this: Object,
     tally: AnyObjectRef,
-    array: ArrayRef,
+    array: WeakArrayRef,
     flag: ObjectRef,
 }
 
+/// The classes of the symbols that are created when interning, provided by the image.
+/// An image may have no WideSymbol, in which case only Latin-1 symbols can be interned
+#[derive(Debug, Copy, Clone)]
+pub struct SymbolClasses {
+    pub byte_symbol: ObjectRef,
+    pub wide_symbol: Option<ObjectRef>,
+}
+
 impl WeakSymbolSet {
     pub fn find_like_byte_str(&self, string: &str) -> Option<ObjectRef> {
-        if let Some(index) = self.scan_for_byte_str(string) {
-            let item = self.array.get(index)?.as_object().ok()?;
+        self.find(string)?.as_object().ok()
+    }
 
-            if !item.equals(&self.flag).ok()? {
-                return Some(item);
-            }
-        }
-        None
+    /// Return the symbol with given characters
+    pub fn find(&self, string: &str) -> Option<AnyObjectRef> {
+        self.as_weak_set()
+            .find(hash_of(string), |each| has_characters(each, string))
     }
 
-    pub fn scan_for_byte_str(&self, string: &str) -> Option<usize> {
-        let hash: u32 = hash_of(string);
-
-        let start = hash.rem_euclid(self.array.len() as u32) as usize;
-        let mut index = start;
-
-        loop {
-            if let Ok(item) = self.array[index].as_object() {
-                if item.equals(&self.flag).ok()? {
-                    return Some(index);
-                }
-
-                if let Ok(byte_symbol) = ByteSymbol::try_from(item.deref()) {
-                    if byte_symbol.as_str() == string {
-                        return Some(index);
-                    }
-                }
-            }
+    /// Return the symbol with given characters, adding a new one to the table if there is none.
+    /// The new symbol is a ByteSymbol if the characters fit in Latin-1, otherwise a WideSymbol.
+    /// Fails without allocating if a WideSymbol is needed but the image has none
+    pub fn intern(&mut self, string: &str, classes: SymbolClasses) -> Result<AnyObjectRef> {
+        if let Some(symbol) = self.find(string) {
+            return Ok(symbol);
+        }
 
-            index = index.rem_euclid(self.array.len());
-            if index == start {
-                break;
+        let symbol: AnyObjectRef = if is_latin1(string) {
+            new_byte_string(classes.byte_symbol, string)?.into()
+        } else {
+            let wide_symbol = classes
+                .wide_symbol
+                .ok_or_else(|| Error::InvalidType("WideSymbol".to_string()))?;
+            new_wide_string(wide_symbol, string)?.into()
+        };
+
+        // the allocation may have collected symbols, so look for an empty slot only now,
+        // reusing the slots of collected symbols
+        let mut symbol_table = self.as_weak_set();
+        let index = match symbol_table.scan_for_empty_slot(hash_of(string)) {
+            Some(index) => index,
+            None => {
+                symbol_table.grow()?;
+                symbol_table
+                    .scan_for_empty_slot(hash_of(string))
+                    .expect("Grown symbol table must have empty space")
             }
-        }
+        };
+        symbol_table.add_at_empty_slot(index, symbol)?;
+        Ok(symbol)
+    }
 
-        None
+    /// Iterate over the symbols that are still alive
+    pub fn iter(&self) -> impl Iterator<Item = AnyObjectRef> + '_ {
+        self.array
+            .iter()
+            .filter(|each| !is_nil(*each) && !each.equals(&self.flag.into()).unwrap_or(false))
     }
-}
 
-impl Deref for WeakSymbolSet {
-    type Target = Object;
+    /// Return the index of the symbol with given characters or of the empty slot where it would be
+    pub fn scan_for_byte_str(&self, string: &str) -> Option<usize> {
+        self.as_weak_set()
+            .scan_for(hash_of(string), |each| has_characters(each, string))
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.this
+    fn as_weak_set(&self) -> WeakSetRef {
+        WeakSetRef::try_from(AnyObjectRef::from(ObjectRef::from(&self.this)))
+            .expect("Symbol table must be a WeakSet")
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-#[repr(transparent)]
-pub struct WeakSymbolSetRef(ObjectRef);
-
-impl Deref for WeakSymbolSetRef {
-    type Target = WeakSymbolSet;
-    fn deref(&self) -> &Self::Target {
-        unsafe { self.0.cast() }
+/// Return true if an object is a byte or wide string (or symbol) with given characters
+fn has_characters(object: AnyObjectRef, string: &str) -> bool {
+    if let Ok(byte_string) = ByteStringRef::try_from(object) {
+        return byte_string.chars().eq(string.chars());
     }
+    WideStringRef::try_from(object)
+        .is_ok_and(|wide_string| wide_string.chars_lossy().eq(string.chars()))
 }
 
-impl TryFrom<AnyObjectRef> for WeakSymbolSetRef {
-    type Error = Error;
-
-    fn try_from(value: AnyObjectRef) -> Result<Self> {
-        let object = value.as_object()?;
-
-        if object.amount_of_slots() != 3 {
-            return Err(Error::InvalidType("WeakSymbolSet".to_string()));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{byte_symbol_class, new_weak_set, wide_symbol_class, with_test_heap};
+
+    #[test]
+    fn find_like_byte_str() {
+        with_test_heap(|heap| {
+            let symbol = heap.new_byte_symbol("foo");
+
+            let mut weak_set = new_weak_set(heap, 8);
+            let index = weak_set.scan_for(hash_of("foo"), |_| false).unwrap();
+            weak_set.at_new_index_put(index, symbol).unwrap();
+            let set = WeakSymbolSetRef::try_from(AnyObjectRef::from(weak_set)).unwrap();
+
+            let found = set.find_like_byte_str("foo").unwrap();
+            assert!(found.is_identical(&symbol).unwrap());
+            assert!(set.find_like_byte_str("bar").is_none());
+        })
+    }
 
-        Ok(Self(object))
+    #[test]
+    fn intern_new_symbols() {
+        with_test_heap(|heap| {
+            let weak_set = new_weak_set(heap, 5);
+            let mut set = WeakSymbolSetRef::try_from(AnyObjectRef::from(weak_set)).unwrap();
+
+            let classes = SymbolClasses {
+                byte_symbol: byte_symbol_class(heap),
+                wide_symbol: Some(wide_symbol_class(heap)),
+            };
+
+            let selectors = (0..20)
+                .map(|index| format!("selector{}:", index))
+                .collect::<Vec<_>>();
+            let symbols = selectors
+                .iter()
+                .map(|each| set.intern(each, classes).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(set.tally.as_integer(), Some(20));
+            assert!(set.array.len() > 20);
+
+            for (selector, symbol) in selectors.iter().zip(symbols) {
+                let found = set.intern(selector, classes).unwrap();
+                assert!(found.equals(&symbol).unwrap());
+            }
+            assert_eq!(set.tally.as_integer(), Some(20));
+
+            let wide = set.intern("λx", classes).unwrap();
+            assert!(WideStringRef::try_from(wide).is_ok());
+            assert!(set.find("λx").unwrap().equals(&wide).unwrap());
+            assert!(set.find("missing").is_none());
+            assert_eq!(set.iter().count(), 21);
+
+            let used_words = heap.used_words();
+            let byte_symbols_only = SymbolClasses {
+                wide_symbol: None,
+                ..classes
+            };
+            assert!(set.intern("μ", byte_symbols_only).is_err());
+            assert_eq!(heap.used_words(), used_words);
+            assert!(set.find("μ").is_none());
+        })
     }
 }
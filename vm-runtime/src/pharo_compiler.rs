@@ -1,4 +1,7 @@
-use crate::objects::{CompiledMethod, WeakSymbolSet};
+use crate::objects::{
+    print_string, CompiledMethodRef, SmalltalkString, SymbolClasses, WeakSymbolSet,
+    DEFAULT_PRINT_DEPTH, DEFAULT_PRINT_WIDTH,
+};
 use crate::vm;
 use libc::open;
 use pharo_compiler::bytecode::CompiledCodeLiteral;
@@ -6,9 +9,8 @@ use pharo_compiler::ir::{OwnedLiteral, OwnedLiteralValue};
 use pharo_compiler::kernel_environment;
 use pharo_compiler::vm_plugin::PharoCompiler;
 use std::fmt::Debug;
-use std::slice;
-use vm_bindings::{Smalltalk, StackOffset};
-use vm_object_model::ObjectFormat;
+use vm_bindings::{Smalltalk, StackOffset, StringKind};
+use vm_object_model::{AnyObjectRef, ObjectFormat, RawObjectPointer, WideStringRef};
 
 #[cfg(not(feature = "pharo-compiler"))]
 compile_error!("\"pharo-compiler\" feature must be enabled for this module.");
@@ -47,22 +49,24 @@ pub fn primitivePharoCompilerCompile() {
     let compiler_ptr = proxy.read_address(compiler_external_object);
     let compiler: &PharoCompiler = unsafe { &*(compiler_ptr as *const PharoCompiler) };
 
-    let source = Smalltalk::stack_object_value(StackOffset::new(1)).unwrap();
-    let source_size = Smalltalk::size_of(source);
-    let source_ptr = Smalltalk::first_indexable_field(source) as *const u8;
-
-    let source_bytes = unsafe { slice::from_raw_parts(source_ptr, source_size) };
-    let source_str = unsafe { std::str::from_utf8_unchecked(source_bytes) };
-
-    let compiled_method = compiler.compile(source_str);
+    let source = match SmalltalkString::try_from(Smalltalk::stack_ref(StackOffset::new(1))) {
+        Ok(source) => source.to_string(),
+        Err(error) => {
+            error!("Source code must be a string: {}", error);
+            return Smalltalk::primitive_fail();
+        }
+    };
 
-    let compiled_method_object = AnyObject::from(compiled_method.pharo_method);
+    let compiled_method = compiler.compile(&source);
 
-    let compiled_method_object =
-        CompiledMethod::try_from(compiled_method_object.as_object_unchecked()).unwrap();
+    let mut compiled_method_object = CompiledMethodRef::try_from(AnyObjectRef::from(
+        RawObjectPointer::from(compiled_method.pharo_method.as_i64()),
+    ))
+    .unwrap();
 
     let symbol_table = AnyObject::from(compiler.symbol_table);
-    let symbol_table = WeakSymbolSet::try_from(symbol_table.as_object_unchecked()).unwrap();
+    let mut symbol_table = WeakSymbolSet::try_from(symbol_table.as_object_unchecked()).unwrap();
+    let symbol_classes = symbol_classes(&symbol_table);
 
     for (index, literal) in compiled_method.literals().iter().enumerate() {
         match literal {
@@ -76,8 +80,16 @@ pub fn primitivePharoCompilerCompile() {
                     OwnedLiteralValue::Character(_) => {}
                     OwnedLiteralValue::String(_) => {}
                     OwnedLiteralValue::Symbol(string) => {
-                        if let Some(symbol) = symbol_table.find_like_byte_str(string) {
-                            compiled_method_object.set_literal(AnyObject::Object(symbol), index);
+                        let symbol = match symbol_classes {
+                            Some(classes) => symbol_table.intern(string, classes),
+                            None => symbol_table
+                                .find(string)
+                                .ok_or(vm_object_model::Error::InvalidType("Symbol".to_string())),
+                        };
+                        let result = symbol
+                            .and_then(|symbol| compiled_method_object.set_literal_at(index, symbol));
+                        if let Err(error) = result {
+                            error!("Failed to intern #{}: {}", string, error);
                         }
                     }
                 },
@@ -92,32 +104,31 @@ pub fn primitivePharoCompilerCompile() {
     Smalltalk::method_return_value(compiled_method.pharo_method.into());
 }
 
+/// Return the classes of new symbols, taken from the symbols that are already in the table.
+/// If there is no wide symbol in the table yet, WideSymbol is looked up in the class table.
+/// An image may have no WideSymbol at all, in which case interning a wide symbol fails
+fn symbol_classes(symbol_table: &WeakSymbolSet) -> Option<SymbolClasses> {
+    let byte_symbol = Smalltalk::class_of(symbol_table.find("ByteSymbol")?)?;
+    let wide_symbol = symbol_table
+        .iter()
+        .find(|each| WideStringRef::try_from(*each).is_ok())
+        .and_then(Smalltalk::class_of)
+        .or_else(|| StringKind::WideSymbol.class().ok());
+
+    Some(SymbolClasses {
+        byte_symbol: byte_symbol.into(),
+        wide_symbol: wide_symbol.map(|class| class.into()),
+    })
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub fn primitivePharoCompilerPrintObject() {
-    let smalltalk = Smalltalk::new();
-
-    let object = smalltalk.get_stack_value(StackOffset::new(0));
-    println!("object: {:#?}", &object);
-
-    if let Some(object) = object.try_as_object() {
-        if let Some(array) = object.try_as_array() {
-            println!("{:?}", array.items().collect::<Vec<_>>());
-        }
-    }
-
-    // let immediate_masked = Into::<sqInt>::into(object) & 7;
-    // println!("object {:?}", object);
-    // println!("immediate_masked {}", immediate_masked);
-    //
-    // if object.is_immediate() {
-    //     println!("immediate {:?}", object);
-    // }
-    // else {
-    //     let raw_header: *mut ObjectHeader = unsafe { std::mem::transmute(object) };
-    //     let object_header = unsafe { &*raw_header };
-    //     println!("{:#?}", object_header);
-    // }
+    let object = Smalltalk::stack_ref(StackOffset::new(0));
+    info!(
+        "object: {}",
+        print_string(object, DEFAULT_PRINT_DEPTH, DEFAULT_PRINT_WIDTH)
+    );
 
     Smalltalk::method_return_boolean(true);
 }
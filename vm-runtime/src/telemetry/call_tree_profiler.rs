@@ -0,0 +1,547 @@
+use crate::{
+    check_emits_send_signals, AbstractTelemetry, ContextSwitchSignal, GlobalTelemetry,
+    IdentityDictionaryRef, PrimitiveActivation, ReturnSignal, SelectorTable, SendSignal,
+    TelemetrySignal,
+};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use vm_bindings::{Smalltalk, StackOffset};
+use vm_object_model::{Immediate, ObjectRef};
+use vm_object_model_derive::ToSmalltalk;
+
+/// A method identified by the class of the receiver and the selector of the message
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CallSite {
+    pub class_index: u32,
+    pub selector: usize,
+}
+
+#[derive(Debug, Default)]
+struct CallTreeNode {
+    call_site: Option<CallSite>,
+    children: HashMap<CallSite, usize>,
+    calls: u64,
+    inclusive_time: Duration,
+    exclusive_time: Duration,
+    is_primitive: bool,
+}
+
+/// Aggregated calls; the same method sent from different paths has different nodes
+#[derive(Debug)]
+struct CallTree {
+    nodes: Vec<CallTreeNode>,
+}
+
+impl CallTree {
+    const ROOT: usize = 0;
+
+    fn new() -> Self {
+        Self {
+            nodes: vec![CallTreeNode::default()],
+        }
+    }
+
+    fn child_at(&mut self, parent: usize, call_site: CallSite) -> usize {
+        if let Some(child) = self.nodes[parent].children.get(&call_site) {
+            return *child;
+        }
+
+        let child = self.nodes.len();
+        self.nodes.push(CallTreeNode {
+            call_site: Some(call_site),
+            ..Default::default()
+        });
+        self.nodes[parent].children.insert(call_site, child);
+        child
+    }
+
+    /// Children sorted by the time spent in them, longest first
+    fn sorted_children(&self, node: usize) -> Vec<usize> {
+        let mut children = self.nodes[node]
+            .children
+            .values()
+            .copied()
+            .collect::<Vec<_>>();
+        children.sort_by_key(|child| std::cmp::Reverse(self.nodes[*child].inclusive_time));
+        children
+    }
+}
+
+/// A method that is being executed by a process
+#[derive(Debug)]
+struct Activation {
+    node: usize,
+    frame_pointer: usize,
+    start: Instant,
+    children_time: Duration,
+}
+
+/// The Rust side mirror of the stack of a Pharo process
+#[derive(Debug, Default)]
+struct ShadowStack {
+    activations: Vec<Activation>,
+    suspended_at: Option<Instant>,
+}
+
+impl ShadowStack {
+    fn suspend(&mut self, timestamp: Instant) {
+        self.suspended_at.get_or_insert(timestamp);
+    }
+
+    /// The time a process was suspended is not accounted to its methods
+    fn resume(&mut self, timestamp: Instant) {
+        if let Some(suspended_at) = self.suspended_at.take() {
+            let suspended_time = timestamp.saturating_duration_since(suspended_at);
+            for activation in &mut self.activations {
+                activation.start += suspended_time;
+            }
+        }
+    }
+}
+
+/// A node of the call tree as it is passed to the image:
+/// an Array of the class name, selector, amount of calls, inclusive and exclusive time in microseconds,
+/// whether the method has a primitive, and an Array of the children
+#[derive(Debug, Clone, PartialEq, ToSmalltalk)]
+pub struct CallTreeReport {
+    pub class_name: String,
+    pub selector: String,
+    pub calls: u64,
+    pub inclusive_microseconds: u64,
+    pub exclusive_microseconds: u64,
+    pub is_primitive: bool,
+    pub children: Vec<CallTreeReport>,
+}
+
+/// A tracing profiler that follows message sends and returns of every Pharo process
+/// and aggregates them in a call tree keyed by the class of the receiver and the selector.
+///
+/// Sends are matched with returns by the frame pointer of the sender,
+/// which lets non-local returns unwind several activations at once.
+/// Only the completed calls are accounted for.
+/// The signals are only emitted by a vm built with the `telemetry` feature.
+///
+/// Processes move in memory and their identity hashes are not unique,
+/// so each process is given an id the first time it is seen, stored in a dictionary of the image
+#[derive(Debug)]
+pub struct CallTreeProfiler {
+    id: usize,
+    tree: CallTree,
+    selectors: SelectorTable,
+    process_ids: IdentityDictionaryRef,
+    stacks: HashMap<u64, ShadowStack>,
+    current_process: u64,
+}
+
+impl CallTreeProfiler {
+    /// Until the first process switch the active process is not known
+    const UNKNOWN_PROCESS: u64 = 0;
+
+    /// Create a profiler that identifies processes with a pinned IdentityDictionary
+    pub fn new(process_ids: IdentityDictionaryRef) -> Self {
+        Self {
+            id: 0,
+            tree: CallTree::new(),
+            selectors: SelectorTable::default(),
+            process_ids,
+            stacks: HashMap::new(),
+            current_process: Self::UNKNOWN_PROCESS,
+        }
+    }
+
+    /// Return the id of a process, assigning the next one if the process is seen for the first time.
+    /// Signals are received inside of the context switch, so a failure is logged
+    fn process_id(&mut self, process: ObjectRef) -> u64 {
+        let next_id = self.process_ids.len() as i64 + 1;
+        match self
+            .process_ids
+            .get_or_insert(process, || Immediate::new_i64(next_id).into())
+        {
+            Ok(id) => id
+                .as_integer()
+                .map_or(Self::UNKNOWN_PROCESS, |id| id as u64),
+            Err(error) => {
+                error!("Failed to assign an id to a process: {}", error);
+                Self::UNKNOWN_PROCESS
+            }
+        }
+    }
+
+    fn receive_context_switch_signal(&mut self, signal: &ContextSwitchSignal) {
+        let old_process = self.process_id(signal.old_process);
+        let new_process = self.process_id(signal.new_process);
+
+        if self.current_process == Self::UNKNOWN_PROCESS {
+            if let Some(stack) = self.stacks.remove(&Self::UNKNOWN_PROCESS) {
+                self.stacks.insert(old_process, stack);
+            }
+        }
+
+        self.stacks
+            .entry(old_process)
+            .or_default()
+            .suspend(signal.timestamp);
+        self.stacks
+            .entry(new_process)
+            .or_default()
+            .resume(signal.timestamp);
+        self.current_process = new_process;
+    }
+
+    fn receive_send_signal(&mut self, signal: &SendSignal) {
+        let call_site = CallSite {
+            class_index: signal.class_index,
+            selector: self.selectors.index_of(signal.selector),
+        };
+
+        let stack = self.stacks.entry(self.current_process).or_default();
+        let parent = stack
+            .activations
+            .last()
+            .map_or(CallTree::ROOT, |activation| activation.node);
+        let node = self.tree.child_at(parent, call_site);
+        self.tree.nodes[node].calls += 1;
+
+        stack.activations.push(Activation {
+            node,
+            frame_pointer: signal.frame_pointer,
+            start: signal.timestamp,
+            children_time: Duration::ZERO,
+        });
+    }
+
+    /// Returns to frames that were entered before the profiler started are ignored
+    fn receive_return_signal(&mut self, signal: &ReturnSignal) {
+        let Some(stack) = self.stacks.get_mut(&self.current_process) else {
+            return;
+        };
+        let Some(position) = stack
+            .activations
+            .iter()
+            .rposition(|activation| activation.frame_pointer == signal.frame_pointer)
+        else {
+            return;
+        };
+
+        while stack.activations.len() > position {
+            let activation = stack.activations.pop().unwrap();
+            let elapsed = signal.timestamp.saturating_duration_since(activation.start);
+
+            let node = &mut self.tree.nodes[activation.node];
+            node.inclusive_time += elapsed;
+            node.exclusive_time += elapsed.saturating_sub(activation.children_time);
+
+            if let Some(caller) = stack.activations.last_mut() {
+                caller.children_time += elapsed;
+            }
+        }
+    }
+
+    fn receive_primitive_activation(&mut self, activation: PrimitiveActivation) {
+        if activation != PrimitiveActivation::Activate {
+            return;
+        }
+        if let Some(activation) = self
+            .stacks
+            .get(&self.current_process)
+            .and_then(|stack| stack.activations.last())
+        {
+            self.tree.nodes[activation.node].is_primitive = true;
+        }
+    }
+
+    fn class_name_and_selector(
+        &self,
+        call_site: CallSite,
+        class_names: &mut HashMap<u32, String>,
+        class_name_at: &impl Fn(u32) -> String,
+    ) -> (String, String) {
+        let class_name = class_names
+            .entry(call_site.class_index)
+            .or_insert_with(|| class_name_at(call_site.class_index))
+            .clone();
+        (
+            class_name,
+            self.selectors.selector_at(call_site.selector).to_string(),
+        )
+    }
+
+    /// Return the call tree, resolving the names of classes with a given function
+    pub fn report(&self, class_name_at: impl Fn(u32) -> String) -> Vec<CallTreeReport> {
+        let mut class_names = HashMap::new();
+        self.tree
+            .sorted_children(CallTree::ROOT)
+            .into_iter()
+            .map(|child| self.report_node(child, &mut class_names, &class_name_at))
+            .collect()
+    }
+
+    fn report_node(
+        &self,
+        node_index: usize,
+        class_names: &mut HashMap<u32, String>,
+        class_name_at: &impl Fn(u32) -> String,
+    ) -> CallTreeReport {
+        let node = &self.tree.nodes[node_index];
+        let (class_name, selector) =
+            self.class_name_and_selector(node.call_site.unwrap(), class_names, class_name_at);
+
+        CallTreeReport {
+            class_name,
+            selector,
+            calls: node.calls,
+            inclusive_microseconds: node.inclusive_time.as_micros() as u64,
+            exclusive_microseconds: node.exclusive_time.as_micros() as u64,
+            is_primitive: node.is_primitive,
+            children: self
+                .tree
+                .sorted_children(node_index)
+                .into_iter()
+                .map(|child| self.report_node(child, class_names, class_name_at))
+                .collect(),
+        }
+    }
+
+    /// Return the call tree in the folded stack format used by flamegraph tools:
+    /// a line per path from the root, with frames separated by `;`,
+    /// followed by the exclusive time in microseconds. Paths without exclusive time are skipped
+    pub fn folded_stacks(&self, class_name_at: impl Fn(u32) -> String) -> String {
+        let mut class_names = HashMap::new();
+        let mut lines = vec![];
+        let mut pending = vec![(CallTree::ROOT, String::new())];
+
+        while let Some((node_index, path)) = pending.pop() {
+            let node = &self.tree.nodes[node_index];
+            let path = match node.call_site {
+                None => path,
+                Some(call_site) => {
+                    let (class_name, selector) =
+                        self.class_name_and_selector(call_site, &mut class_names, &class_name_at);
+                    let frame = format!("{}>>{}", class_name, selector);
+                    if path.is_empty() {
+                        frame
+                    } else {
+                        format!("{};{}", path, frame)
+                    }
+                }
+            };
+
+            let exclusive_microseconds = node.exclusive_time.as_micros();
+            if exclusive_microseconds > 0 {
+                lines.push((path.clone(), exclusive_microseconds));
+            }
+            for child in node.children.values() {
+                pending.push((*child, path.clone()));
+            }
+        }
+
+        lines.sort();
+        lines
+            .into_iter()
+            .fold(String::new(), |mut folded, (path, microseconds)| {
+                writeln!(folded, "{} {}", path, microseconds).unwrap();
+                folded
+            })
+    }
+}
+
+impl AbstractTelemetry for CallTreeProfiler {
+    fn receive_signal(&mut self, signal: &TelemetrySignal) {
+        match signal {
+            TelemetrySignal::ContextSwitch(signal) => {
+                self.receive_context_switch_signal(signal);
+            }
+            TelemetrySignal::Send(signal) => {
+                self.receive_send_signal(signal);
+            }
+            TelemetrySignal::Return(signal) => {
+                self.receive_return_signal(signal);
+            }
+            TelemetrySignal::PrimitiveActivation(signal) => {
+                self.receive_primitive_activation(signal.activation);
+            }
+            TelemetrySignal::SemaphoreWait(_) | TelemetrySignal::MachineMethod(_) => {}
+        }
+    }
+
+    fn assign_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    fn receives_send_signals(&self) -> bool {
+        true
+    }
+
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub(crate) fn class_name_at(class_index: u32) -> String {
+    Smalltalk::class_at_index(class_index)
+        .and_then(|class| class.name())
+        .unwrap_or_else(|| format!("<class {}>", class_index))
+}
+
+/// Start a call tree profiler and answer its id, which is used to retrieve the results
+/// and to stop the profiler with `primitiveStopTelemetry`.
+/// The argument is an empty IdentityDictionary in which the profiler stores the ids of processes
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveStartCallTreeProfiler() {
+    if !check_emits_send_signals("call tree profiler") {
+        return Smalltalk::primitive_fail();
+    }
+
+    let process_ids = Smalltalk::stack_ref(StackOffset::new(0));
+    // the dictionary is referenced from Rust across garbage collections, so it must not move
+    match IdentityDictionaryRef::try_from(process_ids)
+        .and_then(|process_ids| process_ids.check_pinned().map(|_| process_ids))
+    {
+        Ok(process_ids) => {
+            let id = GlobalTelemetry::register(CallTreeProfiler::new(process_ids));
+            Smalltalk::method_return(&id);
+        }
+        Err(error) => {
+            error!("Failed to get the dictionary of process ids: {}", error);
+            Smalltalk::primitive_fail();
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveCallTreeProfilerReport() {
+    let id = Smalltalk::stack_integer_value(StackOffset::new(0)) as usize;
+    match GlobalTelemetry::with_telemetry(id, |profiler: &mut CallTreeProfiler| {
+        profiler.report(class_name_at)
+    }) {
+        Some(report) => Smalltalk::method_return(&report),
+        None => {
+            error!("There is no call tree profiler with id {}", id);
+            Smalltalk::primitive_fail();
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveCallTreeProfilerFoldedStacks() {
+    let id = Smalltalk::stack_integer_value(StackOffset::new(0)) as usize;
+    match GlobalTelemetry::with_telemetry(id, |profiler: &mut CallTreeProfiler| {
+        profiler.folded_stacks(class_name_at)
+    }) {
+        Some(folded_stacks) => Smalltalk::method_return(&folded_stacks),
+        None => {
+            error!("There is no call tree profiler with id {}", id);
+            Smalltalk::primitive_fail();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{new_identity_dictionary, with_test_heap};
+    use crate::telemetry::Signals;
+    use vm_object_model::ObjectFormat;
+
+    fn class_name(class_index: u32) -> String {
+        format!("C{}", class_index)
+    }
+
+    #[test]
+    fn aggregate_calls_and_times() {
+        with_test_heap(|heap| {
+            let signals = Signals::new(heap);
+            let mut profiler = CallTreeProfiler::new(new_identity_dictionary(heap, 4));
+
+            for signal in [
+                signals.send(0, 1, "a", 100),
+                signals.send(1, 2, "b", 200),
+                signals.return_to(3, 200),
+                signals.send(4, 2, "b", 200),
+                signals.return_to(5, 200),
+                signals.return_to(10, 100),
+                // a return from a frame entered before profiling
+                signals.return_to(11, 50),
+            ] {
+                profiler.receive_signal(&signal);
+            }
+
+            let report = profiler.report(class_name);
+            assert_eq!(report.len(), 1);
+            assert_eq!(
+                (
+                    report[0].calls,
+                    report[0].inclusive_microseconds,
+                    report[0].exclusive_microseconds
+                ),
+                (1, 10_000, 7_000)
+            );
+            let child = &report[0].children[0];
+            assert_eq!(
+                (child.class_name.as_str(), child.selector.as_str()),
+                ("C2", "b")
+            );
+            assert_eq!(
+                (
+                    child.calls,
+                    child.inclusive_microseconds,
+                    child.exclusive_microseconds
+                ),
+                (2, 3_000, 3_000)
+            );
+
+            assert_eq!(
+                profiler.folded_stacks(class_name),
+                "C1>>a 7000\nC1>>a;C2>>b 3000\n"
+            );
+        })
+    }
+
+    #[test]
+    fn non_local_returns_and_suspended_processes() {
+        with_test_heap(|heap| {
+            let signals = Signals::new(heap);
+            let process_class = heap.new_class(ObjectFormat::NonIndexable, 0);
+            let first_process = heap.new_object(process_class, &[]);
+            let second_process = heap.new_object(process_class, &[]);
+            let process_ids = new_identity_dictionary(heap, 4);
+            let mut profiler = CallTreeProfiler::new(process_ids);
+
+            for signal in [
+                signals.send(0, 1, "a", 100),
+                signals.switch(2, first_process, second_process),
+                signals.send(3, 3, "other", 100),
+                signals.switch(5, second_process, first_process),
+                signals.send(7, 2, "b", 200),
+                // returns from both b and a
+                signals.return_to(9, 100),
+            ] {
+                profiler.receive_signal(&signal);
+            }
+
+            let report = profiler.report(class_name);
+            assert_eq!(report.len(), 2);
+            let first = report.iter().find(|each| each.selector == "a").unwrap();
+            assert_eq!(
+                (first.inclusive_microseconds, first.exclusive_microseconds),
+                (6_000, 4_000)
+            );
+            assert_eq!(first.children[0].inclusive_microseconds, 2_000);
+
+            // the second process has not returned yet
+            let other = report.iter().find(|each| each.selector == "other").unwrap();
+            assert_eq!((other.calls, other.inclusive_microseconds), (1, 0));
+
+            assert_eq!(process_ids.at(first_process).unwrap().as_integer(), Some(1));
+            assert_eq!(
+                process_ids.at(second_process).unwrap().as_integer(),
+                Some(2)
+            );
+        })
+    }
+}
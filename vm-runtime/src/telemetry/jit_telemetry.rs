@@ -0,0 +1,393 @@
+use crate::{
+    check_emits_send_signals, class_name_at, vm, AbstractTelemetry, CallSite, GlobalTelemetry,
+    MachineMethodEvent, PrimitiveActivation, SelectorTable, SendSignal, SendSource,
+    TelemetrySignal,
+};
+use std::any::Any;
+use std::collections::HashMap;
+use std::time::Instant;
+use vm_bindings::{Smalltalk, StackOffset};
+use vm_object_model_derive::ToSmalltalk;
+
+/// The state of the machine code zone, where the jit puts compiled methods.
+/// When it is full, the zone is compacted and the least used methods are freed
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct CodeZone {
+    pub size: usize,
+    pub used_bytes: usize,
+    pub compactions: u64,
+    pub compaction_microseconds: u64,
+}
+
+impl CodeZone {
+    pub fn current() -> Self {
+        let interpreter = vm().interpreter();
+        Self {
+            size: interpreter.code_zone_size(),
+            used_bytes: interpreter.code_zone_used_bytes(),
+            compactions: interpreter.code_compactions(),
+            compaction_microseconds: interpreter.code_compaction_microseconds(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MethodExecutions {
+    in_machine_code: u64,
+    interpreted: u64,
+    interpreted_after_machine_code: u64,
+    compiled_at: Option<Instant>,
+}
+
+impl MethodExecutions {
+    fn record(&mut self, in_machine_code: bool, timestamp: Instant) {
+        if in_machine_code {
+            if self.in_machine_code == 0 && self.interpreted > 0 {
+                self.compiled_at = Some(timestamp);
+            }
+            self.in_machine_code += 1;
+        } else {
+            if self.in_machine_code > 0 {
+                self.interpreted_after_machine_code += 1;
+            }
+            self.interpreted += 1;
+        }
+    }
+}
+
+/// How the methods activated by a call site were executed:
+/// an Array of the class name, selector, amount of executions in machine code and in the interpreter,
+/// the amount of interpreted executions once the method already ran in machine code,
+/// and the microseconds since the start of the telemetry when the method first ran in machine code
+/// after being interpreted, or nil if that was not observed
+#[derive(Debug, Clone, PartialEq, ToSmalltalk)]
+pub struct JitMethodReport {
+    pub class_name: String,
+    pub selector: String,
+    pub machine_code_executions: u64,
+    pub interpreted_executions: u64,
+    pub interpreted_after_machine_code: u64,
+    pub compiled_at_microseconds: Option<u64>,
+}
+
+/// The report of the jit telemetry as it is passed to the image:
+/// an Array of the amount of sends from the interpreter, from machine code,
+/// and from machine code to interpreted methods, the amount of machine code methods activated
+/// by the interpreter and of machine code method bodies entered, the size and used bytes
+/// of the code zone, the amount of code compactions and their time in microseconds
+/// since the start of the telemetry, and an Array of the methods
+#[derive(Debug, Clone, PartialEq, ToSmalltalk)]
+pub struct JitTelemetryReport {
+    pub interpreted_sends: u64,
+    pub machine_code_sends: u64,
+    pub machine_code_to_interpreter_sends: u64,
+    pub machine_method_activations: u64,
+    pub machine_method_begins: u64,
+    pub code_zone_size: u64,
+    pub code_zone_used_bytes: u64,
+    pub code_compactions: u64,
+    pub code_compaction_microseconds: u64,
+    pub methods: Vec<JitMethodReport>,
+}
+
+/// Follows how often the methods are executed in machine code and in the interpreter.
+///
+/// A send is executed in machine code when it is followed by the activation of a machine code method,
+/// the beginning of a machine code method body, or, for a send from machine code,
+/// the primitive of a machine code method.
+/// Otherwise the method is interpreted, even when the interpreter calls its primitive.
+/// The jit telemetry does not see the compilation itself, a method is considered compiled
+/// when it first runs in machine code after it was interpreted.
+/// Methods that are interpreted again after running in machine code were freed
+/// from the code zone, which hints at code zone pressure
+#[derive(Debug)]
+pub struct JitTelemetry {
+    id: usize,
+    started_at: Instant,
+    initial_code_zone: CodeZone,
+    selectors: SelectorTable,
+    methods: HashMap<CallSite, MethodExecutions>,
+    pending_send: Option<(CallSite, SendSource)>,
+    interpreted_sends: u64,
+    machine_code_sends: u64,
+    machine_code_to_interpreter_sends: u64,
+    machine_method_activations: u64,
+    machine_method_begins: u64,
+}
+
+impl JitTelemetry {
+    pub fn new(initial_code_zone: CodeZone) -> Self {
+        Self {
+            id: 0,
+            started_at: Instant::now(),
+            initial_code_zone,
+            selectors: SelectorTable::default(),
+            methods: HashMap::new(),
+            pending_send: None,
+            interpreted_sends: 0,
+            machine_code_sends: 0,
+            machine_code_to_interpreter_sends: 0,
+            machine_method_activations: 0,
+            machine_method_begins: 0,
+        }
+    }
+
+    /// Decide how the method activated by the last send was executed
+    fn complete_pending_send(&mut self, in_machine_code: bool, timestamp: Instant) {
+        if let Some((call_site, _)) = self.pending_send.take() {
+            self.methods
+                .entry(call_site)
+                .or_default()
+                .record(in_machine_code, timestamp);
+        }
+    }
+
+    fn receive_send_signal(&mut self, signal: &SendSignal) {
+        self.complete_pending_send(false, signal.timestamp);
+
+        let call_site = CallSite {
+            class_index: signal.class_index,
+            selector: self.selectors.index_of(signal.selector),
+        };
+        match signal.source {
+            SendSource::Interpreter => self.interpreted_sends += 1,
+            SendSource::MachineCode => self.machine_code_sends += 1,
+            SendSource::MachineCodeToInterpreter => self.machine_code_to_interpreter_sends += 1,
+        }
+
+        if signal.source == SendSource::MachineCodeToInterpreter {
+            self.methods
+                .entry(call_site)
+                .or_default()
+                .record(false, signal.timestamp);
+        } else {
+            self.pending_send = Some((call_site, signal.source));
+        }
+    }
+
+    fn receive_machine_method_signal(&mut self, event: MachineMethodEvent, timestamp: Instant) {
+        match event {
+            MachineMethodEvent::Activate => self.machine_method_activations += 1,
+            MachineMethodEvent::Begin => self.machine_method_begins += 1,
+        }
+        self.complete_pending_send(true, timestamp);
+    }
+
+    /// Return the report, with the current state of the code zone
+    /// and resolving the names of classes with a given function
+    pub fn report(
+        &self,
+        code_zone: CodeZone,
+        class_name_at: impl Fn(u32) -> String,
+    ) -> JitTelemetryReport {
+        let mut methods = self
+            .methods
+            .iter()
+            .map(|(call_site, executions)| JitMethodReport {
+                class_name: class_name_at(call_site.class_index),
+                selector: self.selectors.selector_at(call_site.selector).to_string(),
+                machine_code_executions: executions.in_machine_code,
+                interpreted_executions: executions.interpreted,
+                interpreted_after_machine_code: executions.interpreted_after_machine_code,
+                compiled_at_microseconds: executions.compiled_at.map(|compiled_at| {
+                    compiled_at
+                        .saturating_duration_since(self.started_at)
+                        .as_micros() as u64
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        // the methods that keep falling back to the interpreter first
+        methods.sort_by(|a, b| {
+            b.interpreted_after_machine_code
+                .cmp(&a.interpreted_after_machine_code)
+                .then(b.interpreted_executions.cmp(&a.interpreted_executions))
+                .then_with(|| (&a.class_name, &a.selector).cmp(&(&b.class_name, &b.selector)))
+        });
+
+        JitTelemetryReport {
+            interpreted_sends: self.interpreted_sends,
+            machine_code_sends: self.machine_code_sends,
+            machine_code_to_interpreter_sends: self.machine_code_to_interpreter_sends,
+            machine_method_activations: self.machine_method_activations,
+            machine_method_begins: self.machine_method_begins,
+            code_zone_size: code_zone.size as u64,
+            code_zone_used_bytes: code_zone.used_bytes as u64,
+            code_compactions: code_zone
+                .compactions
+                .saturating_sub(self.initial_code_zone.compactions),
+            code_compaction_microseconds: code_zone
+                .compaction_microseconds
+                .saturating_sub(self.initial_code_zone.compaction_microseconds),
+            methods,
+        }
+    }
+}
+
+impl AbstractTelemetry for JitTelemetry {
+    fn receive_signal(&mut self, signal: &TelemetrySignal) {
+        match signal {
+            TelemetrySignal::Send(signal) => {
+                self.receive_send_signal(signal);
+            }
+            TelemetrySignal::MachineMethod(signal) => {
+                self.receive_machine_method_signal(signal.event, signal.timestamp);
+            }
+            // a send from machine code activates the primitive of a machine code method,
+            // while the interpreter calls the primitives of any method itself
+            TelemetrySignal::PrimitiveActivation(signal)
+                if signal.activation == PrimitiveActivation::Activate
+                    && self
+                        .pending_send
+                        .is_some_and(|(_, source)| source == SendSource::MachineCode) =>
+            {
+                self.complete_pending_send(true, signal.timestamp);
+            }
+            TelemetrySignal::Return(signal) => {
+                self.complete_pending_send(false, signal.timestamp);
+            }
+            TelemetrySignal::ContextSwitch(signal) => {
+                self.complete_pending_send(false, signal.timestamp);
+            }
+            _ => {}
+        }
+    }
+
+    fn assign_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    fn receives_send_signals(&self) -> bool {
+        true
+    }
+
+    fn receives_machine_method_signals(&self) -> bool {
+        true
+    }
+
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Start a jit telemetry and answer its id, which is used to retrieve the report
+/// and to stop the telemetry with `primitiveStopTelemetry`
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveStartJitTelemetry() {
+    if !check_emits_send_signals("jit telemetry") {
+        return Smalltalk::primitive_fail();
+    }
+
+    let id = GlobalTelemetry::register(JitTelemetry::new(CodeZone::current()));
+    Smalltalk::method_return(&id);
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveJitTelemetryReport() {
+    let id = Smalltalk::stack_integer_value(StackOffset::new(0)) as usize;
+    match GlobalTelemetry::with_telemetry(id, |telemetry: &mut JitTelemetry| {
+        telemetry.report(CodeZone::current(), class_name_at)
+    }) {
+        Some(report) => Smalltalk::method_return(&report),
+        None => {
+            error!("There is no jit telemetry with id {}", id);
+            Smalltalk::primitive_fail();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::with_test_heap;
+    use crate::telemetry::Signals;
+
+    #[test]
+    fn machine_code_and_interpreted_executions() {
+        with_test_heap(|heap| {
+            let signals = Signals::new(heap);
+            let mut telemetry = JitTelemetry::new(CodeZone {
+                size: 1024,
+                used_bytes: 100,
+                compactions: 3,
+                compaction_microseconds: 50,
+            });
+            telemetry.started_at = signals.at(0);
+
+            for signal in [
+                // interpreted twice, then compiled
+                signals.send(1, 1, "hot", 100),
+                signals.return_to(2, 100),
+                signals.send(3, 1, "hot", 100),
+                signals.send(4, 1, "other", 100),
+                // the interpreter calls the primitive of an interpreted method
+                signals.primitive(4, PrimitiveActivation::Activate),
+                signals.primitive(5, PrimitiveActivation::Deactivate),
+                signals.return_to(5, 100),
+                signals.send(6, 1, "hot", 100),
+                signals.machine_method(6, MachineMethodEvent::Activate),
+                signals.machine_method(6, MachineMethodEvent::Begin),
+                // a linked send to a machine code method with a primitive
+                signals.send_from(SendSource::MachineCode, 7, 1, "hot", 100),
+                signals.primitive(7, PrimitiveActivation::Activate),
+                // freed from the code zone and interpreted again
+                signals.send_from(SendSource::MachineCodeToInterpreter, 8, 1, "hot", 100),
+                signals.return_to(9, 100),
+            ] {
+                telemetry.receive_signal(&signal);
+            }
+
+            let report = telemetry.report(
+                CodeZone {
+                    size: 1024,
+                    used_bytes: 1000,
+                    compactions: 5,
+                    compaction_microseconds: 80,
+                },
+                |class_index| format!("C{}", class_index),
+            );
+
+            assert_eq!(
+                (
+                    report.interpreted_sends,
+                    report.machine_code_sends,
+                    report.machine_code_to_interpreter_sends,
+                    report.machine_method_activations,
+                    report.machine_method_begins,
+                ),
+                (4, 1, 1, 1, 1)
+            );
+            assert_eq!(
+                (
+                    report.code_zone_used_bytes,
+                    report.code_compactions,
+                    report.code_compaction_microseconds
+                ),
+                (1000, 2, 30)
+            );
+            assert_eq!(
+                report.methods,
+                vec![
+                    JitMethodReport {
+                        class_name: "C1".to_string(),
+                        selector: "hot".to_string(),
+                        machine_code_executions: 2,
+                        interpreted_executions: 3,
+                        interpreted_after_machine_code: 1,
+                        compiled_at_microseconds: Some(6_000),
+                    },
+                    JitMethodReport {
+                        class_name: "C1".to_string(),
+                        selector: "other".to_string(),
+                        machine_code_executions: 0,
+                        interpreted_executions: 1,
+                        interpreted_after_machine_code: 0,
+                        compiled_at_microseconds: None,
+                    },
+                ]
+            );
+        })
+    }
+}
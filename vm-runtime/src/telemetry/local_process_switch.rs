@@ -1,13 +1,16 @@
 use crate::objects::OrderedCollectionRef;
 use crate::{
-    AbstractTelemetry, ApplicationError, ContextSwitchSignal, GlobalTelemetry, Result,
-    SemaphoreWaitSignal, TelemetrySignal,
+    AbstractTelemetry, ContextSwitchSignal, GlobalTelemetry, SemaphoreWaitSignal,
+    TelemetrySignal,
 };
-use std::ops::{Deref, DerefMut};
+use std::any::Any;
+use std::ops::DerefMut;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use vm_bindings::{ObjectPointer, Smalltalk, StackOffset};
 use vm_object_model::{AnyObjectRef, Immediate, Object, ObjectRef, RawObjectPointer};
+use vm_object_model_derive::PharoObject;
 
+#[derive(PharoObject)]
 #[repr(C)]
 pub struct LocalProcessSwitchTelemetry {
     this: Object,
@@ -37,7 +40,7 @@ impl LocalProcessSwitchTelemetry {
 
     fn add_context_switch_signal(&mut self, alive: bool) {
         self.add_signal(self.context_switch_signal_class, |signal_object| {
-            signal_object.inst_var_at_put(
+            signal_object.inst_var_at_put_unchecked(
                 2,
                 RawObjectPointer::new(Smalltalk::primitive_bool_object(alive).as_i64()),
             );
@@ -46,9 +49,9 @@ impl LocalProcessSwitchTelemetry {
 
     fn add_semaphore_wait_signal(&mut self, signal: &SemaphoreWaitSignal) {
         self.add_signal(self.semaphore_wait_signal_class, |signal_object| {
-            signal_object.inst_var_at_put(2, signal.semaphore);
+            signal_object.inst_var_at_put_unchecked(2, signal.semaphore);
 
-            signal_object.inst_var_at_put(
+            signal_object.inst_var_at_put_unchecked(
                 3,
                 RawObjectPointer::new(Smalltalk::primitive_bool_object(signal.is_locked).as_i64()),
             );
@@ -67,9 +70,9 @@ impl LocalProcessSwitchTelemetry {
 
         let since_the_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
 
-        signal_object.inst_var_at_put(0, Immediate::new_i64(since_the_epoch.as_secs() as i64));
+        signal_object.inst_var_at_put_unchecked(0, Immediate::new_i64(since_the_epoch.as_secs() as i64));
 
-        signal_object.inst_var_at_put(
+        signal_object.inst_var_at_put_unchecked(
             1,
             Immediate::new_i64(since_the_epoch.subsec_nanos() as i64),
         );
@@ -80,10 +83,6 @@ impl LocalProcessSwitchTelemetry {
     }
 }
 
-#[derive(Debug)]
-#[repr(transparent)]
-pub struct LocalProcessSwitchTelemetryRef(ObjectRef);
-
 impl AbstractTelemetry for LocalProcessSwitchTelemetryRef {
     fn receive_signal(&mut self, signal: &TelemetrySignal) {
         match signal {
@@ -93,41 +92,18 @@ impl AbstractTelemetry for LocalProcessSwitchTelemetryRef {
             TelemetrySignal::SemaphoreWait(signal) => {
                 self.receive_semaphore_wait_signal(signal);
             }
+            _ => {}
         }
     }
 
     fn assign_id(&mut self, id: usize) {
-        self.id = Immediate::new_i64(id as i64);
-    }
-}
-
-impl Deref for LocalProcessSwitchTelemetryRef {
-    type Target = LocalProcessSwitchTelemetry;
-    fn deref(&self) -> &Self::Target {
-        unsafe { self.0.cast() }
-    }
-}
-
-impl DerefMut for LocalProcessSwitchTelemetryRef {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { self.0.cast_mut() }
-    }
-}
-
-impl TryFrom<AnyObjectRef> for LocalProcessSwitchTelemetryRef {
-    type Error = ApplicationError;
-
-    fn try_from(value: AnyObjectRef) -> Result<Self> {
-        let object = value.as_object()?;
-
-        if object.amount_of_slots() != 5 {
-            return Err(vm_object_model::Error::InvalidType(
-                "LocalProcessSwitchTelemetry".to_string(),
-            )
-            .into());
+        if let Err(error) = self.set_id(Immediate::new_i64(id as i64)) {
+            error!("Failed to assign the telemetry id: {}", error);
         }
+    }
 
-        Ok(Self(object))
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
     }
 }
 
@@ -135,7 +111,10 @@ impl TryFrom<AnyObjectRef> for LocalProcessSwitchTelemetryRef {
 #[allow(non_snake_case)]
 pub fn primitiveStartLocalProcessSwitchTelemetry() {
     let telemetry_pointer = Smalltalk::stack_ref(StackOffset::new(0));
-    match LocalProcessSwitchTelemetryRef::try_from(telemetry_pointer) {
+    // the telemetry is referenced from Rust across garbage collections, so it must not move
+    match LocalProcessSwitchTelemetryRef::try_from(telemetry_pointer)
+        .and_then(|telemetry| telemetry.check_pinned().map(|_| telemetry))
+    {
         Ok(telemetry) => {
             GlobalTelemetry::register(telemetry);
             Smalltalk::method_return_boolean(true);
@@ -1,10 +1,21 @@
+mod call_tree_profiler;
 mod global_process_switch;
+mod jit_telemetry;
 mod local_process_switch;
+mod primitive_statistics;
+mod selector_table;
 mod telemetry;
 mod signals;
 
 pub use crate::objects::identity_dictionary::*;
+pub use call_tree_profiler::*;
 pub use global_process_switch::*;
+pub use jit_telemetry::*;
 pub use local_process_switch::*;
+pub use primitive_statistics::*;
+pub use selector_table::*;
 pub use signals::*;
 pub use telemetry::*;
+
+#[cfg(test)]
+pub(crate) use telemetry::tests::Signals;
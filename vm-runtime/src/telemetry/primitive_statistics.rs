@@ -0,0 +1,497 @@
+use crate::objects::{lookup_method, ArrayRef, CompiledMethodRef, SmalltalkString};
+use crate::{
+    check_emits_send_signals, AbstractTelemetry, CallSite, GlobalTelemetry, PrimitiveActivation,
+    ReturnSignal, SelectorTable, SendSignal, TelemetrySignal,
+};
+use std::any::Any;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use vm_bindings::{Smalltalk, StackOffset};
+use vm_object_model_derive::ToSmalltalk;
+
+/// `primitiveExternalCall`, whose first literal describes the named primitive to call
+const EXTERNAL_CALL_PRIMITIVE: u16 = 117;
+
+#[derive(Debug, Default, Clone)]
+struct PrimitiveCounters {
+    invocations: u64,
+    failures: u64,
+    total_time: Duration,
+    max_time: Duration,
+}
+
+impl PrimitiveCounters {
+    fn record(&mut self, elapsed: Duration, failed: bool) {
+        self.invocations += 1;
+        if failed {
+            self.failures += 1;
+        }
+        self.total_time += elapsed;
+        self.max_time = self.max_time.max(elapsed);
+    }
+}
+
+/// A primitive that is being executed
+#[derive(Debug)]
+struct ActivePrimitive {
+    /// None if the method activated by the call site has no primitive
+    primitive: Option<Primitive>,
+    frame_pointer: usize,
+    start: Instant,
+    /// Set when the primitive fell through to the method body or returned quickly
+    deactivated_at: Option<Instant>,
+}
+
+/// A primitive identified by its index, or by its module and name for a named primitive
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    Numbered(u16),
+    /// The module is empty for the named primitives of the vm
+    Named {
+        module: String,
+        name: String,
+    },
+}
+
+impl Primitive {
+    /// Look up the primitive of the method that a message activates
+    /// in the class with a given index of the running image
+    pub fn lookup(class_index: u32, selector: &str) -> Option<Self> {
+        let method = lookup_method(Smalltalk::class_at_index(class_index)?, selector)?;
+        match method.primitive_index()? {
+            EXTERNAL_CALL_PRIMITIVE => {
+                Self::named_primitive_of(method).or(Some(Self::Numbered(EXTERNAL_CALL_PRIMITIVE)))
+            }
+            primitive_index => Some(Self::Numbered(primitive_index)),
+        }
+    }
+
+    /// The first literal of a method with a named primitive is an Array of the module name
+    /// (nil for the primitives of the vm), the primitive name, and the vm's own bookkeeping
+    fn named_primitive_of(method: CompiledMethodRef) -> Option<Self> {
+        let description = ArrayRef::try_from(method.literal_at(0)?).ok()?;
+        let name_at = |index| {
+            description
+                .get(index)
+                .and_then(|name| SmalltalkString::try_from(name).ok())
+                .map(|name| name.to_string())
+        };
+        Some(Self::Named {
+            module: name_at(0).unwrap_or_default(),
+            name: name_at(1)?,
+        })
+    }
+}
+
+/// The statistics of a primitive as they are passed to the image:
+/// an Array of the primitive index or the module and name of a named primitive,
+/// amount of invocations and failures, total and longest time in microseconds
+#[derive(Debug, Clone, PartialEq, ToSmalltalk)]
+pub struct PrimitiveStatisticsReport {
+    pub primitive_index: Option<u16>,
+    pub module_name: Option<String>,
+    pub primitive_name: Option<String>,
+    pub invocations: u64,
+    pub failures: u64,
+    pub total_microseconds: u64,
+    pub max_microseconds: u64,
+}
+
+/// Counts how many times each primitive is invoked and fails, and how long it takes.
+///
+/// The primitive of a call site is looked up once, when the call site first activates a primitive,
+/// so the invocations of a primitive are counted together whatever the receiver and selector.
+/// A primitive succeeds when it returns to the frame of the sender.
+/// When it fails, the method body is executed instead, unless the method is a quick one
+/// that returns right away. A primitive that may call back into Smalltalk, such as `perform:`,
+/// is accounted until it calls a method.
+/// Both the interpreter and the jit emit primitive activation signals
+/// when the vm is built with the `telemetry` feature
+#[derive(Debug)]
+pub struct PrimitiveStatistics {
+    id: usize,
+    selectors: SelectorTable,
+    primitive_of: fn(u32, &str) -> Option<Primitive>,
+    primitives: HashMap<CallSite, Option<Primitive>>,
+    counters: HashMap<Primitive, PrimitiveCounters>,
+    last_send: Option<(CallSite, usize)>,
+    active_primitives: Vec<ActivePrimitive>,
+}
+
+impl PrimitiveStatistics {
+    pub fn new() -> Self {
+        Self::with_primitive_lookup(Primitive::lookup)
+    }
+
+    /// Create statistics that find the primitive of a call site with a given function
+    pub fn with_primitive_lookup(primitive_of: fn(u32, &str) -> Option<Primitive>) -> Self {
+        Self {
+            id: 0,
+            selectors: SelectorTable::default(),
+            primitive_of,
+            primitives: HashMap::new(),
+            counters: HashMap::new(),
+            last_send: None,
+            active_primitives: vec![],
+        }
+    }
+
+    /// Forget the counted invocations and the primitives of the call sites,
+    /// the primitives being executed are still counted
+    pub fn reset(&mut self) {
+        self.primitives.clear();
+        self.counters.clear();
+    }
+
+    fn primitive_at(&mut self, call_site: CallSite) -> Option<Primitive> {
+        let primitive_of = self.primitive_of;
+        let selectors = &self.selectors;
+        self.primitives
+            .entry(call_site)
+            .or_insert_with(|| {
+                primitive_of(
+                    call_site.class_index,
+                    selectors.selector_at(call_site.selector),
+                )
+            })
+            .clone()
+    }
+
+    fn complete_primitive(&mut self, timestamp: Instant, failed: bool) {
+        if let Some(active) = self.active_primitives.pop() {
+            if let Some(primitive) = active.primitive {
+                let end = active.deactivated_at.unwrap_or(timestamp);
+                self.counters
+                    .entry(primitive)
+                    .or_default()
+                    .record(end.saturating_duration_since(active.start), failed);
+            }
+        }
+    }
+
+    /// A deactivated primitive that is not immediately followed by a return has failed
+    fn complete_failed_primitive(&mut self, timestamp: Instant) {
+        if self
+            .active_primitives
+            .last()
+            .is_some_and(|primitive| primitive.deactivated_at.is_some())
+        {
+            self.complete_primitive(timestamp, true);
+        }
+    }
+
+    fn receive_send_signal(&mut self, signal: &SendSignal) {
+        self.complete_failed_primitive(signal.timestamp);
+        let call_site = CallSite {
+            class_index: signal.class_index,
+            selector: self.selectors.index_of(signal.selector),
+        };
+        self.last_send = Some((call_site, signal.frame_pointer));
+    }
+
+    fn receive_return_signal(&mut self, signal: &ReturnSignal) {
+        let returns_from_primitive = self
+            .active_primitives
+            .last()
+            .is_some_and(|primitive| primitive.frame_pointer == signal.frame_pointer);
+
+        if returns_from_primitive {
+            self.complete_primitive(signal.timestamp, false);
+        } else {
+            self.complete_failed_primitive(signal.timestamp);
+        }
+    }
+
+    fn receive_primitive_activation(
+        &mut self,
+        timestamp: Instant,
+        activation: PrimitiveActivation,
+    ) {
+        match activation {
+            PrimitiveActivation::Activate => {
+                self.complete_failed_primitive(timestamp);
+                // primitives of methods activated before the first send are not known
+                if let Some((call_site, frame_pointer)) = self.last_send.take() {
+                    let primitive = self.primitive_at(call_site);
+                    self.active_primitives.push(ActivePrimitive {
+                        primitive,
+                        frame_pointer,
+                        start: timestamp,
+                        deactivated_at: None,
+                    });
+                }
+            }
+            PrimitiveActivation::Deactivate => {
+                if let Some(primitive) = self.active_primitives.last_mut() {
+                    primitive.deactivated_at.get_or_insert(timestamp);
+                }
+            }
+            PrimitiveActivation::MayCallMethods => {
+                self.complete_primitive(timestamp, false);
+            }
+        }
+    }
+
+    /// A process switch happens from within a primitive, such as `wait` or `yield`,
+    /// which completes the primitives that were executing
+    fn receive_context_switch(&mut self, timestamp: Instant) {
+        self.complete_failed_primitive(timestamp);
+        while !self.active_primitives.is_empty() {
+            self.complete_primitive(timestamp, false);
+        }
+        self.last_send = None;
+    }
+
+    /// Return the statistics of every invoked primitive, the most time consuming first
+    pub fn snapshot(&self) -> Vec<PrimitiveStatisticsReport> {
+        let mut reports = self
+            .counters
+            .iter()
+            .map(|(primitive, counters)| {
+                let (primitive_index, module_name, primitive_name) = match primitive {
+                    Primitive::Numbered(index) => (Some(*index), None, None),
+                    Primitive::Named { module, name } => {
+                        (None, Some(module.clone()), Some(name.clone()))
+                    }
+                };
+
+                PrimitiveStatisticsReport {
+                    primitive_index,
+                    module_name,
+                    primitive_name,
+                    invocations: counters.invocations,
+                    failures: counters.failures,
+                    total_microseconds: counters.total_time.as_micros() as u64,
+                    max_microseconds: counters.max_time.as_micros() as u64,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        reports.sort_by(|a, b| {
+            b.total_microseconds
+                .cmp(&a.total_microseconds)
+                .then(b.invocations.cmp(&a.invocations))
+                .then_with(|| {
+                    (&a.primitive_index, &a.module_name, &a.primitive_name).cmp(&(
+                        &b.primitive_index,
+                        &b.module_name,
+                        &b.primitive_name,
+                    ))
+                })
+        });
+        reports
+    }
+}
+
+impl Default for PrimitiveStatistics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AbstractTelemetry for PrimitiveStatistics {
+    fn receive_signal(&mut self, signal: &TelemetrySignal) {
+        match signal {
+            TelemetrySignal::ContextSwitch(signal) => {
+                self.receive_context_switch(signal.timestamp);
+            }
+            TelemetrySignal::Send(signal) => {
+                self.receive_send_signal(signal);
+            }
+            TelemetrySignal::Return(signal) => {
+                self.receive_return_signal(signal);
+            }
+            TelemetrySignal::PrimitiveActivation(signal) => {
+                self.receive_primitive_activation(signal.timestamp, signal.activation);
+            }
+            TelemetrySignal::SemaphoreWait(_) | TelemetrySignal::MachineMethod(_) => {}
+        }
+    }
+
+    fn assign_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    fn receives_send_signals(&self) -> bool {
+        true
+    }
+
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn with_primitive_statistics<R>(f: impl FnOnce(&mut PrimitiveStatistics) -> R) -> Option<R> {
+    let id = Smalltalk::stack_integer_value(StackOffset::new(0)) as usize;
+    let result = GlobalTelemetry::with_telemetry(id, f);
+    if result.is_none() {
+        error!("There are no primitive statistics with id {}", id);
+    }
+    result
+}
+
+/// Start counting the invocations of primitives and answer the id of the statistics
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveStartPrimitiveStatistics() {
+    if !check_emits_send_signals("primitive statistics") {
+        return Smalltalk::primitive_fail();
+    }
+
+    let id = GlobalTelemetry::register(PrimitiveStatistics::new());
+    Smalltalk::method_return(&id);
+}
+
+/// Stop counting and answer the final snapshot
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveStopPrimitiveStatistics() {
+    let id = Smalltalk::stack_integer_value(StackOffset::new(0)) as usize;
+    match with_primitive_statistics(|statistics| statistics.snapshot()) {
+        Some(snapshot) => {
+            GlobalTelemetry::unregister(id);
+            Smalltalk::method_return(&snapshot);
+        }
+        None => Smalltalk::primitive_fail(),
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveResetPrimitiveStatistics() {
+    match with_primitive_statistics(|statistics| statistics.reset()) {
+        Some(_) => Smalltalk::method_return(&true),
+        None => Smalltalk::primitive_fail(),
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveSnapshotPrimitiveStatistics() {
+    match with_primitive_statistics(|statistics| statistics.snapshot()) {
+        Some(snapshot) => Smalltalk::method_return(&snapshot),
+        None => Smalltalk::primitive_fail(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{
+        byte_symbol_class, new_method, set_methods, with_test_heap, ALTERNATE_BYTECODE_SET,
+    };
+    use crate::telemetry::Signals;
+    use vm_object_model::{AnyObjectRef, ClassRef, Immediate, ObjectFormat};
+
+    fn primitive_of(_class_index: u32, selector: &str) -> Option<Primitive> {
+        match selector {
+            "at:" | "basicAt:" => Some(Primitive::Numbered(60)),
+            "size" => Some(Primitive::Numbered(62)),
+            "perform:" => Some(Primitive::Numbered(83)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn count_successes_and_failures() {
+        with_test_heap(|heap| {
+            let signals = Signals::new(heap);
+            let mut statistics = PrimitiveStatistics::with_primitive_lookup(primitive_of);
+
+            for signal in [
+                // succeeds
+                signals.send(0, 1, "at:", 100),
+                signals.primitive(0, PrimitiveActivation::Activate),
+                signals.return_to(2, 100),
+                // fails and runs the method body that sends another message
+                signals.send(3, 5, "basicAt:", 100),
+                signals.primitive(3, PrimitiveActivation::Activate),
+                signals.primitive(4, PrimitiveActivation::Deactivate),
+                signals.send(5, 2, "errorSubscriptBounds:", 200),
+                signals.return_to(6, 200),
+                signals.return_to(7, 100),
+                // a quick method returns right after its deactivation
+                signals.send(8, 3, "size", 100),
+                signals.primitive(8, PrimitiveActivation::Activate),
+                signals.primitive(8, PrimitiveActivation::Deactivate),
+                signals.return_to(8, 100),
+                // calls a method and is accounted until then
+                signals.send(10, 4, "perform:", 100),
+                signals.primitive(10, PrimitiveActivation::Activate),
+                signals.primitive(11, PrimitiveActivation::MayCallMethods),
+                signals.send(11, 3, "size", 300),
+                signals.return_to(15, 100),
+                // the interpreter deactivates a primitive that succeeds before returning
+                signals.send(16, 1, "at:", 100),
+                signals.primitive(16, PrimitiveActivation::Activate),
+                signals.primitive(17, PrimitiveActivation::Deactivate),
+                signals.return_to(17, 100),
+            ] {
+                statistics.receive_signal(&signal);
+            }
+
+            let snapshot = statistics.snapshot();
+            let counts = snapshot
+                .iter()
+                .map(|report| {
+                    (
+                        report.primitive_index,
+                        report.invocations,
+                        report.failures,
+                        report.total_microseconds,
+                        report.max_microseconds,
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                counts,
+                vec![
+                    (Some(60), 3, 1, 4_000, 2_000),
+                    (Some(83), 1, 0, 1_000, 1_000),
+                    (Some(62), 1, 0, 0, 0),
+                ]
+            );
+            assert!(snapshot
+                .iter()
+                .all(|report| report.primitive_name.is_none()));
+
+            statistics.reset();
+            assert!(statistics.snapshot().is_empty());
+        })
+    }
+
+    #[test]
+    fn lookup_named_primitives() {
+        with_test_heap(|heap| {
+            let symbol_class = byte_symbol_class(heap);
+            let class = heap.new_named_class("SocketStream", None, ObjectFormat::NonIndexable, &[]);
+
+            let description = heap.new_array(&[
+                heap.new_bytes(symbol_class, b"SocketPlugin").into(),
+                heap.new_bytes(symbol_class, b"primitiveSocketSendData")
+                    .into(),
+                Immediate::new_i64(0).into(),
+            ]);
+            // <primitive: 'primitiveSocketSendData' module: 'SocketPlugin'> followed by an empty trailer
+            let send = new_method(
+                heap,
+                ALTERNATE_BYTECODE_SET | 1 | 1 << 16,
+                &[description.into()],
+                &[248, 117, 0, 0],
+            );
+            set_methods(heap, class, &[("send:", send)]);
+
+            let class_index = ClassRef::try_from(AnyObjectRef::from(class))
+                .unwrap()
+                .class_index();
+            assert_eq!(
+                Primitive::lookup(class_index, "send:"),
+                Some(Primitive::Named {
+                    module: "SocketPlugin".to_string(),
+                    name: "primitiveSocketSendData".to_string(),
+                })
+            );
+            assert_eq!(Primitive::lookup(class_index, "receive"), None);
+        })
+    }
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use vm_object_model::{AnyObjectRef, ByteStringRef};
+
+/// Selectors interned by their characters.
+/// Symbols move during garbage collection, so their addresses can not identify them
+#[derive(Debug, Default)]
+pub struct SelectorTable {
+    selectors: Vec<String>,
+    indices: HashMap<Vec<u8>, usize>,
+}
+
+impl SelectorTable {
+    const UNKNOWN_SELECTOR: &'static [u8] = b"<unknown>";
+
+    /// Return the index of a selector, adding it to the table if needed.
+    /// Objects that are not byte symbols are interned as `<unknown>`
+    pub fn index_of(&mut self, selector: AnyObjectRef) -> usize {
+        let symbol = ByteStringRef::try_from(selector).ok();
+        let bytes = symbol
+            .as_ref()
+            .map_or(Self::UNKNOWN_SELECTOR, |symbol| symbol.as_bytes());
+
+        if let Some(index) = self.indices.get(bytes) {
+            return *index;
+        }
+
+        let index = self.selectors.len();
+        // symbols are Latin-1
+        self.selectors
+            .push(bytes.iter().map(|byte| *byte as char).collect());
+        self.indices.insert(bytes.to_vec(), index);
+        index
+    }
+
+    pub fn selector_at(&self, index: usize) -> &str {
+        self.selectors[index].as_str()
+    }
+}
@@ -1,8 +1,10 @@
 use crate::vm;
 use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
+use std::any::Any;
 use std::collections::HashMap;
 use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 use vm_bindings::bindings::{sqInt, InterpreterTelemetry};
 use vm_bindings::{Smalltalk, StackOffset};
@@ -10,6 +12,11 @@ use vm_object_model::{AnyObjectRef, ObjectRef, RawObjectPointer};
 
 static TELEMETRY_INSTANCE: OnceCell<Mutex<GlobalTelemetry>> = OnceCell::new();
 
+/// The interpreter calls every hook of the installed telemetry without checking for null,
+/// so all hooks are installed and the ones that no registered telemetry needs return right away
+static RECEIVES_SEND_SIGNALS: AtomicBool = AtomicBool::new(false);
+static RECEIVES_MACHINE_METHOD_SIGNALS: AtomicBool = AtomicBool::new(false);
+
 pub struct GlobalTelemetry {
     telemetries: HashMap<usize, Box<dyn AbstractTelemetry>>,
     next_instance_id: usize,
@@ -23,36 +30,72 @@ impl GlobalTelemetry {
         }
     }
 
-    pub fn register(telemetry: impl AbstractTelemetry + 'static) {
+    /// Register a telemetry and return its id
+    pub fn register(telemetry: impl AbstractTelemetry + 'static) -> usize {
         TELEMETRY_INSTANCE
             .get_or_init(|| {
                 let telemetry = Self::init();
                 Mutex::new(telemetry)
             })
             .lock()
-            .add_telemetry(Box::new(telemetry));
+            .add_telemetry(Box::new(telemetry))
     }
 
-    fn add_telemetry(&mut self, mut telemetry: Box<dyn AbstractTelemetry>) -> usize {
-        if self.telemetries.is_empty() {
-            let interpreter = vm().interpreter();
-            interpreter.set_telemetry(self.as_interpreter_telemetry());
-            interpreter.enable_telemetry();
+    /// Remove a registered telemetry, as `primitiveStopTelemetry` does
+    pub fn unregister(id: usize) {
+        if let Some(telemetry) = TELEMETRY_INSTANCE.get() {
+            telemetry.lock().remove_telemetry(id);
         }
+    }
 
+    /// Evaluate a block with a registered telemetry of a given type,
+    /// returning `None` if there is no such telemetry
+    pub fn with_telemetry<T: AbstractTelemetry + 'static, R>(
+        id: usize,
+        block: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        let mut global_telemetry = TELEMETRY_INSTANCE.get()?.lock();
+        let telemetry = global_telemetry.telemetries.get_mut(&id)?;
+        telemetry.any_mut().downcast_mut::<T>().map(block)
+    }
+
+    fn add_telemetry(&mut self, mut telemetry: Box<dyn AbstractTelemetry>) -> usize {
         let id = self.next_instance_id;
         telemetry.assign_id(id);
         self.telemetries.insert(id, telemetry);
         self.next_instance_id += 1;
+        self.install_interpreter_telemetry();
         id
     }
 
     pub fn remove_telemetry(&mut self, id: usize) {
         self.telemetries.remove(&id);
-        if self.telemetries.is_empty() {
-            let interpreter = vm().interpreter();
-            interpreter.disable_telemetry();
-            interpreter.take_telemetry();
+        self.install_interpreter_telemetry();
+    }
+
+    /// The signals that are received depend on the registered telemetries,
+    /// so the interpreter telemetry is replaced every time a telemetry is added or removed
+    fn install_interpreter_telemetry(&self) {
+        let interpreter = vm().interpreter();
+        interpreter.disable_telemetry();
+        interpreter.take_telemetry();
+
+        RECEIVES_SEND_SIGNALS.store(
+            self.telemetries
+                .values()
+                .any(|telemetry| telemetry.receives_send_signals()),
+            Ordering::Relaxed,
+        );
+        RECEIVES_MACHINE_METHOD_SIGNALS.store(
+            self.telemetries
+                .values()
+                .any(|telemetry| telemetry.receives_machine_method_signals()),
+            Ordering::Relaxed,
+        );
+
+        if !self.telemetries.is_empty() {
+            interpreter.set_telemetry(self.as_interpreter_telemetry());
+            interpreter.enable_telemetry();
         }
     }
 
@@ -82,6 +125,43 @@ impl GlobalTelemetry {
         }));
     }
 
+    pub fn receive_send_signal(
+        &mut self,
+        class_index: u32,
+        selector: AnyObjectRef,
+        source: SendSource,
+        frame_pointer: usize,
+    ) {
+        self.receive_signal(TelemetrySignal::Send(SendSignal {
+            timestamp: Instant::now(),
+            class_index,
+            selector,
+            source,
+            frame_pointer,
+        }));
+    }
+
+    pub fn receive_return_signal(&mut self, frame_pointer: usize) {
+        self.receive_signal(TelemetrySignal::Return(ReturnSignal {
+            timestamp: Instant::now(),
+            frame_pointer,
+        }));
+    }
+
+    pub fn receive_primitive_activation_signal(&mut self, activation: PrimitiveActivation) {
+        self.receive_signal(TelemetrySignal::PrimitiveActivation(PrimitiveActivationSignal {
+            timestamp: Instant::now(),
+            activation,
+        }));
+    }
+
+    pub fn receive_machine_method_signal(&mut self, event: MachineMethodEvent) {
+        self.receive_signal(TelemetrySignal::MachineMethod(MachineMethodSignal {
+            timestamp: Instant::now(),
+            event,
+        }));
+    }
+
     pub fn receive_signal(&mut self, signal: TelemetrySignal) {
         self.telemetries
             .values_mut()
@@ -91,14 +171,14 @@ impl GlobalTelemetry {
     pub fn as_interpreter_telemetry(&self) -> InterpreterTelemetry {
         InterpreterTelemetry {
             payload: std::ptr::null_mut(),
-            sendFn: None,
-            returnFn: None,
-            primitiveActivationFn: None,
-            activateMachineMethodFn: None,
-            beginMachineMethodFn: None,
+            sendFn: Some(telemetry_receive_send_signal),
+            returnFn: Some(telemetry_receive_return_signal),
+            primitiveActivationFn: Some(telemetry_receive_primitive_activation_signal),
+            activateMachineMethodFn: Some(telemetry_receive_activate_machine_method_signal),
+            beginMachineMethodFn: Some(telemetry_receive_begin_machine_method_signal),
             contextSwitchFn: Some(telemetry_receive_context_switch_signal),
-            debugRecordClassFn: None,
-            debugRecordSelectorFn: None,
+            debugRecordClassFn: Some(telemetry_ignore_debug_class_signal),
+            debugRecordSelectorFn: Some(telemetry_ignore_debug_selector_signal),
             semaphoreWaitFn: Some(telemetry_receive_semaphore_wait_signal),
         }
     }
@@ -107,6 +187,16 @@ impl GlobalTelemetry {
 pub trait AbstractTelemetry: Send + Sync {
     fn receive_signal(&mut self, signal: &TelemetrySignal);
     fn assign_id(&mut self, id: usize);
+    /// Return true if the telemetry needs the send, return and primitive activation signals.
+    /// They are only emitted by a vm built with the `telemetry` feature
+    fn receives_send_signals(&self) -> bool {
+        false
+    }
+    /// Return true if the telemetry needs the signals of entering machine code methods
+    fn receives_machine_method_signals(&self) -> bool {
+        false
+    }
+    fn any_mut(&mut self) -> &mut dyn Any;
 }
 
 #[derive(Debug, Clone)]
@@ -114,6 +204,10 @@ pub trait AbstractTelemetry: Send + Sync {
 pub enum TelemetrySignal {
     ContextSwitch(ContextSwitchSignal),
     SemaphoreWait(SemaphoreWaitSignal),
+    Send(SendSignal),
+    Return(ReturnSignal),
+    PrimitiveActivation(PrimitiveActivationSignal),
+    MachineMethod(MachineMethodSignal),
 }
 
 #[derive(Debug, Clone)]
@@ -131,6 +225,86 @@ pub struct SemaphoreWaitSignal {
     pub is_locked: bool,
 }
 
+/// A message is sent to an instance of a class.
+/// The frame pointer is the one of the sender, the same frame that is active once the method returns
+#[derive(Debug, Clone)]
+pub struct SendSignal {
+    pub timestamp: Instant,
+    pub class_index: u32,
+    pub selector: AnyObjectRef,
+    pub source: SendSource,
+    pub frame_pointer: usize,
+}
+
+/// Where a message send happens, which tells if the sending method runs in machine code
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SendSource {
+    Interpreter,
+    MachineCode,
+    /// A send from machine code to a method that is not compiled and has to be interpreted
+    MachineCodeToInterpreter,
+}
+
+impl SendSource {
+    /// Decode the id of the vm function that emitted the send signal
+    pub fn from_source_id(source_id: u8) -> Self {
+        match source_id {
+            // linked sends, inline cache misses and super sends of machine code methods
+            1 | 2 | 6 | 7 => Self::MachineCode,
+            // a polymorphic inline cache that interprets its target method
+            3 => Self::MachineCodeToInterpreter,
+            _ => Self::Interpreter,
+        }
+    }
+}
+
+/// A method or a primitive returns to the frame with a given frame pointer
+#[derive(Debug, Clone)]
+pub struct ReturnSignal {
+    pub timestamp: Instant,
+    pub frame_pointer: usize,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PrimitiveActivation {
+    Deactivate,
+    Activate,
+    MayCallMethods,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrimitiveActivationSignal {
+    pub timestamp: Instant,
+    pub activation: PrimitiveActivation,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MachineMethodEvent {
+    /// The interpreter is about to activate a method compiled to machine code
+    Activate,
+    /// The body of a method compiled to machine code starts executing
+    Begin,
+}
+
+#[derive(Debug, Clone)]
+pub struct MachineMethodSignal {
+    pub timestamp: Instant,
+    pub event: MachineMethodEvent,
+}
+
+/// Return true if the vm emits the send signals that a telemetry needs,
+/// otherwise log why the telemetry can not be started
+pub(crate) fn check_emits_send_signals(telemetry_name: &str) -> bool {
+    let emits_send_signals = vm_bindings::emits_send_signals();
+    if !emits_send_signals {
+        error!(
+            "The {} needs a vm built with the `telemetry` feature",
+            telemetry_name
+        );
+    }
+    emits_send_signals
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub fn primitiveStopTelemetry() {
@@ -206,3 +380,219 @@ pub unsafe extern "C" fn telemetry_receive_semaphore_wait_signal(
             .receive_semaphore_wait_signal(semaphore, process, is_locked != 0);
     }
 }
+
+/// For immediate receivers the vm passes the receiver itself instead of its class tag
+#[no_mangle]
+pub unsafe extern "C" fn telemetry_receive_send_signal(
+    _nothing: *mut c_void,
+    class_tag_or_receiver: sqInt,
+    selector: sqInt,
+    is_immediate: u8,
+    source_id: u8,
+    frame_pointer: *mut c_void,
+) {
+    if !RECEIVES_SEND_SIGNALS.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(telemetry) = TELEMETRY_INSTANCE.get() {
+        let class_index = if is_immediate != 0 {
+            let receiver = AnyObjectRef::from(RawObjectPointer::new(class_tag_or_receiver));
+            match receiver.as_immediate() {
+                Ok(immediate) => immediate.class_index(),
+                Err(error) => {
+                    error!("Failed to get immediate receiver: {:?}", error);
+                    return;
+                }
+            }
+        } else {
+            class_tag_or_receiver as u32
+        };
+        let selector = AnyObjectRef::from(RawObjectPointer::new(selector));
+
+        telemetry.lock().receive_send_signal(
+            class_index,
+            selector,
+            SendSource::from_source_id(source_id),
+            frame_pointer as usize,
+        );
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn telemetry_receive_return_signal(
+    _nothing: *mut c_void,
+    _source_id: u8,
+    _execution_location: u8,
+    frame_pointer: *mut c_void,
+) {
+    if !RECEIVES_SEND_SIGNALS.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(telemetry) = TELEMETRY_INSTANCE.get() {
+        telemetry.lock().receive_return_signal(frame_pointer as usize);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn telemetry_receive_primitive_activation_signal(
+    _nothing: *mut c_void,
+    activation: u8,
+) {
+    if !RECEIVES_SEND_SIGNALS.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(telemetry) = TELEMETRY_INSTANCE.get() {
+        let activation = match activation {
+            0 => PrimitiveActivation::Deactivate,
+            1 => PrimitiveActivation::Activate,
+            _ => PrimitiveActivation::MayCallMethods,
+        };
+        telemetry
+            .lock()
+            .receive_primitive_activation_signal(activation);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn telemetry_receive_activate_machine_method_signal(_nothing: *mut c_void) {
+    if !RECEIVES_MACHINE_METHOD_SIGNALS.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(telemetry) = TELEMETRY_INSTANCE.get() {
+        telemetry
+            .lock()
+            .receive_machine_method_signal(MachineMethodEvent::Activate);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn telemetry_receive_begin_machine_method_signal(_nothing: *mut c_void) {
+    if !RECEIVES_MACHINE_METHOD_SIGNALS.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(telemetry) = TELEMETRY_INSTANCE.get() {
+        telemetry
+            .lock()
+            .receive_machine_method_signal(MachineMethodEvent::Begin);
+    }
+}
+
+/// The debug signals are only used while developing the vm
+#[no_mangle]
+pub extern "C" fn telemetry_ignore_debug_class_signal(
+    _nothing: *mut c_void,
+    _class_tag_or_receiver: sqInt,
+    _is_immediate: u8,
+) {
+}
+
+#[no_mangle]
+pub extern "C" fn telemetry_ignore_debug_selector_signal(_nothing: *mut c_void, _selector: sqInt) {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::objects::byte_symbol_class;
+    use std::time::Duration;
+    use vm_object_model::TestHeap;
+
+    /// Creates the signals received by telemetries, timestamped in milliseconds since its creation
+    pub(crate) struct Signals<'heap> {
+        heap: &'heap TestHeap,
+        symbol_class: ObjectRef,
+        start: Instant,
+    }
+
+    impl<'heap> Signals<'heap> {
+        pub(crate) fn new(heap: &'heap TestHeap) -> Self {
+            Self {
+                heap,
+                symbol_class: byte_symbol_class(heap),
+                start: Instant::now(),
+            }
+        }
+
+        pub(crate) fn at(&self, milliseconds: u64) -> Instant {
+            self.start + Duration::from_millis(milliseconds)
+        }
+
+        /// A send from the interpreter
+        pub(crate) fn send(
+            &self,
+            milliseconds: u64,
+            class_index: u32,
+            selector: &str,
+            fp: usize,
+        ) -> TelemetrySignal {
+            self.send_from(
+                SendSource::Interpreter,
+                milliseconds,
+                class_index,
+                selector,
+                fp,
+            )
+        }
+
+        pub(crate) fn send_from(
+            &self,
+            source: SendSource,
+            milliseconds: u64,
+            class_index: u32,
+            selector: &str,
+            fp: usize,
+        ) -> TelemetrySignal {
+            TelemetrySignal::Send(SendSignal {
+                timestamp: self.at(milliseconds),
+                class_index,
+                selector: self
+                    .heap
+                    .new_bytes(self.symbol_class, selector.as_bytes())
+                    .into(),
+                source,
+                frame_pointer: fp,
+            })
+        }
+
+        pub(crate) fn return_to(&self, milliseconds: u64, fp: usize) -> TelemetrySignal {
+            TelemetrySignal::Return(ReturnSignal {
+                timestamp: self.at(milliseconds),
+                frame_pointer: fp,
+            })
+        }
+
+        pub(crate) fn switch(
+            &self,
+            milliseconds: u64,
+            old: ObjectRef,
+            new: ObjectRef,
+        ) -> TelemetrySignal {
+            TelemetrySignal::ContextSwitch(ContextSwitchSignal {
+                timestamp: self.at(milliseconds),
+                old_process: old,
+                new_process: new,
+            })
+        }
+
+        pub(crate) fn primitive(
+            &self,
+            milliseconds: u64,
+            activation: PrimitiveActivation,
+        ) -> TelemetrySignal {
+            TelemetrySignal::PrimitiveActivation(PrimitiveActivationSignal {
+                timestamp: self.at(milliseconds),
+                activation,
+            })
+        }
+
+        pub(crate) fn machine_method(
+            &self,
+            milliseconds: u64,
+            event: MachineMethodEvent,
+        ) -> TelemetrySignal {
+            TelemetrySignal::MachineMethod(MachineMethodSignal {
+                timestamp: self.at(milliseconds),
+                event,
+            })
+        }
+    }
+}
@@ -1,21 +1,29 @@
 use std::cell::RefCell;
 use std::ffi::{c_int, CString};
 use std::mem::transmute;
-use std::ops::Deref;
 use std::os::raw::c_void;
 use std::process::exit;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::thread::JoinHandle;
 
-use crate::objects::{Array, ArrayRef};
+use crate::objects::{
+    primitiveAllProcessesStackDescription, primitiveLogPrintString, primitivePrintString,
+    primitiveProcessStackDescription, print_string, scan_for_identical_key, ArrayRef,
+    DEFAULT_PRINT_DEPTH, DEFAULT_PRINT_WIDTH,
+};
 #[cfg(feature = "pharo-compiler")]
 use crate::pharo_compiler::*;
 use crate::version::{app_info, app_version};
 use crate::{
-    log_signal, primitiveEnableLogSignal, primitiveGetEnabledLogSignals, primitivePollLogger,
-    primitiveStartBeacon, primitiveStartConsoleLogger, primitiveStartGlobalProcessSwitchTelemetry,
-    primitiveStartLocalProcessSwitchTelemetry, primitiveStopLogger, primitiveStopTelemetry,
+    log_signal, primitiveCallTreeProfilerFoldedStacks, primitiveCallTreeProfilerReport,
+    primitiveDisassembleMethod, primitiveEnableLogSignal, primitiveGetEnabledLogSignals,
+    primitiveJitTelemetryReport, primitiveParseJson, primitivePollLogger,
+    primitiveResetPrimitiveStatistics, primitiveSnapshotPrimitiveStatistics, primitiveStartBeacon,
+    primitiveStartCallTreeProfiler, primitiveStartConsoleLogger,
+    primitiveStartGlobalProcessSwitchTelemetry, primitiveStartJitTelemetry,
+    primitiveStartLocalProcessSwitchTelemetry, primitiveStartPrimitiveStatistics,
+    primitiveStopLogger, primitiveStopPrimitiveStatistics, primitiveStopTelemetry,
     should_log_all_signals, should_log_signal, ConsoleLogger, EventLoop, EventLoopMessage,
     EventLoopWaker, VM_LOGGER,
 };
@@ -24,10 +32,9 @@ use crate::{primitiveEventLoopCallout, primitiveExtractReturnValue};
 use anyhow::Result;
 use vm_bindings::{
     virtual_machine_info, InterpreterConfiguration, InterpreterProxy, LogLevel, NamedPrimitive,
-    ObjectFieldIndex, ObjectPointer, PharoInterpreter, Smalltalk, StackOffset,
+    ObjectPointer, PharoInterpreter, Smalltalk, StackOffset,
 };
-use vm_object_model::{AnyObjectRef, Error, RawObjectPointer};
-use widestring::U32Str;
+use vm_object_model::{AnyObjectRef, Error, RawObjectPointer, WideStringRef};
 
 #[no_mangle]
 pub static mut VIRTUAL_MACHINE: Option<Arc<VirtualMachine>> = None;
@@ -119,14 +126,30 @@ impl VirtualMachine {
         vm.add_primitive(primitive!(primitiveWideStringByteIndexToCharIndex));
         vm.add_primitive(primitive!(primitiveIdentityDictionaryScanFor));
         vm.add_primitive(primitive!(primitiveIdentityHash));
+        vm.add_primitive(primitive!(primitiveParseJson));
 
         // telemetry
         vm.add_primitive(primitive!(primitiveStartLocalProcessSwitchTelemetry));
         vm.add_primitive(primitive!(primitiveStartGlobalProcessSwitchTelemetry));
         vm.add_primitive(primitive!(primitiveStopTelemetry));
+        vm.add_primitive(primitive!(primitiveStartCallTreeProfiler));
+        vm.add_primitive(primitive!(primitiveCallTreeProfilerReport));
+        vm.add_primitive(primitive!(primitiveCallTreeProfilerFoldedStacks));
+        vm.add_primitive(primitive!(primitiveStartPrimitiveStatistics));
+        vm.add_primitive(primitive!(primitiveStopPrimitiveStatistics));
+        vm.add_primitive(primitive!(primitiveResetPrimitiveStatistics));
+        vm.add_primitive(primitive!(primitiveSnapshotPrimitiveStatistics));
+        vm.add_primitive(primitive!(primitiveStartJitTelemetry));
+        vm.add_primitive(primitive!(primitiveJitTelemetryReport));
 
         // debug
         vm.add_primitive(primitive!(primitiveDebugPrintArray));
+        vm.add_primitive(primitive!(primitiveVerifyHeap));
+        vm.add_primitive(primitive!(primitivePrintString));
+        vm.add_primitive(primitive!(primitiveLogPrintString));
+        vm.add_primitive(primitive!(primitiveDisassembleMethod));
+        vm.add_primitive(primitive!(primitiveProcessStackDescription));
+        vm.add_primitive(primitive!(primitiveAllProcessesStackDescription));
 
         #[cfg(feature = "pharo-compiler")]
         {
@@ -209,42 +232,19 @@ pub fn is_virtual_machine() {
 #[no_mangle]
 #[allow(non_snake_case)]
 pub fn primitiveGetNamedPrimitives() {
-    let proxy = vm().proxy();
-    let named_primitives = vm().named_primitives();
-
-    let return_array = Smalltalk::primitive_instantiate_indexable_class_of_size(
-        Smalltalk::primitive_class_array(),
-        named_primitives.len(),
-    );
-    for (index, named_primitive) in named_primitives.iter().enumerate() {
-        let each_primitive_array = Smalltalk::primitive_instantiate_indexable_class_of_size(
-            Smalltalk::primitive_class_array(),
-            3,
-        );
-
-        let plugin_name = proxy.new_string(named_primitive.plugin_name());
-        let primitive_name = proxy.new_string(named_primitive.primitive_name());
-        let primitive_address = proxy.new_external_address(named_primitive.primitive_address());
-
-        Smalltalk::item_at_put(each_primitive_array, ObjectFieldIndex::new(1), plugin_name);
-        Smalltalk::item_at_put(
-            each_primitive_array,
-            ObjectFieldIndex::new(2),
-            primitive_name,
-        );
-        Smalltalk::item_at_put(
-            each_primitive_array,
-            ObjectFieldIndex::new(3),
-            primitive_address,
-        );
-
-        Smalltalk::item_at_put(
-            return_array,
-            ObjectFieldIndex::new(index + 1),
-            each_primitive_array,
-        );
-    }
-    Smalltalk::method_return_value(return_array);
+    let named_primitives = vm()
+        .named_primitives()
+        .iter()
+        .map(|named_primitive| {
+            (
+                named_primitive.plugin_name(),
+                named_primitive.primitive_name(),
+                named_primitive.primitive_address(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Smalltalk::method_return(&named_primitives);
 }
 
 #[no_mangle]
@@ -449,15 +449,19 @@ pub fn primitiveWideStringByteIndexToCharIndex() {
 
     let byte_offset = Smalltalk::stack_integer_value(StackOffset::new(0)) as usize;
 
-    let wide_string = Smalltalk::stack_object_value_unchecked(StackOffset::new(1));
-    let wide_string_size = Smalltalk::size_of(wide_string);
-
-    let wide_string_ptr = Smalltalk::first_indexable_field(wide_string) as *const u32;
+    let wide_string = match WideStringRef::try_from(Smalltalk::stack_ref(StackOffset::new(1))) {
+        Ok(wide_string) => wide_string,
+        Err(error) => {
+            error!("{}", error);
+            Smalltalk::primitive_fail();
+            return;
+        }
+    };
+    let wide_string_size = wide_string.len();
 
-    let u32_str = unsafe { U32Str::from_ptr(wide_string_ptr, wide_string_size) };
     let mut char_offset = 1usize;
     let mut current_byte_offset = 0usize;
-    for each in u32_str.chars_lossy() {
+    for each in wide_string.chars_lossy() {
         current_byte_offset += each.len_utf8();
         if current_byte_offset > byte_offset {
             Smalltalk::method_return_integer(char_offset as i64);
@@ -488,55 +492,34 @@ pub fn primitiveIdentityDictionaryScanFor() {
         return;
     }
 
-    let hash = Smalltalk::stack_integer_value(StackOffset::new(0)) as u32;
-    let object = Smalltalk::stack_object_value_unchecked(StackOffset::new(1));
-    let dictionary = Smalltalk::stack_object_value_unchecked(StackOffset::new(2));
-
-    let array = Smalltalk::object_field_at(dictionary, 1usize.into());
-
-    let finish = Smalltalk::size_of(array) as u32;
-    let start = hash.rem_euclid(finish) + 1;
-
-    fn find_item_or_empty_slot(
-        start: u32,
-        finish: u32,
-        array: ObjectPointer,
-        object: ObjectPointer,
-    ) -> Option<u32> {
-        let nil_object = Smalltalk::nil_object();
-
-        // Search from (hash mod size) to the end.
-        for index in start..(finish + 1) {
-            let association = Smalltalk::item_at(array, index.into());
-            if Smalltalk::is_identical(association, nil_object)? {
-                return Some(index);
-            }
-
-            let key = Smalltalk::object_field_at(association, 0usize.into());
-            if Smalltalk::is_identical(key, object)? {
-                return Some(index);
-            }
-        }
-
-        // Search from 1 to where we started.
-        for index in 1..start {
-            let association = Smalltalk::item_at(array, index.into());
-            if Smalltalk::is_identical(association, nil_object)? {
-                return Some(index);
-            }
-
-            let key = Smalltalk::object_field_at(association, 0usize.into());
-            if Smalltalk::is_identical(key, object)? {
-                return Some(index);
-            }
+    let hash = Smalltalk::stack_integer_value(StackOffset::new(0));
+    let object = Smalltalk::stack_ref(StackOffset::new(1));
+    let dictionary = Smalltalk::stack_ref(StackOffset::new(2));
+
+    let array = match dictionary.as_object().and_then(|dictionary| {
+        dictionary
+            .inst_var_at(1)
+            .ok_or_else(|| Error::InvalidType("IdentityDictionary".to_string()))
+            .and_then(ArrayRef::try_from)
+    }) {
+        Ok(array) => array,
+        Err(error) => {
+            error!("{}", error);
+            Smalltalk::primitive_fail();
+            return;
         }
+    };
 
-        Some(0)
+    if array.len() == 0 {
+        Smalltalk::method_return_integer(0);
+        return;
     }
 
-    match find_item_or_empty_slot(start, finish, array, object) {
-        None => Smalltalk::primitive_fail_code(1),
-        Some(index) => Smalltalk::method_return_integer(index as i64),
+    // answer a one-based index, or 0 if there is no empty slot
+    let start = hash.rem_euclid(array.len() as i64) as usize;
+    match scan_for_identical_key(&array, start, object) {
+        None => Smalltalk::method_return_integer(0),
+        Some(index) => Smalltalk::method_return_integer(index as i64 + 1),
     }
 }
 
@@ -627,25 +610,30 @@ pub fn primitiveIdentityDictionaryScanFor() {
 pub fn primitiveDebugPrintArray() {
     let value_ptr = Smalltalk::stack_ref(StackOffset::new(0));
 
-    let array: &Array = &ArrayRef::try_from(value_ptr).unwrap();
-
-    for each in array.iter() {
-        if each.is_immediate() {
-            println!("{:?}", each.as_immediate().unwrap());
-        } else {
-            println!("{:?}", each.as_object().unwrap().deref());
+    match ArrayRef::try_from(value_ptr) {
+        Ok(array) => {
+            for each in array.iter() {
+                info!(
+                    "{}",
+                    print_string(*each, DEFAULT_PRINT_DEPTH, DEFAULT_PRINT_WIDTH)
+                );
+            }
+            Smalltalk::method_return_boolean(true);
+        }
+        Err(error) => {
+            error!("{}", error);
+            Smalltalk::primitive_fail();
         }
     }
+}
 
-    // match ArrayRef::try_from(value_ptr)  {
-    //     Ok(array_ref) => {
-    //         println!("{:?}",&array_ref);
-    //         Smalltalk::method_return_boolean(true);
-    //     }
-    //     Err(error) => {
-    //         error!("{}", error);
-    //         Smalltalk::primitive_fail();
-    //     }
-    // }
-    Smalltalk::method_return_boolean(true);
+/// Walk the heap and return a report of any corruption found as a String
+#[no_mangle]
+#[allow(non_snake_case)]
+pub fn primitiveVerifyHeap() {
+    let report = Smalltalk::verify_heap();
+    if !report.is_ok() {
+        error!("{}", report);
+    }
+    Smalltalk::method_return(&report.to_string());
 }